@@ -0,0 +1,90 @@
+//actors.rs lets a fragment be performed by a company of threads, one per Player. A stage manager thread cues one actor at a time through a
+//mutex and condvar, and the cued actor delivers its lines up to the cued line number and hands the stage back, so only one thread is ever
+//speaking and the recital comes out exactly as SceneFragment::recite gives it. Hanson Li, Johnny Huang, Aman Verma
+
+use super::cast::Cast;
+use super::declarations::GENERATION_FAILURE;
+use super::excerpt::Excerpt;
+use super::line_num::LineNum;
+use super::player::Player;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+
+pub const ACTORS_FLAG: &str = "actors"; //recite --actors
+
+//what the stage manager and the actors share, only ever read or changed under Stage::cue
+#[derive(Debug, Default)]
+pub struct Cue {
+    pub actor_idx: Option<usize>,    //actor whose turn it is, None while the stage manager has the stage
+    pub up_to: LineNum,              //the cued actor delivers its lines up to and including this one
+    pub curtain: bool,               //set once the fragment is over, every actor thread then finishes
+    pub most_recent_speaker: String, //so a speaker header is only printed when the speaker changes, whoever prints next
+}
+
+#[derive(Debug, Default)]
+pub struct Stage {
+    pub cue: Mutex<Cue>,
+    pub cue_changed: Condvar, //signalled whenever an actor is cued, hands the stage back or the curtain comes down
+}
+
+//wakes everyone when an actor leaves the stage, also if it panicked, so the stage manager sees the poisoned cue rather than waiting forever
+struct LeaveStage<'a>(&'a Stage);
+
+impl Drop for LeaveStage<'_> {
+    fn drop(&mut self) {
+        self.0.cue_changed.notify_all();
+    }
+}
+
+impl Stage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //gives the stage to actor_idx until it has delivered its lines up to up_to. Takes and returns the stage manager's hold on the cue,
+    //which is let go of while the actor speaks
+    pub fn cue_actor<'a>(&'a self, mut cue: MutexGuard<'a, Cue>, actor_idx: usize, up_to: LineNum) -> Result<MutexGuard<'a, Cue>, u8> {
+        cue.actor_idx = Some(actor_idx);
+        cue.up_to = up_to;
+        self.cue_changed.notify_all();
+        self.cue_changed.wait_while(cue, |a_cue| a_cue.actor_idx.is_some()).map_err(|_| GENERATION_FAILURE)
+    }
+
+    //ends the fragment for every actor. A poisoned cue is still changed, since an actor left waiting would never finish
+    pub fn lower_curtain(&self) {
+        let mut cue = self.cue.lock().unwrap_or_else(PoisonError::into_inner);
+        cue.curtain = true;
+        self.cue_changed.notify_all();
+    }
+}
+
+//an actor thread: waits for its cue, delivers or skips its lines up to the cued line like recite does, and hands the stage back. Returns how
+//many of its lines it got through once the curtain comes down
+pub fn perform(plyr: &Player, actor_idx: usize, stage: &Stage, delivers: bool, excerpt: &Excerpt, cast: &Cast) -> usize {
+    let _leave_stage = LeaveStage(stage);
+    let mut line_idx: usize = 0;
+
+    let Ok(mut cue) = stage.cue.lock() else {
+        return line_idx;
+    };
+    loop {
+        cue = match stage.cue_changed.wait_while(cue, |a_cue| a_cue.actor_idx != Some(actor_idx) && !a_cue.curtain) {
+            Ok(cue) => cue,
+            Err(_) => return line_idx,
+        };
+        if cue.curtain {
+            return line_idx;
+        }
+
+        while let Some((line_num, _)) = plyr.char_lines.get(line_idx) {
+            if *line_num > cue.up_to {
+                break;
+            }
+            if delivers && excerpt.line_in_range(*line_num) {
+                plyr.deliver_line(line_idx, &mut cue.most_recent_speaker, cast);
+            }
+            line_idx += 1;
+        }
+        cue.actor_idx = None;
+        stage.cue_changed.notify_all();
+    }
+}
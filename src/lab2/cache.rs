@@ -0,0 +1,401 @@
+//cache.rs keeps a compact binary copy of a prepared Play next to its script, so later runs can skip reading and parsing the script, config and part files.
+//the cache records the size, modification time and content hash of every file the play was read from and is only used while none of them has changed. Johnny Huang, Aman Verma, Hanson Li
+
+use super::cast::{Cast, CastMember};
+use super::declarations::GENERATION_FAILURE;
+use super::line_num::LineNum;
+use super::play::Play;
+use super::player::Player;
+use super::scene_fragments::SceneFragment;
+use super::scene_meta::SceneMeta;
+use super::script_gen::note_file_read;
+use super::variables::{outside_value, variables_used};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+pub const CACHE_SUFFIX: &str = ".lab2cache"; //the cache of hamlet_script.txt is hamlet_script.txt.lab2cache
+pub const CACHE_MAGIC: &[u8] = b"LAB2CACHE";
+pub const CACHE_VERSION: u32 = 3; //bumped whenever the layout below changes, so older caches are rebuilt
+
+pub const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+pub const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+pub type FileStamp = (String, u64, u64, u32, u64); //file name, size, modification time in seconds and nanoseconds, content hash
+
+//64 bit FNV-1a hash of a file's contents
+pub fn content_hash(file_bytes: &[u8]) -> u64 {
+    file_bytes.iter().fold(FNV_OFFSET, |a_hash, a_byte| (a_hash ^ *a_byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+//the cache file for a script
+pub fn cache_file_name(script_fname: &str) -> String {
+    format!("{}{}", script_fname, CACHE_SUFFIX)
+}
+
+//size and modification time of a file, without reading it
+fn file_metadata(file_name: &str) -> Option<(u64, u64, u32)> {
+    let metadata = fs::metadata(file_name).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len(), modified.as_secs(), modified.subsec_nanos()))
+}
+
+fn stamp_file(file_name: &str) -> Option<FileStamp> {
+    let (file_size, mtime_secs, mtime_nanos) = file_metadata(file_name)?;
+    let file_bytes = fs::read(file_name).ok()?;
+    Some((file_name.to_string(), file_size, mtime_secs, mtime_nanos, content_hash(&file_bytes)))
+}
+
+//true if the file still has the recorded size, modification time and contents. The hash is only worked out when the cheaper checks pass
+fn stamp_matches(a_stamp: &FileStamp) -> bool {
+    let (file_name, file_size, mtime_secs, mtime_nanos, file_hash) = a_stamp;
+    match file_metadata(file_name) {
+        Some(cur_metadata) if cur_metadata == (*file_size, *mtime_secs, *mtime_nanos) => {
+            fs::read(file_name).is_ok_and(|file_bytes| content_hash(&file_bytes) == *file_hash)
+        }
+        _ => false,
+    }
+}
+
+//every file the play was read from: the script, any script files it includes, the cast file, each config file and each part file
+fn source_files(play: &Play) -> Result<Vec<String>, u8> {
+    let mut file_names: Vec<String> = vec![play.script_file().to_string()];
+    let mut add_file = |file_name: &str| {
+        if !file_name.is_empty() && !file_names.iter().any(|a_name| a_name == file_name) {
+            file_names.push(file_name.to_string());
+        }
+    };
+    for include_fname in play.includes().iter() {
+        add_file(include_fname);
+    }
+    add_file(&play.cast().cast_file);
+    for a_fragment in play.fragments().iter() {
+        let Ok(scene_ref) = a_fragment.lock() else {
+            let _ = writeln!(io::stderr().lock(), "Error: Failed to acquire lock on ith scene fragment");
+            return Err(GENERATION_FAILURE);
+        };
+        add_file(&scene_ref.config_file);
+        for a_player in scene_ref.chars_in_play.iter() {
+            add_file(&a_player.part_file);
+        }
+    }
+    Ok(file_names)
+}
+
+//appends values to the cache in little endian, strings and lists are preceded by their length
+struct CacheWriter {
+    cache_bytes: Vec<u8>,
+}
+
+impl CacheWriter {
+    fn put_u32(&mut self, number: u32) {
+        self.cache_bytes.extend_from_slice(&number.to_le_bytes());
+    }
+
+    fn put_u64(&mut self, number: u64) {
+        self.cache_bytes.extend_from_slice(&number.to_le_bytes());
+    }
+
+    fn put_len(&mut self, length: usize) {
+        self.put_u32(length as u32);
+    }
+
+    fn put_str(&mut self, text: &str) {
+        self.put_len(text.len());
+        self.cache_bytes.extend_from_slice(text.as_bytes());
+    }
+
+    fn put_option(&mut self, number: Option<usize>) {
+        match number {
+            Some(number) => {
+                self.cache_bytes.push(1);
+                self.put_u64(number as u64);
+            }
+            None => self.cache_bytes.push(0),
+        }
+    }
+
+    //how many levels the number has, then each level, so 12.1 is 2, 12, 1
+    fn put_line_num(&mut self, line_num: LineNum) {
+        self.put_len(line_num.levels().len());
+        for a_level in line_num.levels() {
+            self.put_u64(*a_level as u64);
+        }
+    }
+
+    fn put_line_option(&mut self, line_num: Option<LineNum>) {
+        match line_num {
+            Some(line_num) => {
+                self.cache_bytes.push(1);
+                self.put_line_num(line_num);
+            }
+            None => self.cache_bytes.push(0),
+        }
+    }
+}
+
+//reads values back in the order they were written. None for a cache that is cut short or damaged, which is then rebuilt
+struct CacheReader<'a> {
+    cache_bytes: &'a [u8],
+    pos: usize,
+}
+
+impl CacheReader<'_> {
+    fn take(&mut self, count: usize) -> Option<&[u8]> {
+        let some_bytes = self.cache_bytes.get(self.pos..self.pos.checked_add(count)?)?;
+        self.pos += count;
+        Some(some_bytes)
+    }
+
+    fn get_u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn get_u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn get_len(&mut self) -> Option<usize> {
+        self.get_u32().map(|length| length as usize)
+    }
+
+    fn get_str(&mut self) -> Option<String> {
+        let length = self.get_len()?;
+        String::from_utf8(self.take(length)?.to_vec()).ok()
+    }
+
+    fn get_option(&mut self) -> Option<Option<usize>> {
+        match self.take(1)?[0] {
+            0 => Some(None),
+            1 => Some(Some(self.get_u64()? as usize)),
+            _ => None,
+        }
+    }
+
+    fn get_line_num(&mut self) -> Option<LineNum> {
+        let level_count = self.get_len()?;
+        let mut levels: Vec<usize> = Vec::new();
+        for _ in 0..level_count.min(self.cache_bytes.len()) {
+            levels.push(self.get_u64()? as usize);
+        }
+        LineNum::from_levels(&levels)
+    }
+
+    fn get_line_option(&mut self) -> Option<Option<LineNum>> {
+        match self.take(1)?[0] {
+            0 => Some(None),
+            1 => Some(Some(self.get_line_num()?)),
+            _ => None,
+        }
+    }
+}
+
+fn encode_play(play: &Play, stamps: &[FileStamp]) -> Result<Vec<u8>, u8> {
+    let mut writer = CacheWriter { cache_bytes: CACHE_MAGIC.to_vec() };
+    writer.put_u32(CACHE_VERSION);
+    writer.put_len(stamps.len());
+    for (file_name, file_size, mtime_secs, mtime_nanos, file_hash) in stamps.iter() {
+        writer.put_str(file_name);
+        writer.put_u64(*file_size);
+        writer.put_u64(*mtime_secs);
+        writer.put_u32(*mtime_nanos);
+        writer.put_u64(*file_hash);
+    }
+    //the ${NAME} values the paths were filled in with, since a different value would read other files
+    let used_variables = variables_used();
+    writer.put_len(used_variables.len());
+    for (var_name, var_value, from_set) in used_variables.iter() {
+        writer.put_str(var_name);
+        writer.put_str(var_value);
+        writer.put_u32(u32::from(*from_set));
+    }
+
+    let cast = play.cast();
+    writer.put_str(&cast.cast_file);
+    writer.put_len(cast.members.len());
+    for a_member in cast.members.iter() {
+        for a_field in [&a_member.char_key, &a_member.display_name, &a_member.speech_prefix, &a_member.description] {
+            writer.put_str(a_field);
+        }
+    }
+
+    let scene_count: usize = play.acts().iter().map(|an_act| an_act.scenes.len()).sum();
+    writer.put_len(scene_count);
+    for a_scene in play.acts().iter().flat_map(|an_act| an_act.scenes.iter()) {
+        let scene_meta = &a_scene.meta;
+        writer.put_str(&scene_meta.full_title);
+        writer.put_str(&scene_meta.play_title);
+        writer.put_option(scene_meta.act);
+        writer.put_option(scene_meta.scene);
+        writer.put_str(&scene_meta.location);
+        writer.put_str(&scene_meta.author);
+        writer.put_len(a_scene.fragments.len());
+
+        for a_fragment in a_scene.fragments.iter() {
+            let Ok(scene_ref) = a_fragment.lock() else {
+                let _ = writeln!(io::stderr().lock(), "Error: Failed to acquire lock on ith scene fragment");
+                return Err(GENERATION_FAILURE);
+            };
+            writer.put_str(&scene_ref.config_file);
+            writer.put_len(scene_ref.chars_in_play.len());
+            for a_player in scene_ref.chars_in_play.iter() {
+                writer.put_str(&a_player.char_name);
+                writer.put_str(&a_player.part_file);
+                writer.put_line_option(a_player.enter_at);
+                writer.put_line_option(a_player.exit_at);
+                writer.put_len(a_player.char_lines.len());
+                for (line_num, line_text) in a_player.char_lines.iter() {
+                    writer.put_line_num(*line_num);
+                    writer.put_str(line_text);
+                }
+            }
+        }
+    }
+    Ok(writer.cache_bytes)
+}
+
+//rebuilds the play from a cache, None if the cache is damaged, was made for another script or any file it was read from has changed
+fn decode_play(cache_bytes: &[u8], script_fname: &str) -> Option<Play> {
+    let mut reader = CacheReader { cache_bytes, pos: 0 };
+    if reader.take(CACHE_MAGIC.len())? != CACHE_MAGIC || reader.get_u32()? != CACHE_VERSION {
+        return None;
+    }
+    let stamp_count = reader.get_len()?;
+    for stamp_idx in 0..stamp_count {
+        let a_stamp: FileStamp = (reader.get_str()?, reader.get_u64()?, reader.get_u64()?, reader.get_u32()?, reader.get_u64()?);
+        //the script is always the first file
+        if (stamp_idx == 0 && a_stamp.0 != script_fname) || !stamp_matches(&a_stamp) {
+            return None;
+        }
+        note_file_read(&a_stamp.0);
+    }
+    //a value from --define or the environment must be the same again, and a [set] value must not be overridden by either now.
+    //a [set] value itself only changes with the script, which is stamped above
+    let variable_count = reader.get_len()?;
+    for _ in 0..variable_count {
+        let (var_name, var_value, from_set) = (reader.get_str()?, reader.get_str()?, reader.get_u32()? == 1);
+        let now_value = outside_value(&var_name);
+        if (from_set && now_value.is_some()) || (!from_set && now_value.as_deref() != Some(var_value.as_str())) {
+            return None;
+        }
+    }
+
+    let mut cast = Cast::new();
+    cast.cast_file = reader.get_str()?;
+    let member_count = reader.get_len()?;
+    for _ in 0..member_count {
+        cast.members.push(CastMember {
+            char_key: reader.get_str()?,
+            display_name: reader.get_str()?,
+            speech_prefix: reader.get_str()?,
+            description: reader.get_str()?,
+        });
+    }
+    let mut play = Play::new();
+    play.set_provenance(script_fname, cast);
+
+    let scene_count = reader.get_len()?;
+    for _ in 0..scene_count {
+        let scene_meta = SceneMeta {
+            full_title: reader.get_str()?,
+            play_title: reader.get_str()?,
+            act: reader.get_option()?,
+            scene: reader.get_option()?,
+            location: reader.get_str()?,
+            author: reader.get_str()?,
+        };
+        let scene_title = scene_meta.full_title.to_string();
+        play.push_scene(scene_meta);
+
+        let fragment_count = reader.get_len()?;
+        for _ in 0..fragment_count {
+            let mut fragment = SceneFragment::new(&scene_title);
+            fragment.config_file = reader.get_str()?;
+            let player_count = reader.get_len()?;
+            for _ in 0..player_count {
+                let mut a_player = Player::new(&reader.get_str()?);
+                let part_file = reader.get_str()?;
+                a_player.enter_at = reader.get_line_option()?;
+                a_player.exit_at = reader.get_line_option()?;
+                let line_count = reader.get_len()?;
+                let mut char_lines = Vec::with_capacity(line_count.min(cache_bytes.len()));
+                for _ in 0..line_count {
+                    char_lines.push((reader.get_line_num()?, reader.get_str()?));
+                }
+                a_player.prepare_lines(&part_file, char_lines);
+                fragment.add_player(a_player);
+            }
+            play.push_fragment(fragment);
+        }
+    }
+    (reader.pos == cache_bytes.len()).then_some(play)
+}
+
+//the play cached for this script, if the cache is there and still up to date
+pub fn load_cached_play(script_fname: &str) -> Option<Play> {
+    let cache_bytes = fs::read(cache_file_name(script_fname)).ok()?;
+    decode_play(&cache_bytes, script_fname)
+}
+
+//writes the cache for a play just read from its script. It is written to a temporary file first so a half written cache is never read
+pub fn write_play_cache(play: &Play) -> Result<(), u8> {
+    let mut stamps: Vec<FileStamp> = Vec::new();
+    for file_name in source_files(play)? {
+        match stamp_file(&file_name) {
+            Some(a_stamp) => stamps.push(a_stamp),
+            None => return Err(GENERATION_FAILURE),
+        }
+    }
+    let cache_bytes = encode_play(play, &stamps)?;
+    let cache_fname = cache_file_name(play.script_file());
+    let temp_fname = format!("{}.tmp", cache_fname);
+    fs::write(&temp_fname, cache_bytes).and_then(|_| fs::rename(&temp_fname, &cache_fname)).map_err(|_| {
+        let _ = fs::remove_file(&temp_fname);
+        GENERATION_FAILURE
+    })
+}
+
+//removes the cache of a script, or every cache under a directory. Returns the files removed
+pub fn clean_caches(target: &str) -> Result<Vec<String>, u8> {
+    let mut stderr = io::stderr().lock();
+    let mut removed: Vec<String> = Vec::new();
+    let target_path = Path::new(target);
+
+    if !target_path.is_dir() {
+        let cache_fname = cache_file_name(target);
+        if Path::new(&cache_fname).is_file() {
+            if let Err(e_msg) = fs::remove_file(&cache_fname) {
+                let _ = writeln!(stderr, "Error: could not remove {}: {}", cache_fname, e_msg);
+                return Err(GENERATION_FAILURE);
+            }
+            removed.push(cache_fname);
+        }
+        return Ok(removed);
+    }
+
+    let mut dirs_left: Vec<std::path::PathBuf> = vec![target_path.to_path_buf()];
+    while let Some(a_dir) = dirs_left.pop() {
+        let dir_entries = match fs::read_dir(&a_dir) {
+            Ok(dir_entries) => dir_entries,
+            Err(e_msg) => {
+                let _ = writeln!(stderr, "Error: could not read directory {}: {}", a_dir.display(), e_msg);
+                return Err(GENERATION_FAILURE);
+            }
+        };
+        for an_entry in dir_entries.flatten() {
+            let entry_path = an_entry.path();
+            if entry_path.is_dir() {
+                dirs_left.push(entry_path);
+            } else if entry_path.to_string_lossy().ends_with(CACHE_SUFFIX) {
+                if let Err(e_msg) = fs::remove_file(&entry_path) {
+                    let _ = writeln!(stderr, "Error: could not remove {}: {}", entry_path.display(), e_msg);
+                    return Err(GENERATION_FAILURE);
+                }
+                removed.push(entry_path.display().to_string());
+            }
+        }
+    }
+    removed.sort();
+    Ok(removed)
+}
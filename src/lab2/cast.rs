@@ -0,0 +1,125 @@
+//cast.rs declares the Cast struct that maps the character keys used in config files to the display names, speech prefixes and descriptions read from an optional cast file. Johnny Huang, Hanson Li, Aman Verma
+
+use super::declarations::{WHINGE, GENERATION_FAILURE};
+use super::script_gen::grab_trimmed_file_lines;
+use std::sync::atomic::Ordering;
+use std::io::{self, Write};
+
+//a cast file line looks like: King | Claudius, King of Denmark | KING | the new king, Hamlet's uncle
+pub const CAST_FIELD_DELIM: char = '|';
+pub const CAST_KEY_POS: usize = 0;
+pub const CAST_DISPLAY_POS: usize = 1;
+pub const CAST_PREFIX_POS: usize = 2;
+pub const CAST_DESCRIPTION_POS: usize = 3;
+pub const CAST_MAX_FIELDS: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct CastMember {
+    pub char_key: String,     //name used in the config files, this is what continuity is checked against
+    pub display_name: String, //name printed in entrances and exits
+    pub speech_prefix: String, //short name printed above each speech
+    pub description: String,  //optional one-line description, empty if not given
+}
+
+impl CastMember {
+    //an empty display name or speech prefix falls back to the character key
+    pub fn new(char_key: &str, display_name: &str, speech_prefix: &str, description: &str) -> Self {
+        let field_or_key = |a_field: &str| -> String { if a_field.is_empty() { char_key.to_string() } else { a_field.to_string() } };
+        Self {
+            char_key: char_key.to_string(),
+            display_name: field_or_key(display_name),
+            speech_prefix: field_or_key(speech_prefix),
+            description: description.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Cast {
+    pub members: Vec<CastMember>, //kept in cast file order
+    pub cast_file: String,        //empty when no cast file was read
+}
+
+impl Cast {
+    pub fn new() -> Self {
+        Self {
+            members: Vec::new(),
+            cast_file: String::new(),
+        }
+    }
+
+    //splits one cast file line on '|' and stores it, missing fields fall back to the character key
+    pub fn add_config(&mut self, cast_line: &str) {
+        let mut stderr = io::stderr().lock();
+        let cast_items: Vec<&str> = cast_line.split(CAST_FIELD_DELIM).map(|an_item| an_item.trim()).collect();
+
+        if cast_items[CAST_KEY_POS].is_empty() {
+            if !cast_line.is_empty() && WHINGE.load(Ordering::SeqCst) {
+                let _ = writeln!(stderr,"Whinge Warning: cast line '{}' has no character key, skipping it", cast_line);
+            }
+            return;
+        }
+        if cast_items.len() > CAST_MAX_FIELDS && WHINGE.load(Ordering::SeqCst) {
+            let _ = writeln!(stderr,"Whinge Warning: cast line '{}' has more than {} fields, ignoring the extra ones", cast_line, CAST_MAX_FIELDS);
+        }
+
+        let field_text = |field_pos: usize| -> String { cast_items.get(field_pos).map(|a_field| a_field.to_string()).unwrap_or_default() };
+        self.add_member(CastMember::new(&field_text(CAST_KEY_POS), &field_text(CAST_DISPLAY_POS), &field_text(CAST_PREFIX_POS), &field_text(CAST_DESCRIPTION_POS)));
+    }
+
+    //stores a member unless its character is already in the cast
+    pub fn add_member(&mut self, a_member: CastMember) {
+        if self.find(&a_member.char_key).is_some() {
+            if WHINGE.load(Ordering::SeqCst) {
+                let _ = writeln!(io::stderr().lock(),"Whinge Warning: character '{}' is listed more than once in the cast file, keeping the first entry", a_member.char_key);
+            }
+            return;
+        }
+        self.members.push(a_member);
+    }
+
+    //reads the cast file and adds each of its lines
+    pub fn prepare(&mut self, cast_fname: &String) -> Result<(), u8> {
+        let mut stderr = io::stderr().lock();
+        let mut cast_lines: Vec<String> = Vec::new();
+        self.cast_file = cast_fname.to_string();
+
+        if let Err(e_code) = grab_trimmed_file_lines(cast_fname, &mut cast_lines) {
+            let _ = writeln!(stderr,"Error: could not read cast file '{}', error code {}", cast_fname, e_code);
+            return Err(GENERATION_FAILURE);
+        }
+
+        for a_cast_line in cast_lines.iter() {
+            self.add_config(a_cast_line);
+        }
+        Ok(())
+    }
+
+    pub fn find(&self, char_key: &str) -> Option<&CastMember> {
+        self.members.iter().find(|a_member| a_member.char_key == char_key)
+    }
+
+    //name used in entrances and exits, the key itself if the character is not in the cast file
+    pub fn display_name(&self, char_key: &str) -> String {
+        match self.find(char_key) {
+            Some(a_member) => a_member.display_name.to_string(),
+            None => char_key.to_string(),
+        }
+    }
+
+    //name used above each speech, the key itself if the character is not in the cast file
+    pub fn speech_prefix(&self, char_key: &str) -> String {
+        match self.find(char_key) {
+            Some(a_member) => a_member.speech_prefix.to_string(),
+            None => char_key.to_string(),
+        }
+    }
+
+    //description from the cast file, empty if there is none
+    pub fn description(&self, char_key: &str) -> String {
+        match self.find(char_key) {
+            Some(a_member) => a_member.description.to_string(),
+            None => String::new(),
+        }
+    }
+}
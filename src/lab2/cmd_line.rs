@@ -0,0 +1,126 @@
+//cmd_line.rs parses the command line into a command, its positional arguments and any --name value options. Aman Verma, Johnny Huang, Hanson Li
+
+use super::declarations::{WHINGE, EXIT_BAD_CMDLINE, WHINGE_TOKEN, USE_CACHE, NO_CACHE_TOKEN, ENCODING_OPTION, DEFINE_OPTION, CONTINUATION_OPTION};
+use super::player::{Continuation, CONTINUATION, CONTINUATION_JOIN, CONTINUATION_WRAP};
+use super::script_gen::set_encoding;
+use super::variables::add_define;
+use std::sync::atomic::Ordering;
+
+pub const OPTION_PREFIX: &str = "--";
+pub const OPTION_VALUE_DELIM: char = '=';
+
+//commands understood by the program, anything else in the command position is taken as a script file for recite
+pub const RECITE_COMMAND: &str = "recite";
+pub const DOUBLING_COMMAND: &str = "doubling";
+pub const SEARCH_COMMAND: &str = "search";
+pub const CONCORDANCE_COMMAND: &str = "concordance";
+pub const DIFF_COMMAND: &str = "diff";
+pub const RENUMBER_COMMAND: &str = "renumber";
+pub const IMPORT_COMMAND: &str = "import";
+pub const EXPORT_COMMAND: &str = "export";
+pub const CACHE_COMMAND: &str = "cache";
+pub const VALIDATE_COMMAND: &str = "validate";
+pub const COMMANDS: [&str; 10] = [RECITE_COMMAND, DOUBLING_COMMAND, SEARCH_COMMAND, CONCORDANCE_COMMAND, DIFF_COMMAND, RENUMBER_COMMAND, IMPORT_COMMAND, EXPORT_COMMAND, CACHE_COMMAND, VALIDATE_COMMAND];
+
+//options that take no value, every other option expects one
+pub const FLAG_OPTIONS: [&str; 10] = ["whinge", "whole-word", "case-sensitive", "dry-run", "join-lines", "split", "no-cache", "watch", "actors", "force"];
+
+pub struct CmdLine {
+    pub program_name: String,
+    pub command: String,
+    pub positionals: Vec<String>,     //arguments that are not options, in order
+    pub options: Vec<(String, String)>, //option name without the leading -- and its value, empty for flags
+}
+
+impl CmdLine {
+    pub fn new(program_name: &str) -> Self {
+        Self {
+            program_name: program_name.to_string(),
+            command: RECITE_COMMAND.to_string(),
+            positionals: Vec::new(),
+            options: Vec::new(),
+        }
+    }
+
+    //splits the arguments after the program name. The bare whinge token is kept working for old scripts
+    pub fn parse(&mut self, args: &[String]) -> Result<(), u8> {
+        let mut arg_iter = args.iter().peekable();
+
+        if let Some(first_arg) = arg_iter.peek() {
+            if COMMANDS.contains(&first_arg.as_str()) {
+                self.command = first_arg.to_string();
+                arg_iter.next();
+            }
+        }
+
+        while let Some(an_arg) = arg_iter.next() {
+            if an_arg == WHINGE_TOKEN {
+                WHINGE.store(true, Ordering::SeqCst);
+            } else if let Some(option_text) = an_arg.strip_prefix(OPTION_PREFIX) {
+                //either --name=value, a known flag, or --name value
+                if let Some((option_name, option_value)) = option_text.split_once(OPTION_VALUE_DELIM) {
+                    self.options.push((option_name.to_string(), option_value.to_string()));
+                } else if FLAG_OPTIONS.contains(&option_text) {
+                    self.options.push((option_text.to_string(), String::new()));
+                } else {
+                    match arg_iter.next() {
+                        Some(option_value) => self.options.push((option_text.to_string(), option_value.to_string())),
+                        None => return Err(EXIT_BAD_CMDLINE),
+                    }
+                }
+            } else {
+                self.positionals.push(an_arg.to_string());
+            }
+        }
+
+        if self.has_flag(WHINGE_TOKEN) {
+            WHINGE.store(true, Ordering::SeqCst);
+        }
+        if self.has_flag(NO_CACHE_TOKEN) {
+            USE_CACHE.store(false, Ordering::SeqCst);
+        }
+        //a cache does not record the encoding its play was read with, so a run that chooses one reads every file again
+        for (_, option_value) in self.options.iter().filter(|(option_name, _)| option_name == ENCODING_OPTION) {
+            if set_encoding(option_value).is_err() {
+                return Err(EXIT_BAD_CMDLINE);
+            }
+            USE_CACHE.store(false, Ordering::SeqCst);
+        }
+        //nor whether unnumbered part file lines were kept, so the same goes for --continuation
+        if let Some(mode_name) = self.option(CONTINUATION_OPTION) {
+            let Some(a_mode) = Continuation::from_name(&mode_name) else {
+                println!("Error: unknown continuation mode '{}', expected {} or {}", mode_name, CONTINUATION_JOIN, CONTINUATION_WRAP);
+                return Err(EXIT_BAD_CMDLINE);
+            };
+            if let Ok(mut continuation) = CONTINUATION.lock() {
+                *continuation = Some(a_mode);
+            }
+            USE_CACHE.store(false, Ordering::SeqCst);
+        }
+        for (_, option_value) in self.options.iter().filter(|(option_name, _)| option_name == DEFINE_OPTION) {
+            if add_define(option_value).is_err() {
+                return Err(EXIT_BAD_CMDLINE);
+            }
+        }
+        Ok(())
+    }
+
+    //last value given for an option, so a later --name overrides an earlier one
+    pub fn option(&self, option_name: &str) -> Option<String> {
+        self.options.iter().rev().find(|(a_name, _)| a_name == option_name).map(|(_, a_value)| a_value.to_string())
+    }
+
+    pub fn has_flag(&self, option_name: &str) -> bool {
+        self.options.iter().any(|(a_name, _)| a_name == option_name)
+    }
+
+    //fails if an option outside of known_options was given, so typos are not silently ignored
+    pub fn check_options(&self, known_options: &[&str]) -> Result<(), String> {
+        for (option_name, _) in self.options.iter() {
+            if option_name != WHINGE_TOKEN && option_name != NO_CACHE_TOKEN && option_name != ENCODING_OPTION && option_name != DEFINE_OPTION && option_name != CONTINUATION_OPTION && !known_options.contains(&option_name.as_str()) {
+                return Err(format!("unknown option {}{} for {}", OPTION_PREFIX, option_name, self.command));
+            }
+        }
+        Ok(())
+    }
+}
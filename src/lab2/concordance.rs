@@ -0,0 +1,317 @@
+//concordance.rs builds a word index over every line of a prepared Play: each distinct word with its frequency per character and per scene, the line numbers it occurs at, and the words most characteristic of each character. Prints it as text, CSV or JSON. Hanson Li, Johnny Huang, Aman Verma
+
+use super::cast::Cast;
+use super::declarations::GENERATION_FAILURE;
+use super::json::JsonValue;
+use super::line_num::LineNum;
+use super::play::Play;
+use super::search::split_words;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+pub const DEFAULT_TOP_WORDS: usize = 5;
+pub const CSV_DELIM: char = ',';
+pub const CSV_HEADER: &str = "record,word,character,scene,fragment,line,count,score";
+
+pub type Occurrence = (String, usize, String, LineNum); //scene title, fragment number counted from 1, character key and line number
+pub type WordCount = (String, usize); //character key or scene title and how often the word is spoken there
+pub type CharacteristicWord = (String, usize, f64); //word, times the character says it and its log-likelihood score
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConcordanceFormat {
+    Text,
+    Csv,
+    Json,
+}
+
+impl ConcordanceFormat {
+    //reads the --format option value
+    pub fn from_name(format_name: &str) -> Option<Self> {
+        match format_name {
+            "text" => Some(ConcordanceFormat::Text),
+            "csv" => Some(ConcordanceFormat::Csv),
+            "json" => Some(ConcordanceFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WordEntry {
+    pub total: usize,
+    pub by_character: Vec<WordCount>, //in order of each character's first use of the word
+    pub by_scene: Vec<WordCount>,     //in scene order
+    pub occurrences: Vec<Occurrence>, //in speaking order
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Concordance {
+    pub words: BTreeMap<String, WordEntry>, //keyed by the normalized word, so words come out alphabetically
+    pub characters: Vec<WordCount>,         //every speaking character and how many words they speak, in order of first line
+    pub total_words: usize,
+    pub cast: Cast, //characters are counted by key and printed with their display name
+}
+
+//adds one to the count kept under a name, appending the name if it is new
+fn bump_count(counts: &mut Vec<WordCount>, count_name: &str) {
+    match counts.iter_mut().find(|(a_name, _)| a_name == count_name) {
+        Some((_, a_count)) => *a_count += 1,
+        None => counts.push((count_name.to_string(), 1)),
+    }
+}
+
+//lower cases a word and treats curly apostrophes as straight ones, so Sir, sir and sir's match the way they are written
+pub fn normalize_words(line_text: &str) -> Vec<String> {
+    split_words(&line_text.replace('\u{2019}', "'").to_lowercase())
+}
+
+//Dunning's log-likelihood of a word being said word_count times in char_total words by one character
+//and rest_count times in rest_total words by everyone else. Larger means the character leans on the word more
+fn log_likelihood(word_count: usize, char_total: usize, rest_count: usize, rest_total: usize) -> f64 {
+    let (word_count, char_total, rest_count, rest_total) = (word_count as f64, char_total as f64, rest_count as f64, rest_total as f64);
+    let all_total = char_total + rest_total;
+    if all_total == 0.0 {
+        return 0.0;
+    }
+    let expected_char = char_total * (word_count + rest_count) / all_total;
+    let expected_rest = rest_total * (word_count + rest_count) / all_total;
+    let term = |observed: f64, expected: f64| if observed > 0.0 && expected > 0.0 { observed * (observed / expected).ln() } else { 0.0 };
+    2.0 * (term(word_count, expected_char) + term(rest_count, expected_rest))
+}
+
+//rounds a score so text, CSV and JSON print the same value
+fn round_score(score: f64) -> f64 {
+    (score * 100.0).round() / 100.0
+}
+
+//quotes a CSV field if it holds the delimiter, a quote or a line break
+fn csv_field(field_text: &str) -> String {
+    if field_text.contains(CSV_DELIM) || field_text.contains('"') || field_text.contains('\n') {
+        format!("\"{}\"", field_text.replace('"', "\"\""))
+    } else {
+        field_text.to_string()
+    }
+}
+
+impl Concordance {
+    //indexes every word of every line in the play. Shared lines count for each character who speaks them
+    pub fn build(play: &Play) -> Result<Self, u8> {
+        let mut stderr = io::stderr().lock();
+        let scene_titles = play.fragment_scene_titles()?;
+        let mut concordance = Concordance { cast: play.cast().clone(), ..Concordance::default() };
+
+        for (fragment_idx, a_fragment) in play.fragments().iter().enumerate() {
+            let scene_ref = match a_fragment.lock() {
+                Ok(scene_ref) => scene_ref,
+                Err(_) => {
+                    let _ = writeln!(stderr, "Error: Failed to acquire lock on ith scene fragment");
+                    return Err(GENERATION_FAILURE);
+                }
+            };
+
+            for (line_num, player_idx, line_idx) in scene_ref.speaking_order() {
+                let plyr = &scene_ref.chars_in_play[player_idx];
+                let speakers: Vec<String> = if plyr.members.is_empty() { vec![plyr.char_name.to_string()] } else { plyr.members.clone() };
+
+                for a_word in normalize_words(&plyr.char_lines[line_idx].1) {
+                    let word_entry = concordance.words.entry(a_word).or_default();
+                    word_entry.total += 1;
+                    bump_count(&mut word_entry.by_scene, &scene_titles[fragment_idx]);
+                    for a_speaker in speakers.iter() {
+                        bump_count(&mut word_entry.by_character, a_speaker);
+                        word_entry.occurrences.push((scene_titles[fragment_idx].to_string(), fragment_idx + 1, a_speaker.to_string(), line_num));
+                        bump_count(&mut concordance.characters, a_speaker);
+                    }
+                    concordance.total_words += 1;
+                }
+            }
+        }
+        Ok(concordance)
+    }
+
+    //the top_words words the character says noticeably more often than the rest of the play, highest score first
+    pub fn characteristic_words(&self, char_name: &str, top_words: usize) -> Vec<CharacteristicWord> {
+        let char_total = self.characters.iter().find(|(a_name, _)| a_name == char_name).map_or(0, |(_, a_count)| *a_count);
+        let all_spoken: usize = self.characters.iter().map(|(_, a_count)| a_count).sum();
+        let rest_total = all_spoken - char_total;
+
+        let mut scored: Vec<CharacteristicWord> = Vec::new();
+        for (a_word, word_entry) in self.words.iter() {
+            let word_count = word_entry.by_character.iter().find(|(a_name, _)| a_name == char_name).map_or(0, |(_, a_count)| *a_count);
+            let all_count: usize = word_entry.by_character.iter().map(|(_, a_count)| a_count).sum();
+            let rest_count = all_count - word_count;
+            //only words the character uses more than everyone else does, relative to how much each side speaks
+            if word_count == 0 || (rest_total > 0 && word_count * rest_total <= rest_count * char_total) {
+                continue;
+            }
+            let score = round_score(log_likelihood(word_count, char_total, rest_count, rest_total));
+            scored.push((a_word.to_string(), word_count, score));
+        }
+        scored.sort_by(|(word_a, count_a, score_a), (word_b, count_b, score_b)| {
+            score_b.total_cmp(score_a).then(count_b.cmp(count_a)).then(word_a.cmp(word_b))
+        });
+        scored.truncate(top_words);
+        scored
+    }
+
+    fn display_name(&self, char_name: &str) -> String {
+        self.cast.display_name(char_name)
+    }
+
+    pub fn print(&self, format: ConcordanceFormat, top_words: usize) {
+        match format {
+            ConcordanceFormat::Text => self.print_text(top_words),
+            ConcordanceFormat::Csv => self.print_csv(top_words),
+            ConcordanceFormat::Json => {
+                let mut stdout = io::stdout().lock();
+                let _ = writeln!(stdout, "{}", self.to_json(top_words).render());
+            }
+        }
+    }
+
+    fn print_text(&self, top_words: usize) {
+        let mut stdout = io::stdout().lock();
+        let _ = writeln!(stdout, "Concordance: {} distinct word(s), {} word(s) spoken", self.words.len(), self.total_words);
+
+        for (a_word, word_entry) in self.words.iter() {
+            let _ = writeln!(stdout);
+            let _ = writeln!(stdout, "{} ({})", a_word, word_entry.total);
+            let by_character: Vec<String> = word_entry.by_character.iter().map(|(a_name, a_count)| format!("{} {}", self.display_name(a_name), a_count)).collect();
+            let _ = writeln!(stdout, "  characters: {}", by_character.join(", "));
+            let by_scene: Vec<String> = word_entry.by_scene.iter().map(|(a_title, a_count)| format!("{} {}", a_title, a_count)).collect();
+            let _ = writeln!(stdout, "  scenes: {}", by_scene.join(", "));
+            for (scene_title, fragment_num, char_name, line_num) in word_entry.occurrences.iter() {
+                let _ = writeln!(stdout, "    {}, fragment {}, line {}, {}", scene_title, fragment_num, line_num, self.display_name(char_name));
+            }
+        }
+
+        let _ = writeln!(stdout);
+        let _ = writeln!(stdout, "Characteristic words");
+        for (char_name, char_total) in self.characters.iter() {
+            let scored: Vec<String> = self.characteristic_words(char_name, top_words).iter()
+                .map(|(a_word, a_count, a_score)| format!("{} ({}, {:.2})", a_word, a_count, a_score)).collect();
+            let _ = writeln!(stdout, "  {} ({} word(s)): {}", self.display_name(char_name), char_total, scored.join(", "));
+        }
+    }
+
+    //one row per word total, per character count, per scene count, per occurrence and per characteristic word, distinguished by the record column
+    fn print_csv(&self, top_words: usize) {
+        let mut stdout = io::stdout().lock();
+        let _ = writeln!(stdout, "{}", CSV_HEADER);
+        let row = |fields: [&str; 8]| fields.iter().map(|a_field| csv_field(a_field)).collect::<Vec<String>>().join(&CSV_DELIM.to_string());
+
+        for (a_word, word_entry) in self.words.iter() {
+            let _ = writeln!(stdout, "{}", row(["word", a_word, "", "", "", "", &word_entry.total.to_string(), ""]));
+            for (char_name, a_count) in word_entry.by_character.iter() {
+                let _ = writeln!(stdout, "{}", row(["character", a_word, &self.display_name(char_name), "", "", "", &a_count.to_string(), ""]));
+            }
+            for (scene_title, a_count) in word_entry.by_scene.iter() {
+                let _ = writeln!(stdout, "{}", row(["scene", a_word, "", scene_title, "", "", &a_count.to_string(), ""]));
+            }
+            for (scene_title, fragment_num, char_name, line_num) in word_entry.occurrences.iter() {
+                let _ = writeln!(stdout, "{}", row(["occurrence", a_word, &self.display_name(char_name), scene_title, &fragment_num.to_string(), &line_num.to_string(), "", ""]));
+            }
+        }
+        for (char_name, _) in self.characters.iter() {
+            for (a_word, a_count, a_score) in self.characteristic_words(char_name, top_words) {
+                let _ = writeln!(stdout, "{}", row(["characteristic", &a_word, &self.display_name(char_name), "", "", "", &a_count.to_string(), &format!("{:.2}", a_score)]));
+            }
+        }
+    }
+
+    pub fn to_json(&self, top_words: usize) -> JsonValue {
+        let counts_json = |counts: &Vec<WordCount>| JsonValue::Object(counts.iter().map(|(a_name, a_count)| (a_name.to_string(), JsonValue::from_usize(*a_count))).collect());
+        let char_counts_json = |counts: &Vec<WordCount>| JsonValue::Object(counts.iter().map(|(a_name, a_count)| (self.display_name(a_name), JsonValue::from_usize(*a_count))).collect());
+
+        let words_json: Vec<JsonValue> = self.words.iter().map(|(a_word, word_entry)| {
+            let occurrences_json: Vec<JsonValue> = word_entry.occurrences.iter().map(|(scene_title, fragment_num, char_name, line_num)| JsonValue::Object(vec![
+                ("scene".to_string(), JsonValue::from_text(scene_title)),
+                ("fragment".to_string(), JsonValue::from_usize(*fragment_num)),
+                ("character".to_string(), JsonValue::from_text(&self.display_name(char_name))),
+                ("line".to_string(), line_num.to_json()),
+            ])).collect();
+            JsonValue::Object(vec![
+                ("word".to_string(), JsonValue::from_text(a_word)),
+                ("total".to_string(), JsonValue::from_usize(word_entry.total)),
+                ("by_character".to_string(), char_counts_json(&word_entry.by_character)),
+                ("by_scene".to_string(), counts_json(&word_entry.by_scene)),
+                ("occurrences".to_string(), JsonValue::Array(occurrences_json)),
+            ])
+        }).collect();
+
+        let characteristic_json: Vec<(String, JsonValue)> = self.characters.iter().map(|(char_name, _)| {
+            let scored_json: Vec<JsonValue> = self.characteristic_words(char_name, top_words).iter().map(|(a_word, a_count, a_score)| JsonValue::Object(vec![
+                ("word".to_string(), JsonValue::from_text(a_word)),
+                ("count".to_string(), JsonValue::from_usize(*a_count)),
+                ("score".to_string(), JsonValue::Number(*a_score)),
+            ])).collect();
+            (self.display_name(char_name), JsonValue::Array(scored_json))
+        }).collect();
+
+        JsonValue::Object(vec![
+            ("total_words".to_string(), JsonValue::from_usize(self.total_words)),
+            ("distinct_words".to_string(), JsonValue::from_usize(self.words.len())),
+            ("characters".to_string(), char_counts_json(&self.characters)),
+            ("words".to_string(), JsonValue::Array(words_json)),
+            ("characteristic".to_string(), JsonValue::Object(characteristic_json)),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //a concordance from (character, words spoken) pairs, without the scenes and line numbers
+    fn concordance(spoken: &[(&str, &str)]) -> Concordance {
+        let mut concordance = Concordance::default();
+        for (char_name, line_text) in spoken.iter() {
+            for a_word in normalize_words(line_text) {
+                let word_entry = concordance.words.entry(a_word).or_default();
+                word_entry.total += 1;
+                bump_count(&mut word_entry.by_character, char_name);
+                bump_count(&mut concordance.characters, char_name);
+                concordance.total_words += 1;
+            }
+        }
+        concordance
+    }
+
+    #[test]
+    fn words_are_normalized() {
+        assert_eq!(normalize_words("Sir, SIR and sir\u{2019}s"), vec!["sir", "sir", "and", "sir's"]);
+    }
+
+    #[test]
+    fn log_likelihood_scores() {
+        //using a word at the same rate as everyone else scores nothing
+        assert_eq!(log_likelihood(2, 100, 4, 200), 0.0);
+        assert_eq!(log_likelihood(0, 0, 0, 0), 0.0);
+        assert!(log_likelihood(10, 100, 1, 200) > log_likelihood(5, 100, 1, 200));
+        assert_eq!(round_score(log_likelihood(10, 100, 0, 100)), 13.86);
+    }
+
+    #[test]
+    fn characteristic_words_are_the_ones_leant_on() {
+        let concordance = concordance(&[
+            ("Polonius", "my lord my lord indeed my lord"),
+            ("Reynaldo", "my lord I will my lord"),
+            ("Reynaldo", "I will indeed"),
+        ]);
+        let polonius_words: Vec<String> = concordance.characteristic_words("Polonius", 5).into_iter().map(|(a_word, _, _)| a_word).collect();
+        //indeed is one word in seven for Polonius and one in nine for Reynaldo, so it counts but scores lowest
+        assert_eq!(polonius_words, vec!["lord", "my", "indeed"]);
+        //equal scores come out alphabetically, and top_words cuts the list short
+        let reynaldo_words = concordance.characteristic_words("Reynaldo", 2);
+        assert_eq!(reynaldo_words.iter().map(|(a_word, a_count, _)| (a_word.as_str(), *a_count)).collect::<Vec<_>>(), vec![("i", 2), ("will", 2)]);
+        assert_eq!(reynaldo_words[0].2, reynaldo_words[1].2);
+        assert!(concordance.characteristic_words("Ophelia", 5).is_empty());
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("II.1 A room"), "II.1 A room");
+        assert_eq!(csv_field("Polonius, Lord Chamberlain"), "\"Polonius, Lord Chamberlain\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
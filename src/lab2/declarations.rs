@@ -4,12 +4,32 @@ use std::sync::atomic::AtomicBool;
 
 pub type Line = (usize, String, String);
 
-//command-line arity & positions
-pub const ARGS_MIN: usize = 2;
-pub const ARGS_MAX: usize = 3;
+//command-line positions, positional indices do not count the program name, the command or any options
 pub const ARG_PROGRAM_IDX: usize = 0;
-pub const ARG_SCRIPT_IDX: usize = 1;
-pub const ARG_WHINGE_IDX: usize = 2;
+pub const ARG_SCRIPT_IDX: usize = 0;
+pub const ARG_DOUBLING_IDX: usize = 1;
+pub const ARG_QUERY_IDX: usize = 1;
+pub const ARG_NEW_SCRIPT_IDX: usize = 1;
+pub const ARG_FORMAT_IDX: usize = 0;        //import and export name the format first
+pub const ARG_CONVERT_SOURCE_IDX: usize = 1; //file imported, or script exported
+pub const ARG_OUT_DIR_IDX: usize = 2;
+pub const ARG_CACHE_ACTION_IDX: usize = 0;
+pub const ARG_CACHE_TARGET_IDX: usize = 1; //script whose cache is cleaned, or a directory to clean
+pub const RECITE_POSITIONALS: usize = 1;
+pub const DOUBLING_POSITIONALS: usize = 2;
+pub const SEARCH_POSITIONALS: usize = 2;
+pub const CONCORDANCE_POSITIONALS: usize = 1;
+pub const DIFF_POSITIONALS: usize = 2;
+pub const RENUMBER_POSITIONALS: usize = 1;
+pub const IMPORT_POSITIONALS: usize = 3;
+pub const EXPORT_POSITIONALS: usize = 2;
+pub const CACHE_POSITIONALS: usize = 2;
+pub const VALIDATE_POSITIONALS: usize = 1;
+pub const WHINGE_TOKEN: &str = "whinge";
+pub const NO_CACHE_TOKEN: &str = "no-cache"; //accepted by every command, like whinge
+pub const ENCODING_OPTION: &str = "encoding"; //accepted by every command, may be given once per file
+pub const DEFINE_OPTION: &str = "define";     //accepted by every command, may be given once per variable
+pub const CONTINUATION_OPTION: &str = "continuation"; //accepted by every command, join or wrap
 
 //for variable initalizations to 0
 pub const ZERO_IDX: usize = 0;
@@ -17,8 +37,11 @@ pub const ZERO_IDX: usize = 0;
 //exit codes
 pub const EXIT_BAD_CMDLINE: u8 = 1;
 pub const GENERATION_FAILURE: u8 = 2;
+pub const CHECK_FAILURE: u8 = 3; //the play was read but a check over it found problems
 pub const SUCCESS_CODE: u8 = 0;
 //whinge, default to false
 pub static WHINGE: AtomicBool = AtomicBool::new(false);
+//scripts are read through their cache unless --no-cache is given
+pub static USE_CACHE: AtomicBool = AtomicBool::new(true);
 
 
@@ -0,0 +1,413 @@
+//diff.rs compares two prepared versions of a Play: scenes and fragments added or removed, characters added or removed per fragment, and each character's lines inserted, deleted, changed (with the changed words marked) or renumbered. Johnny Huang, Aman Verma, Hanson Li
+
+use super::cast::Cast;
+use super::declarations::GENERATION_FAILURE;
+use super::json::JsonValue;
+use super::line_num::LineNum;
+use super::play::{Fragments, Play};
+use super::player::Player;
+use super::scene_fragments::SceneFragment;
+use std::io::{self, Write};
+use std::sync::MutexGuard;
+
+//markers around deleted and inserted words in a changed line, e.g. "my [-good-]{+dear+} lord"
+pub const DELETED_WORDS_OPEN: &str = "[-";
+pub const DELETED_WORDS_CLOSE: &str = "-]";
+pub const INSERTED_WORDS_OPEN: &str = "{+";
+pub const INSERTED_WORDS_CLOSE: &str = "+}";
+
+pub type WordEdit = (WordOp, String); //what happened to a run of words and the words themselves
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WordOp {
+    Same,
+    Deleted,
+    Inserted,
+}
+
+impl WordOp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WordOp::Same => "same",
+            WordOp::Deleted => "deleted",
+            WordOp::Inserted => "inserted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    SceneAdded,
+    SceneRemoved,
+    FragmentAdded,
+    FragmentRemoved,
+    CharacterAdded,
+    CharacterRemoved,
+    LineInserted,
+    LineDeleted,
+    LineChanged,
+    LineRenumbered,
+}
+
+impl ChangeKind {
+    //the name used in the text report and in JSON
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChangeKind::SceneAdded => "scene added",
+            ChangeKind::SceneRemoved => "scene removed",
+            ChangeKind::FragmentAdded => "fragment added",
+            ChangeKind::FragmentRemoved => "fragment removed",
+            ChangeKind::CharacterAdded => "character added",
+            ChangeKind::CharacterRemoved => "character removed",
+            ChangeKind::LineInserted => "line inserted",
+            ChangeKind::LineDeleted => "line deleted",
+            ChangeKind::LineChanged => "line changed",
+            ChangeKind::LineRenumbered => "line renumbered",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlayChange {
+    pub kind: ChangeKind,
+    pub scene: String,              //short title of the scene, from the new play unless the scene was removed
+    pub fragment_num: Option<usize>, //fragment number counted from 1 within the scene
+    pub char_name: String,          //cast display name of the character, matching is done on the key
+    pub old_line: Option<LineNum>,
+    pub new_line: Option<LineNum>,
+    pub old_text: String,
+    pub new_text: String,
+    pub word_edits: Vec<WordEdit>, //only for changed lines
+}
+
+impl PlayChange {
+    fn new(kind: ChangeKind, scene: &str) -> Self {
+        Self {
+            kind,
+            scene: scene.to_string(),
+            fragment_num: None,
+            char_name: String::new(),
+            old_line: None,
+            new_line: None,
+            old_text: String::new(),
+            new_text: String::new(),
+            word_edits: Vec::new(),
+        }
+    }
+
+    //where the change is, e.g. "II.1 A room in Polonius house, fragment 2, Polonius"
+    fn location(&self) -> String {
+        let mut location = self.scene.to_string();
+        if let Some(fragment_num) = self.fragment_num {
+            location.push_str(&format!(", fragment {}", fragment_num));
+        }
+        if !self.char_name.is_empty() {
+            location.push_str(&format!(", {}", self.char_name));
+        }
+        location
+    }
+
+    //one line of the text report
+    pub fn describe(&self) -> String {
+        match self.kind {
+            ChangeKind::SceneAdded | ChangeKind::SceneRemoved | ChangeKind::FragmentAdded | ChangeKind::FragmentRemoved
+            | ChangeKind::CharacterAdded | ChangeKind::CharacterRemoved => format!("{}: {}", self.location(), self.kind.name()),
+            ChangeKind::LineInserted => format!("{}: line {} inserted: {}", self.location(), self.new_line.unwrap_or_default(), self.new_text),
+            ChangeKind::LineDeleted => format!("{}: line {} deleted: {}", self.location(), self.old_line.unwrap_or_default(), self.old_text),
+            ChangeKind::LineChanged => format!("{}: line {} changed: {}", self.location(), self.new_line.unwrap_or_default(), render_word_edits(&self.word_edits)),
+            ChangeKind::LineRenumbered => format!("{}: line {} renumbered to {}: {}", self.location(), self.old_line.unwrap_or_default(), self.new_line.unwrap_or_default(), self.new_text),
+        }
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let optional_number = |line_num: Option<usize>| line_num.map_or(JsonValue::Null, JsonValue::from_usize);
+        let mut fields: Vec<(String, JsonValue)> = vec![
+            ("kind".to_string(), JsonValue::from_text(self.kind.name())),
+            ("scene".to_string(), JsonValue::from_text(&self.scene)),
+            ("fragment".to_string(), optional_number(self.fragment_num)),
+            ("character".to_string(), if self.char_name.is_empty() { JsonValue::Null } else { JsonValue::from_text(&self.char_name) }),
+            ("old_line".to_string(), self.old_line.map_or(JsonValue::Null, |line_num| line_num.to_json())),
+            ("new_line".to_string(), self.new_line.map_or(JsonValue::Null, |line_num| line_num.to_json())),
+            ("old_text".to_string(), JsonValue::from_text(&self.old_text)),
+            ("new_text".to_string(), JsonValue::from_text(&self.new_text)),
+        ];
+        if self.kind == ChangeKind::LineChanged {
+            let words_json: Vec<JsonValue> = self.word_edits.iter().map(|(an_op, some_words)| JsonValue::Object(vec![
+                ("op".to_string(), JsonValue::from_text(an_op.name())),
+                ("text".to_string(), JsonValue::from_text(some_words)),
+            ])).collect();
+            fields.push(("words".to_string(), JsonValue::Array(words_json)));
+        }
+        JsonValue::Object(fields)
+    }
+}
+
+//index pairs of a longest common subsequence of old_items and new_items, in order
+pub fn common_subsequence<T: PartialEq>(old_items: &[T], new_items: &[T]) -> Vec<(usize, usize)> {
+    //common_len[i][j] is the length of the longest common subsequence of old_items[i..] and new_items[j..]
+    let mut common_len = vec![vec![0usize; new_items.len() + 1]; old_items.len() + 1];
+    for old_idx in (0..old_items.len()).rev() {
+        for new_idx in (0..new_items.len()).rev() {
+            common_len[old_idx][new_idx] = if old_items[old_idx] == new_items[new_idx] {
+                common_len[old_idx + 1][new_idx + 1] + 1
+            } else {
+                common_len[old_idx + 1][new_idx].max(common_len[old_idx][new_idx + 1])
+            };
+        }
+    }
+
+    let mut matched: Vec<(usize, usize)> = Vec::new();
+    let (mut old_idx, mut new_idx) = (0, 0);
+    while old_idx < old_items.len() && new_idx < new_items.len() {
+        if old_items[old_idx] == new_items[new_idx] {
+            matched.push((old_idx, new_idx));
+            old_idx += 1;
+            new_idx += 1;
+        } else if common_len[old_idx + 1][new_idx] >= common_len[old_idx][new_idx + 1] {
+            old_idx += 1;
+        } else {
+            new_idx += 1;
+        }
+    }
+    matched
+}
+
+//word level edits turning old_text into new_text, neighbouring words with the same edit are joined into one run
+pub fn diff_words(old_text: &str, new_text: &str) -> Vec<WordEdit> {
+    let old_words: Vec<&str> = old_text.split_whitespace().collect();
+    let new_words: Vec<&str> = new_text.split_whitespace().collect();
+    let mut word_edits: Vec<WordEdit> = Vec::new();
+    let mut push_word = |an_op: WordOp, a_word: &str| match word_edits.last_mut() {
+        Some((last_op, some_words)) if *last_op == an_op => {
+            some_words.push(' ');
+            some_words.push_str(a_word);
+        }
+        _ => word_edits.push((an_op, a_word.to_string())),
+    };
+
+    let (mut old_idx, mut new_idx) = (0, 0);
+    for (matched_old, matched_new) in common_subsequence(&old_words, &new_words).into_iter().chain(std::iter::once((old_words.len(), new_words.len()))) {
+        for a_word in old_words[old_idx..matched_old].iter() {
+            push_word(WordOp::Deleted, a_word);
+        }
+        for a_word in new_words[new_idx..matched_new].iter() {
+            push_word(WordOp::Inserted, a_word);
+        }
+        if matched_old < old_words.len() {
+            push_word(WordOp::Same, old_words[matched_old]);
+        }
+        old_idx = matched_old + 1;
+        new_idx = matched_new + 1;
+    }
+    word_edits
+}
+
+//a changed line with its deleted and inserted words marked
+pub fn render_word_edits(word_edits: &[WordEdit]) -> String {
+    let rendered: Vec<String> = word_edits.iter().map(|(an_op, some_words)| match an_op {
+        WordOp::Same => some_words.to_string(),
+        WordOp::Deleted => format!("{}{}{}", DELETED_WORDS_OPEN, some_words, DELETED_WORDS_CLOSE),
+        WordOp::Inserted => format!("{}{}{}", INSERTED_WORDS_OPEN, some_words, INSERTED_WORDS_CLOSE),
+    }).collect();
+    rendered.join(" ")
+}
+
+//scenes of a play keyed by their label (or title when there is no act or scene number), with their short titles and fragments
+fn scene_list(play: &Play) -> Vec<(String, String, Fragments)> {
+    let mut scenes: Vec<(String, String, Fragments)> = Vec::new();
+    for an_act in play.acts().iter() {
+        for a_scene in an_act.scenes.iter() {
+            let scene_label = a_scene.meta.label();
+            let scene_key = if scene_label.is_empty() { a_scene.meta.full_title.to_string() } else { scene_label };
+            scenes.push((scene_key, a_scene.meta.short_title(), a_scene.fragments.clone()));
+        }
+    }
+    scenes
+}
+
+fn lock_fragment<'a>(a_fragment: &'a std::sync::Mutex<SceneFragment>) -> Result<MutexGuard<'a, SceneFragment>, u8> {
+    match a_fragment.lock() {
+        Ok(scene_ref) => Ok(scene_ref),
+        Err(_) => {
+            let _ = writeln!(io::stderr().lock(), "Error: Failed to acquire lock on ith scene fragment");
+            Err(GENERATION_FAILURE)
+        }
+    }
+}
+
+//words of a line, so a change in spacing alone does not count as a change
+fn line_words(line_text: &str) -> Vec<String> {
+    line_text.split_whitespace().map(|a_word| a_word.to_string()).collect()
+}
+
+//compares one character's lines. Lines with the same text are matched in order; a matched line with a new number is renumbered,
+//and between matched lines the remaining old and new lines are paired up as changed, with any left over deleted or inserted
+fn diff_player(old_plyr: &Player, new_plyr: &Player, template: &PlayChange, changes: &mut Vec<PlayChange>) {
+    let old_texts: Vec<Vec<String>> = old_plyr.char_lines.iter().map(|(_, a_text)| line_words(a_text)).collect();
+    let new_texts: Vec<Vec<String>> = new_plyr.char_lines.iter().map(|(_, a_text)| line_words(a_text)).collect();
+    let line_change = |kind: ChangeKind, old_idx: Option<usize>, new_idx: Option<usize>| {
+        let mut a_change = template.clone();
+        a_change.kind = kind;
+        if let Some(old_idx) = old_idx {
+            a_change.old_line = Some(old_plyr.char_lines[old_idx].0);
+            a_change.old_text = old_plyr.char_lines[old_idx].1.to_string();
+        }
+        if let Some(new_idx) = new_idx {
+            a_change.new_line = Some(new_plyr.char_lines[new_idx].0);
+            a_change.new_text = new_plyr.char_lines[new_idx].1.to_string();
+        }
+        if kind == ChangeKind::LineChanged {
+            a_change.word_edits = diff_words(&a_change.old_text, &a_change.new_text);
+        }
+        a_change
+    };
+
+    let (mut old_idx, mut new_idx) = (0, 0);
+    let line_ends = std::iter::once((old_texts.len(), new_texts.len()));
+    for (matched_old, matched_new) in common_subsequence(&old_texts, &new_texts).into_iter().chain(line_ends) {
+        let gap_len = (matched_old - old_idx).max(matched_new - new_idx);
+        for gap_idx in 0..gap_len {
+            let old_in_gap = (old_idx + gap_idx < matched_old).then_some(old_idx + gap_idx);
+            let new_in_gap = (new_idx + gap_idx < matched_new).then_some(new_idx + gap_idx);
+            let kind = match (old_in_gap, new_in_gap) {
+                (Some(_), Some(_)) => ChangeKind::LineChanged,
+                (Some(_), None) => ChangeKind::LineDeleted,
+                _ => ChangeKind::LineInserted,
+            };
+            changes.push(line_change(kind, old_in_gap, new_in_gap));
+        }
+        if matched_old < old_texts.len() && old_plyr.char_lines[matched_old].0 != new_plyr.char_lines[matched_new].0 {
+            changes.push(line_change(ChangeKind::LineRenumbered, Some(matched_old), Some(matched_new)));
+        }
+        old_idx = matched_old + 1;
+        new_idx = matched_new + 1;
+    }
+}
+
+//compares the characters of two fragments with the same place in the same scene
+fn diff_fragment(old_fragment: &SceneFragment, new_fragment: &SceneFragment, casts: (&Cast, &Cast), template: &PlayChange, changes: &mut Vec<PlayChange>) {
+    let (old_cast, new_cast) = casts;
+    for old_plyr in old_fragment.chars_in_play.iter() {
+        if !new_fragment.chars_in_play.iter().any(|a_player| a_player.char_name == old_plyr.char_name) {
+            let mut a_change = template.clone();
+            a_change.kind = ChangeKind::CharacterRemoved;
+            a_change.char_name = old_cast.display_name(&old_plyr.char_name);
+            changes.push(a_change);
+        }
+    }
+    for new_plyr in new_fragment.chars_in_play.iter() {
+        let mut a_change = template.clone();
+        a_change.char_name = new_cast.display_name(&new_plyr.char_name);
+        match old_fragment.chars_in_play.iter().find(|a_player| a_player.char_name == new_plyr.char_name) {
+            Some(old_plyr) => diff_player(old_plyr, new_plyr, &a_change, changes),
+            None => {
+                a_change.kind = ChangeKind::CharacterAdded;
+                changes.push(a_change);
+            }
+        }
+    }
+}
+
+//every structural and line change from old_play to new_play, scene by scene in the new play's order with removed scenes first
+pub fn diff_plays(old_play: &Play, new_play: &Play) -> Result<Vec<PlayChange>, u8> {
+    let old_scenes = scene_list(old_play);
+    let new_scenes = scene_list(new_play);
+    let mut changes: Vec<PlayChange> = Vec::new();
+
+    for (old_key, old_title, _) in old_scenes.iter() {
+        if !new_scenes.iter().any(|(new_key, _, _)| new_key == old_key) {
+            changes.push(PlayChange::new(ChangeKind::SceneRemoved, old_title));
+        }
+    }
+
+    for (new_key, new_title, new_fragments) in new_scenes.iter() {
+        let old_fragments = match old_scenes.iter().find(|(old_key, _, _)| old_key == new_key) {
+            Some((_, _, old_fragments)) => old_fragments,
+            None => {
+                changes.push(PlayChange::new(ChangeKind::SceneAdded, new_title));
+                continue;
+            }
+        };
+
+        //fragments are matched by their place in the scene
+        for fragment_idx in 0..old_fragments.len().max(new_fragments.len()) {
+            let mut template = PlayChange::new(ChangeKind::LineChanged, new_title);
+            template.fragment_num = Some(fragment_idx + 1);
+            match (old_fragments.get(fragment_idx), new_fragments.get(fragment_idx)) {
+                (Some(old_fragment), Some(new_fragment)) => {
+                    let old_ref = lock_fragment(old_fragment)?;
+                    let new_ref = lock_fragment(new_fragment)?;
+                    diff_fragment(&old_ref, &new_ref, (old_play.cast(), new_play.cast()), &template, &mut changes);
+                }
+                (Some(_), None) => {
+                    template.kind = ChangeKind::FragmentRemoved;
+                    changes.push(template);
+                }
+                _ => {
+                    template.kind = ChangeKind::FragmentAdded;
+                    changes.push(template);
+                }
+            }
+        }
+    }
+    Ok(changes)
+}
+
+pub fn print_changes(changes: &[PlayChange], as_json: bool) {
+    let mut stdout = io::stdout().lock();
+    if as_json {
+        let changes_json = JsonValue::Object(vec![
+            ("change_count".to_string(), JsonValue::from_usize(changes.len())),
+            ("changes".to_string(), JsonValue::Array(changes.iter().map(|a_change| a_change.to_json()).collect())),
+        ]);
+        let _ = writeln!(stdout, "{}", changes_json.render());
+        return;
+    }
+    for a_change in changes.iter() {
+        let _ = writeln!(stdout, "{}", a_change.describe());
+    }
+    let _ = writeln!(stdout, "{} change(s)", changes.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(char_name: &str, char_lines: &[(usize, &str)]) -> Player {
+        let mut plyr = Player::new(&char_name.to_string());
+        plyr.char_lines = char_lines.iter().map(|(line_num, line_text)| (LineNum::from(*line_num), line_text.to_string())).collect();
+        plyr
+    }
+
+    #[test]
+    fn common_subsequence_pairs() {
+        assert_eq!(common_subsequence(&['a', 'b', 'c', 'd'], &['a', 'c', 'd', 'e']), vec![(0, 0), (2, 1), (3, 2)]);
+        assert_eq!(common_subsequence(&['a', 'b'], &['c', 'd']), vec![]);
+        assert_eq!(common_subsequence::<char>(&[], &['a']), vec![]);
+        //a repeated item is matched in order
+        assert_eq!(common_subsequence(&['x', 'a', 'x'], &['x', 'x']), vec![(0, 0), (2, 1)]);
+    }
+
+    #[test]
+    fn changed_words_are_marked() {
+        let word_edits = diff_words("Give him this money and these notes", "Give him these notes and this money");
+        assert_eq!(render_word_edits(&word_edits), "Give him [-this money and-] these notes {+and this money+}");
+        assert_eq!(render_word_edits(&diff_words("my good lord", "my  good lord")), "my good lord");
+        assert_eq!(render_word_edits(&diff_words("", "Reynaldo")), "{+Reynaldo+}");
+        assert_eq!(diff_words("my good lord", "my dear lord"),
+            vec![(WordOp::Same, "my".to_string()), (WordOp::Deleted, "good".to_string()), (WordOp::Inserted, "dear".to_string()), (WordOp::Same, "lord".to_string())]);
+    }
+
+    #[test]
+    fn player_lines_are_matched_before_they_are_changed() {
+        let old_plyr = player("Polonius", &[(1, "Give him this money"), (2, "and these notes"), (3, "Reynaldo")]);
+        let new_plyr = player("Polonius", &[(1, "Give him this money"), (2, "and these two notes"), (3, "Farewell"), (4, "Reynaldo")]);
+        let mut changes: Vec<PlayChange> = Vec::new();
+        diff_player(&old_plyr, &new_plyr, &PlayChange::new(ChangeKind::LineChanged, "II.1"), &mut changes);
+        let kinds: Vec<ChangeKind> = changes.iter().map(|a_change| a_change.kind).collect();
+        assert_eq!(kinds, vec![ChangeKind::LineChanged, ChangeKind::LineInserted, ChangeKind::LineRenumbered]);
+        assert_eq!(changes[0].describe(), "II.1: line 2 changed: and these {+two+} notes");
+        assert_eq!(changes[1].new_text, "Farewell");
+        assert_eq!((changes[2].old_line, changes[2].new_line), (Some(LineNum::from(3)), Some(LineNum::from(4))));
+    }
+}
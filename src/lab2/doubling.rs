@@ -0,0 +1,338 @@
+//doubling.rs declares the DoublingPlan struct that maps actors to the characters they play, and checks it against a prepared Play for characters of one actor on stage together and quick changes between fragments. Johnny Huang, Aman Verma, Hanson Li
+
+use super::declarations::{WHINGE, GENERATION_FAILURE};
+use super::line_num::LineNum;
+use super::play::Play;
+use super::script_gen::grab_trimmed_file_lines;
+use std::sync::atomic::Ordering;
+use std::io::{self, Write};
+
+//a doubling file line looks like: Jane Smith | Polonius, Guildenstern
+pub const DOUBLING_DELIM: char = '|';
+pub const ROLE_DELIM: char = ','; //only commas separate roles, so a role can be more than one word, e.g. Player King
+pub const DOUBLING_HEADING: &str = "Doubling Plan";
+
+//presence of every character in one fragment, collected once so the fragment locks are not held during the checks
+struct FragmentPresence {
+    scene_title: String,
+    names_at_start: Vec<String>,
+    names_at_end: Vec<String>,
+    ranges: Vec<(String, (LineNum, LineNum))>, //character and the lines they are on stage for
+}
+
+//a fragment index and a line in it, None for the start of the fragment when walking on or its end when leaving
+type StagePoint = (usize, Option<LineNum>);
+
+//an exit as one role and the next entrance as another, with no whole fragment between them
+type QuickChange = (String, StagePoint, String, StagePoint);
+
+impl FragmentPresence {
+    fn range(&self, char_name: &String) -> Option<(LineNum, LineNum)> {
+        self.ranges.iter().find(|(a_name, _)| a_name == char_name).map(|(_, a_range)| *a_range)
+    }
+
+    //the line a character walks on before, None inside if they are on stage from the start. None if they are not in the fragment
+    fn entrance_line(&self, char_name: &String) -> Option<Option<LineNum>> {
+        let (enter_line, _) = self.range(char_name)?;
+        Some(if self.names_at_start.contains(char_name) { None } else { Some(enter_line) })
+    }
+
+    //the line a character leaves after, None inside if they are still on stage at the end. None if they are not in the fragment
+    fn exit_line(&self, char_name: &String) -> Option<Option<LineNum>> {
+        let (_, exit_line) = self.range(char_name)?;
+        Some(if self.names_at_end.contains(char_name) { None } else { Some(exit_line) })
+    }
+}
+
+//pairs of roles whose stage time overlaps within a fragment
+fn conflicts(a_presence: &FragmentPresence, roles: &[String]) -> Vec<(String, String)> {
+    let mut conflict_vec: Vec<(String, String)> = Vec::new();
+    for (role_idx, a_role) in roles.iter().enumerate() {
+        for b_role in roles[role_idx + 1..].iter() {
+            if let (Some(a_range), Some(b_range)) = (a_presence.range(a_role), a_presence.range(b_role)) {
+                if a_range.0 <= b_range.1 && b_range.0 <= a_range.1 {
+                    conflict_vec.push((a_role.to_string(), b_role.to_string()));
+                }
+            }
+        }
+    }
+    conflict_vec
+}
+
+//leaving as one role in fragment fragment_idx and walking on as another later in the same fragment or anywhere in the next one. An exit at
+//the end of a fragment is no exit if the role is on stage again at the start of the next, and likewise for an entrance at the start
+fn quick_changes(presence_vec: &[FragmentPresence], fragment_idx: usize, roles: &[String]) -> Vec<QuickChange> {
+    let mut change_vec: Vec<QuickChange> = Vec::new();
+    let a_presence = &presence_vec[fragment_idx];
+    let next_presence = presence_vec.get(fragment_idx + 1);
+
+    for exit_role in roles.iter() {
+        let Some(exit_line) = a_presence.exit_line(exit_role) else {
+            continue;
+        };
+        if exit_line.is_none() && next_presence.is_some_and(|b_presence| b_presence.names_at_start.contains(exit_role)) {
+            continue;
+        }
+        for enter_role in roles.iter().filter(|a_role| *a_role != exit_role) {
+            let same_fragment = match (exit_line, a_presence.entrance_line(enter_role)) {
+                (Some(exit_num), Some(Some(enter_num))) if enter_num > exit_num => Some((fragment_idx, Some(enter_num))),
+                _ => None,
+            };
+            let next_fragment = next_presence.and_then(|b_presence| match b_presence.entrance_line(enter_role) {
+                Some(None) if !a_presence.names_at_end.contains(enter_role) => Some((fragment_idx + 1, None)),
+                Some(Some(enter_num)) => Some((fragment_idx + 1, Some(enter_num))),
+                _ => None,
+            });
+            if let Some(enter_point) = same_fragment.or(next_fragment) {
+                change_vec.push((exit_role.to_string(), (fragment_idx, exit_line), enter_role.to_string(), enter_point));
+            }
+        }
+    }
+    change_vec
+}
+
+fn describe_exit((fragment_idx, exit_line): StagePoint) -> String {
+    match exit_line {
+        Some(line_num) => format!("after line {} of fragment {}", line_num, fragment_idx + 1),
+        None => format!("at the end of fragment {}", fragment_idx + 1),
+    }
+}
+
+fn describe_entrance((fragment_idx, enter_line): StagePoint) -> String {
+    match enter_line {
+        Some(line_num) => format!("before line {} of fragment {}", line_num, fragment_idx + 1),
+        None => format!("at the start of fragment {}", fragment_idx + 1),
+    }
+}
+
+#[derive(Default)]
+pub struct DoublingPlan {
+    pub actors: Vec<(String, Vec<String>)>, //actor name and the character keys they play, in file order
+}
+
+impl DoublingPlan {
+    pub fn new() -> Self {
+        Self {
+            actors: Vec::new(),
+        }
+    }
+
+    //splits one doubling file line into the actor and their roles
+    pub fn add_config(&mut self, doubling_line: &str) {
+        let mut stderr = io::stderr().lock();
+
+        if doubling_line.is_empty() {
+            return;
+        }
+        let Some((actor_name, role_list)) = doubling_line.split_once(DOUBLING_DELIM) else {
+            if WHINGE.load(Ordering::SeqCst) {
+                let _ = writeln!(stderr,"Whinge Warning: doubling line '{}' is missing '{}' between the actor and their roles, skipping it", doubling_line, DOUBLING_DELIM);
+            }
+            return;
+        };
+
+        let mut roles: Vec<String> = Vec::new();
+        for a_role in role_list.split(ROLE_DELIM).map(|a_role| a_role.trim()).filter(|a_role| !a_role.is_empty()) {
+            //one character can only be played by one actor in a plan
+            if let Some(other_actor) = self.actor_for(a_role) {
+                if WHINGE.load(Ordering::SeqCst) {
+                    let _ = writeln!(stderr,"Whinge Warning: {} is already played by {}, ignoring it for {}", a_role, other_actor, actor_name.trim());
+                }
+                continue;
+            }
+            roles.push(a_role.to_string());
+        }
+        self.actors.push((actor_name.trim().to_string(), roles));
+    }
+
+    //reads the doubling file and adds each of its lines
+    pub fn prepare(&mut self, doubling_fname: &String) -> Result<(), u8> {
+        let mut stderr = io::stderr().lock();
+        let mut doubling_lines: Vec<String> = Vec::new();
+
+        if let Err(e_code) = grab_trimmed_file_lines(doubling_fname, &mut doubling_lines) {
+            let _ = writeln!(stderr,"Error: could not read doubling file '{}', error code {}", doubling_fname, e_code);
+            return Err(GENERATION_FAILURE);
+        }
+        for a_line in doubling_lines.iter() {
+            self.add_config(a_line);
+        }
+        Ok(())
+    }
+
+    pub fn actor_for(&self, char_name: &str) -> Option<&String> {
+        self.actors.iter().find(|(_, roles)| roles.iter().any(|a_role| a_role == char_name)).map(|(actor_name, _)| actor_name)
+    }
+
+    fn collect_presence(play: &Play) -> Result<Vec<FragmentPresence>, u8> {
+        let mut stderr = io::stderr().lock();
+        let scene_titles = play.fragment_scene_titles()?;
+        let mut presence_vec: Vec<FragmentPresence> = Vec::new();
+
+        for (fragment_idx, a_fragment) in play.fragments().iter().enumerate() {
+            match a_fragment.lock() {
+                Ok(scene_ref) => {
+                    let ranges = scene_ref.names_in_fragment().iter()
+                        .filter_map(|a_name| scene_ref.presence_range(a_name).map(|a_range| (a_name.to_string(), a_range)))
+                        .collect();
+                    presence_vec.push(FragmentPresence {
+                        scene_title: scene_titles[fragment_idx].to_string(),
+                        names_at_start: scene_ref.names_at_start(),
+                        names_at_end: scene_ref.names_at_end(),
+                        ranges,
+                    });
+                }
+                Err(_) => {
+                    let _ = writeln!(stderr, "Error: Failed to acquire lock on ith scene fragment");
+                    return Err(GENERATION_FAILURE);
+                }
+            }
+        }
+        Ok(presence_vec)
+    }
+
+    //prints the conflicts, quick changes and per-actor tracks. Returns how many conflicts and quick changes were found
+    pub fn check(&self, play: &Play) -> Result<usize, u8> {
+        let presence_vec = Self::collect_presence(play)?;
+        let cast = play.cast();
+        let mut stdout = io::stdout().lock();
+        let mut problem_count: usize = 0;
+
+        let _ = writeln!(stdout,"{:?}", DOUBLING_HEADING);
+
+        for (actor_name, roles) in self.actors.iter() {
+            for (fragment_idx, a_presence) in presence_vec.iter().enumerate() {
+                for (a_role, b_role) in conflicts(a_presence, roles) {
+                    problem_count += 1;
+                    let _ = writeln!(stdout,"Conflict: {} plays {} and {}, both on stage in fragment {} ({})",
+                        actor_name, cast.display_name(&a_role), cast.display_name(&b_role), fragment_idx + 1, a_presence.scene_title);
+                }
+                for (exit_role, exit_point, enter_role, enter_point) in quick_changes(&presence_vec, fragment_idx, roles) {
+                    problem_count += 1;
+                    let _ = writeln!(stdout,"Quick change: {} exits as {} {} and enters as {} {}",
+                        actor_name, cast.display_name(&exit_role), describe_exit(exit_point), cast.display_name(&enter_role), describe_entrance(enter_point));
+                }
+            }
+        }
+
+        for (actor_name, roles) in self.actors.iter() {
+            let _ = writeln!(stdout);
+            let _ = writeln!(stdout,"Track for {}:", actor_name);
+            for (fragment_idx, a_presence) in presence_vec.iter().enumerate() {
+                let on_stage_roles: Vec<String> = roles.iter().filter(|a_role| a_presence.range(a_role).is_some()).map(|a_role| cast.display_name(a_role)).collect();
+                if !on_stage_roles.is_empty() {
+                    let _ = writeln!(stdout,"    fragment {} {}: {}", fragment_idx + 1, a_presence.scene_title, on_stage_roles.join(", "));
+                }
+            }
+        }
+
+        self.report_unmatched(&presence_vec);
+        Ok(problem_count)
+    }
+
+    //characters in the play nobody plays, and roles in the plan that never appear
+    fn report_unmatched(&self, presence_vec: &[FragmentPresence]) {
+        let mut stdout = io::stdout().lock();
+        let mut stderr = io::stderr().lock();
+
+        let mut play_names: Vec<String> = Vec::new();
+        for a_presence in presence_vec.iter() {
+            for (a_name, _) in a_presence.ranges.iter() {
+                if !play_names.contains(a_name) {
+                    play_names.push(a_name.to_string());
+                }
+            }
+        }
+
+        let unassigned: Vec<String> = play_names.iter().filter(|a_name| self.actor_for(a_name).is_none()).map(|a_name| a_name.to_string()).collect();
+        if !unassigned.is_empty() {
+            let _ = writeln!(stdout);
+            let _ = writeln!(stdout,"Unassigned: {}", unassigned.join(", "));
+        }
+
+        if WHINGE.load(Ordering::SeqCst) {
+            for (actor_name, roles) in self.actors.iter() {
+                for a_role in roles.iter().filter(|a_role| !play_names.contains(a_role)) {
+                    let _ = writeln!(stderr,"Whinge Warning: {} is cast as {} but no config file uses that character", actor_name, a_role);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //a fragment where each character is on stage for (enter, exit), None meaning from the start or to the end
+    fn fragment(char_ranges: &[(&str, Option<usize>, Option<usize>)]) -> FragmentPresence {
+        FragmentPresence {
+            scene_title: "II.1".to_string(),
+            names_at_start: char_ranges.iter().filter(|(_, enter, _)| enter.is_none()).map(|(a_name, _, _)| a_name.to_string()).collect(),
+            names_at_end: char_ranges.iter().filter(|(_, _, exit)| exit.is_none()).map(|(a_name, _, _)| a_name.to_string()).collect(),
+            ranges: char_ranges.iter()
+                .map(|(a_name, enter, exit)| (a_name.to_string(), (LineNum::from(enter.unwrap_or(0)), LineNum::from(exit.unwrap_or(usize::MAX)))))
+                .collect(),
+        }
+    }
+
+    fn roles(role_names: &[&str]) -> Vec<String> {
+        role_names.iter().map(|a_name| a_name.to_string()).collect()
+    }
+
+    #[test]
+    fn overlapping_roles_conflict() {
+        let a_fragment = fragment(&[("Rosencrantz", None, None), ("Guildenstern", Some(4), Some(9)), ("Reynaldo", None, Some(3))]);
+        assert_eq!(conflicts(&a_fragment, &roles(&["Rosencrantz", "Guildenstern"])), vec![("Rosencrantz".to_string(), "Guildenstern".to_string())]);
+        assert!(conflicts(&a_fragment, &roles(&["Reynaldo", "Guildenstern"])).is_empty());
+        //exiting after line 4 and entering before it puts both on stage for line 4
+        let b_fragment = fragment(&[("Reynaldo", None, Some(4)), ("Ophelia", Some(4), None)]);
+        assert_eq!(conflicts(&b_fragment, &roles(&["Reynaldo", "Ophelia"])).len(), 1);
+    }
+
+    #[test]
+    fn boundary_quick_change() {
+        let presence_vec = [fragment(&[("Polonius", None, None), ("Reynaldo", None, None)]), fragment(&[("Polonius", None, None), ("Ophelia", None, None)])];
+        assert_eq!(quick_changes(&presence_vec, 0, &roles(&["Reynaldo", "Ophelia"])),
+            vec![("Reynaldo".to_string(), (0, None), "Ophelia".to_string(), (1, None))]);
+        assert!(quick_changes(&presence_vec, 1, &roles(&["Reynaldo", "Ophelia"])).is_empty());
+        //staying on across the boundary is no exit
+        assert!(quick_changes(&presence_vec, 0, &roles(&["Polonius", "Ophelia"])).is_empty());
+    }
+
+    #[test]
+    fn exit_line_then_entrance_in_the_next_fragment() {
+        let presence_vec = [fragment(&[("Polonius", None, None), ("Reynaldo", None, Some(80))]), fragment(&[("Polonius", None, None), ("Ophelia", Some(1), None)])];
+        assert_eq!(quick_changes(&presence_vec, 0, &roles(&["Reynaldo", "Ophelia"])),
+            vec![("Reynaldo".to_string(), (0, Some(LineNum::from(80))), "Ophelia".to_string(), (1, Some(LineNum::from(1))))]);
+    }
+
+    #[test]
+    fn exit_then_entrance_in_one_fragment() {
+        let presence_vec = [fragment(&[("Polonius", None, None), ("Reynaldo", None, Some(80)), ("Ophelia", Some(82), None)])];
+        assert_eq!(quick_changes(&presence_vec, 0, &roles(&["Reynaldo", "Ophelia"])),
+            vec![("Reynaldo".to_string(), (0, Some(LineNum::from(80))), "Ophelia".to_string(), (0, Some(LineNum::from(82))))]);
+        //entering before the other role exits is a conflict, not a quick change
+        let early_entrance = [fragment(&[("Reynaldo", None, Some(80)), ("Ophelia", Some(75), None)])];
+        assert!(quick_changes(&early_entrance, 0, &roles(&["Reynaldo", "Ophelia"])).is_empty());
+    }
+
+    #[test]
+    fn a_fragment_between_gives_time_to_change() {
+        let presence_vec = [
+            fragment(&[("Polonius", None, None), ("Reynaldo", None, None)]),
+            fragment(&[("Polonius", None, None)]),
+            fragment(&[("Polonius", None, None), ("Ophelia", None, None)]),
+        ];
+        for fragment_idx in 0..presence_vec.len() {
+            assert!(quick_changes(&presence_vec, fragment_idx, &roles(&["Reynaldo", "Ophelia"])).is_empty());
+        }
+    }
+
+    #[test]
+    fn roles_are_split_on_commas_only() {
+        let mut doubling_plan = DoublingPlan::new();
+        doubling_plan.add_config("Actor One | Player King,  Polonius ,, Player Queen");
+        assert_eq!(doubling_plan.actors, vec![("Actor One".to_string(), roles(&["Player King", "Polonius", "Player Queen"]))]);
+    }
+}
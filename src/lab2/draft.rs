@@ -0,0 +1,230 @@
+//draft.rs declares the Draft struct an importer fills in from another format: scenes, fragments and the speeches in them, along with the source lines that were not dialogue. A Draft is written out as a script, config files and numbered part files, or turned straight into a Play. Hanson Li, Johnny Huang, Aman Verma
+
+use super::declarations::GENERATION_FAILURE;
+use super::line_num::LineNum;
+use super::play::{Play, AUTHOR_DIRECTIVE, SCENE_DIRECTIVE, TITLE_DIRECTIVE};
+use super::player::{Player, ALL_GROUP, GROUP_DELIM};
+use super::scene_fragments::SceneFragment;
+use super::scene_meta::{SceneMeta, to_roman, ACT_MARK, AUTHOR_MARK};
+use super::tokens::quote_token;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+pub const SCRIPT_FILE_SUFFIX: &str = "_script.txt";
+pub const CONFIG_FILE_SUFFIX: &str = "_config.txt";
+pub const PART_FILE_SUFFIX: &str = ".txt";
+pub const UNCLASSIFIED_KIND: &str = "unclassified"; //skipped lines an importer could not make sense of, always reported
+pub const NAME_WORD_DELIM: char = '_'; //character names are one token, so "First Player" becomes First_Player
+
+pub type DraftLine = (String, String);     //character name and the text of one line
+pub type SkippedLine = (usize, String, String); //source line number, what kind of line it was and its text
+
+#[derive(Debug, Clone, Default)]
+pub struct DraftScene {
+    pub title: String,                  //[scene] title, e.g. "ACT II Scene 1 A room in Polonius house"
+    pub fragments: Vec<Vec<DraftLine>>, //lines of each fragment in speaking order
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Draft {
+    pub play_title: String,
+    pub author: String,
+    pub scenes: Vec<DraftScene>,
+    pub skipped: Vec<SkippedLine>, //source lines that are not dialogue, such as stage directions
+}
+
+//turns a speaker as written in a source file, e.g. "LORD POLONIUS" or "ROSENCRANTZ AND GUILDENSTERN", into a character name such as
+//Lord_Polonius or the group Rosencrantz+Guildenstern
+pub fn speaker_to_char_name(speaker: &str) -> String {
+    let speaker = speaker.trim();
+    if speaker.eq_ignore_ascii_case(ALL_GROUP) {
+        return ALL_GROUP.to_string();
+    }
+    let title_case = |a_word: &str| -> String {
+        let mut word_chars = a_word.chars();
+        match word_chars.next() {
+            Some(first_char) => first_char.to_uppercase().chain(word_chars.flat_map(|a_char| a_char.to_lowercase())).collect(),
+            None => String::new(),
+        }
+    };
+    //a shared speech names its speakers like "A, B and C" or "A & B"
+    let spaced = speaker.replace([',', '&'], " , ");
+    let mut members: Vec<Vec<String>> = vec![Vec::new()];
+    for a_token in spaced.split_whitespace() {
+        if a_token == "," || a_token.eq_ignore_ascii_case("and") {
+            members.push(Vec::new());
+        } else if let Some(cur_member) = members.last_mut() {
+            cur_member.push(title_case(a_token));
+        }
+    }
+    let member_names: Vec<String> = members.iter().filter(|some_words| !some_words.is_empty())
+        .map(|some_words| some_words.join(&NAME_WORD_DELIM.to_string())).collect();
+    member_names.join(&GROUP_DELIM.to_string())
+}
+
+//a character name as a speaker cue, e.g. Lord_Polonius as "LORD POLONIUS"
+pub fn char_name_to_speaker(char_name: &str) -> String {
+    char_name.replace(NAME_WORD_DELIM, " ").to_uppercase()
+}
+
+impl Draft {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //starts a new scene, later lines go into its first fragment
+    pub fn start_scene(&mut self, scene_title: &str) {
+        self.scenes.push(DraftScene { title: scene_title.to_string(), fragments: vec![Vec::new()] });
+    }
+
+    //starts a new fragment in the last scene, unless the last fragment has no lines yet
+    pub fn start_fragment(&mut self) {
+        if let Some(cur_scene) = self.scenes.last_mut() {
+            if cur_scene.fragments.last().is_some_and(|a_fragment| !a_fragment.is_empty()) {
+                cur_scene.fragments.push(Vec::new());
+            }
+        }
+    }
+
+    //a [scene] title in the form SceneMeta reads back, e.g. "Hamlet ACT II Scene 1 A room in Polonius house by William Shakespeare"
+    pub fn scene_title(&self, act_num: Option<usize>, scene_num: usize, location: &str) -> String {
+        let mut title_parts: Vec<String> = Vec::new();
+        if !self.play_title.is_empty() {
+            title_parts.push(self.play_title.to_string());
+        }
+        if let Some(act_num) = act_num {
+            title_parts.push(format!("{} {}", ACT_MARK.to_uppercase(), to_roman(act_num)));
+        }
+        title_parts.push(format!("Scene {}", to_roman(scene_num)));
+        if !location.is_empty() {
+            title_parts.push(location.to_string());
+        }
+        if !self.author.is_empty() {
+            title_parts.push(format!("{} {}", AUTHOR_MARK, self.author));
+        }
+        title_parts.join(" ")
+    }
+
+    //adds a line to the last fragment of the last scene, starting a scene with the play title if there is none yet
+    pub fn add_line(&mut self, char_name: &str, line_text: &str) {
+        if self.scenes.is_empty() {
+            let scene_title = if self.play_title.is_empty() { "Scene 1".to_string() } else { self.play_title.to_string() };
+            self.start_scene(&scene_title);
+        }
+        if let Some(cur_fragment) = self.scenes.last_mut().and_then(|cur_scene| cur_scene.fragments.last_mut()) {
+            cur_fragment.push((char_name.to_string(), line_text.to_string()));
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.scenes.iter().flat_map(|a_scene| a_scene.fragments.iter()).map(|a_fragment| a_fragment.len()).sum()
+    }
+
+    //scenes without any lines are dropped, since a fragment has to have someone speaking in it
+    fn spoken_scenes(&self) -> impl Iterator<Item = &DraftScene> {
+        self.scenes.iter().filter(|a_scene| a_scene.fragments.iter().any(|a_fragment| !a_fragment.is_empty()))
+    }
+
+    //the players of a fragment in order of their first line, lines numbered from 0 in speaking order
+    fn fragment_players(fragment_lines: &[DraftLine], part_file: &dyn Fn(&str) -> String) -> Vec<Player> {
+        let mut players: Vec<Player> = Vec::new();
+        for (line_num, (char_name, line_text)) in fragment_lines.iter().enumerate() {
+            let player_idx = match players.iter().position(|a_player| a_player.char_name == *char_name) {
+                Some(player_idx) => player_idx,
+                None => {
+                    let mut new_player = Player::new(char_name);
+                    new_player.part_file = part_file(char_name);
+                    players.push(new_player);
+                    players.len() - 1
+                }
+            };
+            players[player_idx].char_lines.push((LineNum::from(line_num), line_text.to_string()));
+        }
+        players
+    }
+
+    //builds the Play in memory, with source_name standing in for the part file of every line
+    pub fn to_play(&self, source_name: &str) -> Result<Play, u8> {
+        let mut play = Play::new();
+        for a_scene in self.spoken_scenes() {
+            let mut scene_meta = SceneMeta::from_title(&a_scene.title);
+            if !self.play_title.is_empty() {
+                scene_meta.play_title = self.play_title.to_string();
+            }
+            if !self.author.is_empty() {
+                scene_meta.set_author(&self.author);
+            }
+            play.push_scene(scene_meta);
+
+            for fragment_lines in a_scene.fragments.iter().filter(|a_fragment| !a_fragment.is_empty()) {
+                let mut fragment = SceneFragment::new(&a_scene.title);
+                for a_player in Self::fragment_players(fragment_lines, &|_| source_name.to_string()) {
+                    fragment.add_player(a_player);
+                }
+                play.push_fragment(fragment);
+            }
+        }
+        play.finish_import()?;
+        Ok(play)
+    }
+
+    //fragments are lettered a, b, c like the hand written configs, and numbered after z
+    fn fragment_tag(fragment_idx: usize) -> String {
+        match u8::try_from(fragment_idx) {
+            Ok(small_idx) if small_idx < 26 => ((b'a' + small_idx) as char).to_string(),
+            _ => format!("_{}", fragment_idx + 1),
+        }
+    }
+
+    //writes base_name_script.txt, one config file per fragment and one part file per character per fragment into out_dir.
+    //returns the script file's path
+    pub fn write_layout(&self, out_dir: &str, base_name: &str) -> Result<String, u8> {
+        let mut stderr = io::stderr().lock();
+        if let Err(e_msg) = fs::create_dir_all(out_dir) {
+            let _ = writeln!(stderr, "Error: could not create output directory {}: {}", out_dir, e_msg);
+            return Err(GENERATION_FAILURE);
+        }
+        let out_path = |file_name: String| Path::new(out_dir).join(file_name).display().to_string();
+        let write_file = |file_path: &str, file_lines: &[String]| -> Result<(), u8> {
+            let mut file_text = file_lines.join("\n");
+            file_text.push('\n');
+            fs::write(file_path, file_text).map_err(|e_msg| {
+                let _ = writeln!(io::stderr().lock(), "Error: could not write {}: {}", file_path, e_msg);
+                GENERATION_FAILURE
+            })
+        };
+
+        let mut script_lines: Vec<String> = Vec::new();
+        if !self.play_title.is_empty() {
+            script_lines.push(format!("{} {}", TITLE_DIRECTIVE, self.play_title));
+        }
+        if !self.author.is_empty() {
+            script_lines.push(format!("{} {}", AUTHOR_DIRECTIVE, self.author));
+        }
+
+        for (scene_idx, a_scene) in self.spoken_scenes().enumerate() {
+            script_lines.push(format!("{} {}", SCENE_DIRECTIVE, a_scene.title));
+            let scene_tag = SceneMeta::from_title(&a_scene.title).file_tag(scene_idx);
+
+            for (fragment_idx, fragment_lines) in a_scene.fragments.iter().filter(|a_fragment| !a_fragment.is_empty()).enumerate() {
+                let fragment_tag = format!("{}_{}{}", base_name, scene_tag, Self::fragment_tag(fragment_idx));
+                let part_file = |char_name: &str| out_path(format!("{}_{}{}", char_name, fragment_tag, PART_FILE_SUFFIX));
+                let mut config_lines: Vec<String> = Vec::new();
+
+                for a_player in Self::fragment_players(fragment_lines, &part_file) {
+                    let part_lines: Vec<String> = a_player.char_lines.iter().map(|(line_num, line_text)| format!("{} {}", line_num, line_text)).collect();
+                    write_file(&a_player.part_file, &part_lines)?;
+                    config_lines.push(format!("{} {}", quote_token(&a_player.char_name), quote_token(&a_player.part_file)));
+                }
+                let config_file = out_path(format!("{}{}", fragment_tag, CONFIG_FILE_SUFFIX));
+                write_file(&config_file, &config_lines)?;
+                script_lines.push(quote_token(&config_file));
+            }
+        }
+
+        let script_file = out_path(format!("{}{}", base_name, SCRIPT_FILE_SUFFIX));
+        write_file(&script_file, &script_lines)?;
+        Ok(script_file)
+    }
+}
@@ -0,0 +1,40 @@
+//excerpt.rs declares the Excerpt struct describing which part of a play to recite: a scene, a fragment, a line range, and which characters' lines to deliver. Hanson Li, Aman Verma, Johnny Huang
+
+use super::line_num::LineNum;
+use super::player::Player;
+
+pub const NAME_LIST_DELIM: char = ','; //--only Polonius,Reynaldo
+
+#[derive(Debug, Clone, Default)]
+pub struct Excerpt {
+    pub scene_label: Option<String>, //scene such as II.1
+    pub fragment_num: Option<usize>, //fragment number counted from 1 over the whole play
+    pub from_line: Option<usize>,    //first line number delivered in each selected fragment
+    pub to_line: Option<usize>,      //last line number delivered in each selected fragment
+    pub only: Vec<String>,           //if not empty, only these characters' lines are delivered
+    pub except: Vec<String>,         //these characters' lines are never delivered
+}
+
+impl Excerpt {
+    //an excerpt covering the whole play
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //splits a comma separated list of character names from the command line
+    pub fn split_names(name_list: &str) -> Vec<String> {
+        name_list.split(NAME_LIST_DELIM).map(|a_name| a_name.trim()).filter(|a_name| !a_name.is_empty()).map(|a_name| a_name.to_string()).collect()
+    }
+
+    //the range is of whole line numbers, so --to-line 12 takes in 12.1 and 12.2 as well
+    pub fn line_in_range(&self, line_num: LineNum) -> bool {
+        self.from_line.is_none_or(|from_line| line_num.whole() >= from_line) && self.to_line.is_none_or(|to_line| line_num.whole() <= to_line)
+    }
+
+    //whether this player's lines are delivered. Shared lines are delivered if any selected member speaks them
+    pub fn delivers(&self, plyr: &Player) -> bool {
+        let selected = self.only.is_empty() || plyr.members.iter().any(|a_member| self.only.contains(a_member)) || self.only.contains(&plyr.char_name);
+        let excluded = self.except.contains(&plyr.char_name) || (!plyr.members.is_empty() && plyr.members.iter().all(|a_member| self.except.contains(a_member)));
+        selected && !excluded
+    }
+}
@@ -0,0 +1,293 @@
+//fountain.rs reads a Fountain screenplay (title page, # act sections, scene headings, upper case character cues and their dialogue) into a Draft, and writes any Play back out as Fountain. Johnny Huang, Hanson Li, Aman Verma
+
+use super::declarations::GENERATION_FAILURE;
+use super::draft::{Draft, char_name_to_speaker, speaker_to_char_name};
+use super::personae::{Persona, PERSONAE_HEADING, persona_line};
+use super::play::Play;
+use super::player::{Player, ALL_GROUP};
+use super::scene_meta::{SceneMeta, parse_numeral, to_roman, ACT_MARK, LABEL_DELIM};
+use super::script_gen::read_text_file;
+use std::io::{self, Write};
+
+pub const FOUNTAIN_EXTENSION: &str = "fountain";
+
+//title page keys we keep, every other key is skipped
+pub const TITLE_KEY: &str = "title";
+pub const AUTHOR_KEYS: [&str; 2] = ["author", "authors"];
+
+pub const SCENE_HEADING_PREFIXES: [&str; 6] = ["INT", "EXT", "EST", "INT./EXT", "INT/EXT", "I/E"];
+pub const FORCED_SCENE_HEADING: char = '.';
+pub const FORCED_CHARACTER: char = '@';
+pub const FORCED_ACTION: char = '!';
+pub const FORCED_TRANSITION: char = '>';
+pub const CENTERED_END: char = '<';
+pub const SECTION_MARK: char = '#';
+pub const SCENE_NUMBER_MARK: char = '#'; //scene numbers close a heading, e.g. "INT. CASTLE #II.1#"
+pub const DUAL_DIALOGUE_MARK: char = '^';
+pub const TRANSITION_END: &str = "TO:";
+pub const FRAGMENT_SECTION: &str = "Fragment"; //export marks each fragment after a scene's first with "## Fragment 2"
+
+//kinds of skipped lines named in the import report
+pub const ACTION_KIND: &str = "action";
+pub const PARENTHETICAL_KIND: &str = "parenthetical";
+pub const TRANSITION_KIND: &str = "transition";
+
+//removes /* boneyard */ and [[notes]], which can span lines, keeping the line breaks so line numbers still match the file
+fn strip_comments(fountain_text: &str) -> String {
+    let mut stripped = String::new();
+    let mut rest = fountain_text;
+    loop {
+        let next_open = [("/*", "*/"), ("[[", "]]")].iter()
+            .filter_map(|(open_mark, close_mark)| rest.find(open_mark).map(|open_idx| (open_idx, *open_mark, *close_mark)))
+            .min_by_key(|(open_idx, _, _)| *open_idx);
+        let Some((open_idx, open_mark, close_mark)) = next_open else {
+            stripped.push_str(rest);
+            return stripped;
+        };
+        stripped.push_str(&rest[..open_idx]);
+        let after_open = &rest[open_idx + open_mark.len()..];
+        let close_idx = after_open.find(close_mark).unwrap_or(after_open.len());
+        //keep the line breaks inside the comment
+        stripped.extend(after_open[..close_idx].chars().filter(|a_char| *a_char == '\n'));
+        rest = &after_open[(close_idx + close_mark.len()).min(after_open.len())..];
+    }
+}
+
+//true for a character cue: at least one letter, no lower case letters once any (extension) is removed
+fn is_character_cue(trimmed: &str) -> bool {
+    let cue_name = trimmed.split('(').next().unwrap_or_default().trim_end_matches(DUAL_DIALOGUE_MARK).trim();
+    !cue_name.is_empty() && cue_name.chars().any(|a_char| a_char.is_alphabetic()) && !cue_name.chars().any(|a_char| a_char.is_lowercase())
+}
+
+//the scene heading text of a line, None if the line is not a scene heading
+fn scene_heading(trimmed: &str) -> Option<&str> {
+    if let Some(forced) = trimmed.strip_prefix(FORCED_SCENE_HEADING) {
+        if !forced.starts_with(FORCED_SCENE_HEADING) && !forced.is_empty() {
+            return Some(forced.trim());
+        }
+        return None;
+    }
+    let upper_line = trimmed.to_uppercase();
+    SCENE_HEADING_PREFIXES.iter().any(|a_prefix| {
+        upper_line.strip_prefix(a_prefix).is_some_and(|rest| rest.starts_with('.') || rest.starts_with(' '))
+    }).then_some(trimmed)
+}
+
+//splits a trailing #scene number# off a heading
+fn split_scene_number(heading: &str) -> (String, Option<String>) {
+    let heading = heading.trim();
+    if let Some(without_close) = heading.strip_suffix(SCENE_NUMBER_MARK) {
+        if let Some(open_idx) = without_close.rfind(SCENE_NUMBER_MARK) {
+            return (without_close[..open_idx].trim().to_string(), Some(without_close[open_idx + 1..].trim().to_string()));
+        }
+    }
+    (heading.to_string(), None)
+}
+
+//reads a Fountain screenplay into a Draft. Each scene heading starts a scene with one fragment, "# ACT II" sections set the act,
+//character cues and the dialogue under them become lines, and action, parentheticals and transitions are kept in the skipped list
+pub fn parse_fountain(fountain_text: &str) -> Draft {
+    let mut draft = Draft::new();
+    let stripped = strip_comments(fountain_text);
+    let source_lines: Vec<&str> = stripped.lines().collect();
+    let mut line_idx: usize = 0;
+
+    //the title page is a run of "Key: value" lines at the very top, indented lines continue the value above
+    if source_lines.first().is_some_and(|first_line| first_line.contains(':') && !first_line.starts_with(' ')) {
+        let mut cur_key = String::new();
+        while line_idx < source_lines.len() && !source_lines[line_idx].trim().is_empty() {
+            let a_line = source_lines[line_idx];
+            let (key, value) = match a_line.split_once(':') {
+                Some((key, value)) if !a_line.starts_with(' ') && !a_line.starts_with('\t') => {
+                    cur_key = key.trim().to_lowercase();
+                    (cur_key.as_str(), value.trim())
+                }
+                _ => (cur_key.as_str(), a_line.trim()),
+            };
+            let value_slot = if key == TITLE_KEY { Some(&mut draft.play_title) } else if AUTHOR_KEYS.contains(&key) { Some(&mut draft.author) } else { None };
+            if let (Some(value_slot), false) = (value_slot, value.is_empty()) {
+                if !value_slot.is_empty() {
+                    value_slot.push(' ');
+                }
+                value_slot.push_str(value.trim_matches('_').trim_matches('*'));
+            }
+            line_idx += 1;
+        }
+    }
+
+    let mut cur_act: Option<usize> = None;
+    let mut scene_count: usize = 0;
+    while line_idx < source_lines.len() {
+        let a_line = source_lines[line_idx];
+        let trimmed = a_line.trim();
+        let source_line_num = line_idx + 1;
+        let after_blank = line_idx == 0 || source_lines[line_idx - 1].trim().is_empty();
+        let before_text = source_lines.get(line_idx + 1).is_some_and(|next_line| !next_line.trim().is_empty());
+        line_idx += 1;
+
+        //page breaks (===) and synopses (= text) are not part of the play
+        if trimmed.is_empty() || trimmed.starts_with('=') {
+            continue;
+        }
+
+        if let Some(section) = trimmed.strip_prefix(SECTION_MARK) {
+            //"# ACT II" starts an act, a deeper section inside a scene starts a new fragment
+            let section_tokens: Vec<&str> = section.trim_start_matches(SECTION_MARK).split_whitespace().collect();
+            if section_tokens.len() >= 2 && section_tokens[0].eq_ignore_ascii_case(ACT_MARK) {
+                if let Some(act_num) = parse_numeral(section_tokens[1].trim_end_matches(['.', ':'])) {
+                    cur_act = Some(act_num);
+                    scene_count = 0;
+                }
+            } else if section.starts_with(SECTION_MARK) {
+                draft.start_fragment();
+            }
+            continue;
+        }
+
+        if let Some(heading) = scene_heading(trimmed).filter(|_| after_blank) {
+            let (location, scene_number) = split_scene_number(heading);
+            scene_count += 1;
+            //a scene number like II.1 gives the act as well
+            let (act_num, scene_num) = match scene_number.as_deref().map(|a_number| a_number.split_once(LABEL_DELIM).unwrap_or(("", a_number))) {
+                Some((act_text, scene_text)) => (parse_numeral(act_text).or(cur_act), parse_numeral(scene_text).unwrap_or(scene_count)),
+                None => (cur_act, scene_count),
+            };
+            cur_act = act_num;
+            scene_count = scene_num;
+            draft.start_scene(&draft.scene_title(act_num, scene_num, &location));
+            continue;
+        }
+
+        let forced_cue = trimmed.starts_with(FORCED_CHARACTER);
+        let is_transition = trimmed.starts_with(FORCED_TRANSITION) || (trimmed.ends_with(TRANSITION_END) && trimmed.to_uppercase() == trimmed);
+        if after_blank && before_text && !trimmed.starts_with(FORCED_ACTION) && !is_transition && (forced_cue || is_character_cue(trimmed)) {
+            let cue_name = trimmed.trim_start_matches(FORCED_CHARACTER).split('(').next().unwrap_or_default().trim_end().trim_end_matches(DUAL_DIALOGUE_MARK).trim();
+            let char_name = speaker_to_char_name(cue_name);
+            //dialogue runs to the next blank line
+            while line_idx < source_lines.len() && !source_lines[line_idx].trim().is_empty() {
+                let dialogue_line = source_lines[line_idx].trim();
+                if dialogue_line.starts_with('(') && dialogue_line.ends_with(')') {
+                    draft.skipped.push((line_idx + 1, PARENTHETICAL_KIND.to_string(), dialogue_line.to_string()));
+                } else {
+                    draft.add_line(&char_name, dialogue_line);
+                }
+                line_idx += 1;
+            }
+            continue;
+        }
+
+        let skipped_kind = if is_transition && !trimmed.ends_with(CENTERED_END) { TRANSITION_KIND } else { ACTION_KIND };
+        draft.skipped.push((source_line_num, skipped_kind.to_string(), trimmed.trim_start_matches(FORCED_ACTION).to_string()));
+    }
+    draft
+}
+
+//reads a .fountain file straight into a Play, the same checks run as for a play read from a script
+pub fn read_fountain_play(fountain_fname: &str) -> Result<Play, u8> {
+    let fountain_text = read_text_file(fountain_fname)?;
+    parse_fountain(&fountain_text).to_play(fountain_fname)
+}
+
+//the cue above a speech: the character key in upper case, shared lines name each member. Display names are left out since one like
+//"Polonius, Lord Chamberlain" would be read back as a shared part
+fn fountain_cue(plyr: &Player) -> String {
+    if plyr.char_name == ALL_GROUP || plyr.members.len() < 2 {
+        return char_name_to_speaker(&plyr.char_name);
+    }
+    let member_cues: Vec<String> = plyr.members.iter().map(|a_member| char_name_to_speaker(a_member)).collect();
+    let (last_cue, other_cues) = member_cues.split_last().unwrap_or((&plyr.char_name, &[]));
+    let cue = format!("{} AND {}", other_cues.join(", "), last_cue);
+    //a cue without letters would be read as action, so it is forced
+    if cue.chars().any(|a_char| a_char.is_alphabetic()) { cue } else { format!("{}{}", FORCED_CHARACTER, cue) }
+}
+
+//a forced scene heading for the scene, numbered with its label so it reads back into the same act and scene
+fn fountain_heading(scene_meta: &SceneMeta) -> String {
+    let scene_label = scene_meta.label();
+    let heading_text = if !scene_meta.location.is_empty() { scene_meta.location.to_string() } else if scene_label.is_empty() { scene_meta.full_title.to_string() } else { scene_label.to_string() };
+    if scene_label.is_empty() {
+        format!("{}{}", FORCED_SCENE_HEADING, heading_text)
+    } else {
+        format!("{}{} {}{}{}", FORCED_SCENE_HEADING, heading_text, SCENE_NUMBER_MARK, scene_label, SCENE_NUMBER_MARK)
+    }
+}
+
+//the cast list as a boneyard block, which import skips, so it is there to read without becoming part of the play
+fn fountain_personae(personae: &[Persona]) -> Vec<String> {
+    let mut personae_lines: Vec<String> = vec![format!("/* {}", PERSONAE_HEADING)];
+    for a_persona in personae.iter() {
+        personae_lines.push(persona_line(a_persona).replace("*/", "* /"));
+        for a_scene in a_persona.scenes.iter() {
+            personae_lines.push(format!("    {}", a_scene.replace("*/", "* /")));
+        }
+    }
+    personae_lines.push("*/".to_string());
+    personae_lines.push(String::new());
+    personae_lines
+}
+
+//writes the play as Fountain: a title page, the cast list if there is one, a section for each act, a heading for each scene and every
+//speech in speaking order
+pub fn export_fountain(play: &Play, personae: &[Persona]) -> Result<String, u8> {
+    let mut stderr = io::stderr().lock();
+    let mut fountain_lines: Vec<String> = Vec::new();
+
+    if let Some(first_meta) = play.acts().iter().flat_map(|an_act| an_act.scenes.iter()).map(|a_scene| &a_scene.meta).next() {
+        if !first_meta.play_title.is_empty() {
+            fountain_lines.push(format!("Title: {}", first_meta.play_title));
+        }
+        if !first_meta.author.is_empty() {
+            fountain_lines.push(format!("Author: {}", first_meta.author));
+        }
+        if !fountain_lines.is_empty() {
+            fountain_lines.push(String::new());
+        }
+    }
+    if !personae.is_empty() {
+        fountain_lines.extend(fountain_personae(personae));
+    }
+
+    for an_act in play.acts().iter() {
+        if let Some(act_num) = an_act.act {
+            fountain_lines.push(format!("{} {} {}", SECTION_MARK, ACT_MARK.to_uppercase(), to_roman(act_num)));
+            fountain_lines.push(String::new());
+        }
+        for a_scene in an_act.scenes.iter() {
+            fountain_lines.push(fountain_heading(&a_scene.meta));
+            fountain_lines.push(String::new());
+
+            for (fragment_idx, a_fragment) in a_scene.fragments.iter().enumerate() {
+                if fragment_idx > 0 {
+                    fountain_lines.push(format!("{}{} {} {}", SECTION_MARK, SECTION_MARK, FRAGMENT_SECTION, fragment_idx + 1));
+                    fountain_lines.push(String::new());
+                }
+                let scene_ref = match a_fragment.lock() {
+                    Ok(scene_ref) => scene_ref,
+                    Err(_) => {
+                        let _ = writeln!(stderr, "Error: Failed to acquire lock on ith scene fragment");
+                        return Err(GENERATION_FAILURE);
+                    }
+                };
+                let mut prev_player: Option<usize> = None;
+                for (_, player_idx, line_idx) in scene_ref.speaking_order() {
+                    let plyr = &scene_ref.chars_in_play[player_idx];
+                    if prev_player != Some(player_idx) {
+                        if prev_player.is_some() {
+                            fountain_lines.push(String::new());
+                        }
+                        fountain_lines.push(fountain_cue(plyr));
+                        prev_player = Some(player_idx);
+                    }
+                    fountain_lines.push(plyr.char_lines[line_idx].1.to_string());
+                }
+                if prev_player.is_some() {
+                    fountain_lines.push(String::new());
+                }
+            }
+        }
+    }
+
+    let mut fountain_text = fountain_lines.join("\n");
+    fountain_text.push('\n');
+    Ok(fountain_text)
+}
@@ -0,0 +1,312 @@
+//gutenberg.rs reads a play from Project Gutenberg style plain text, where ACT and SCENE headings start scenes, a speaker's name in capitals ("POLONIUS.") starts each speech and stage directions are bracketed or start with Enter/Exit, into a Draft. Aman Verma, Johnny Huang, Hanson Li
+
+use super::draft::{Draft, speaker_to_char_name, UNCLASSIFIED_KIND};
+use super::scene_meta::{parse_numeral, ACT_MARK, SCENE_MARK};
+
+pub const GUTENBERG_FORMAT: &str = "gutenberg";
+
+//the ebook text proper sits between these markers, the header above it can hold Title: and Author: lines
+pub const START_MARKER: &str = "*** START OF";
+pub const END_MARKER: &str = "*** END OF";
+pub const TITLE_PREFIX: &str = "Title:";
+pub const AUTHOR_PREFIX: &str = "Author:";
+
+pub const DIRECTION_OPEN: char = '[';
+pub const DIRECTION_CLOSE: char = ']';
+pub const SPEAKER_END: char = '.'; //"POLONIUS." or "POLONIUS. Give him this money"
+//unbracketed stage directions start with one of these words
+pub const DIRECTION_WORDS: [&str; 7] = ["Enter", "Exit", "Exeunt", "Re-enter", "Manet", "Flourish", "Dies"];
+pub const ENTER_WORDS: [&str; 2] = ["Enter", "Re-enter"]; //an entrance after some lines starts a new fragment
+
+//kinds of skipped lines named in the import report
+pub const STAGE_DIRECTION_KIND: &str = "stage direction";
+pub const FRONT_MATTER_KIND: &str = "front matter"; //anything before the first ACT or SCENE heading, such as the cast list
+
+//the speaker of a line like "POLONIUS." or "ROSENCRANTZ and GUILDENSTERN. We both obey.", with the text after it
+fn split_speaker(trimmed: &str) -> Option<(&str, &str)> {
+    let (speaker, rest) = trimmed.split_once(SPEAKER_END)?;
+    let speaker_tokens: Vec<&str> = speaker.split_whitespace().collect();
+    let is_name_token = |a_token: &str| {
+        a_token.chars().any(|a_char| a_char.is_alphabetic())
+            && a_token.chars().all(|a_char| a_char.is_uppercase() || a_char == '\'' || a_char == '\u{2019}' || a_char == '-' || a_char == ',')
+    };
+    let is_joiner = |a_token: &str| a_token == "and" || a_token == "&";
+    let letter_count = speaker.chars().filter(|a_char| a_char.is_alphabetic()).count();
+    let names_a_speaker = letter_count >= 2
+        && speaker_tokens.first().is_some_and(|first_token| is_name_token(first_token))
+        && speaker_tokens.iter().all(|a_token| is_name_token(a_token) || is_joiner(a_token))
+        //headings are not speakers, and a lone roman numeral is more likely a numbered heading
+        && !speaker_tokens[0].eq_ignore_ascii_case(ACT_MARK) && !speaker_tokens[0].eq_ignore_ascii_case(SCENE_MARK)
+        && !(speaker_tokens.len() == 1 && parse_numeral(speaker_tokens[0]).is_some());
+    names_a_speaker.then_some((speaker.trim(), rest.trim()))
+}
+
+//the numeral after ACT or SCENE at the start of a line, and the rest of the line after it
+fn split_heading<'a>(trimmed: &'a str, marker: &str) -> Option<(usize, &'a str)> {
+    let (first_token, rest) = trimmed.split_once(char::is_whitespace)?;
+    if !first_token.eq_ignore_ascii_case(marker) {
+        return None;
+    }
+    let rest = rest.trim_start();
+    let numeral_end = rest.find(|a_char: char| !a_char.is_alphanumeric()).unwrap_or(rest.len());
+    let heading_num = parse_numeral(&rest[..numeral_end])?;
+    Some((heading_num, rest[numeral_end..].trim_start_matches(['.', ':']).trim()))
+}
+
+//a stage direction as read, without the brackets and the underscores marking italics
+fn direction_text(direction: &str) -> String {
+    direction.trim().trim_start_matches(DIRECTION_OPEN).trim_end_matches(DIRECTION_CLOSE).replace('_', "").trim().to_string()
+}
+
+//removes [bracketed] directions from a line of a speech, returning what is left and the directions
+fn split_inline_directions(line_text: &str) -> (String, Vec<String>) {
+    let mut spoken = String::new();
+    let mut directions: Vec<String> = Vec::new();
+    let mut rest = line_text;
+    while let Some(open_idx) = rest.find(DIRECTION_OPEN) {
+        let Some(close_idx) = rest[open_idx..].find(DIRECTION_CLOSE).map(|close_idx| open_idx + close_idx) else {
+            break;
+        };
+        spoken.push_str(&rest[..open_idx]);
+        directions.push(direction_text(&rest[open_idx..=close_idx]));
+        rest = &rest[close_idx + 1..];
+    }
+    spoken.push_str(rest);
+    (spoken.split_whitespace().collect::<Vec<&str>>().join(" "), directions)
+}
+
+//reads the text into a Draft. Each physical line of a speech becomes one line, or with join_lines each speech becomes a single line.
+//stage directions are kept in the skipped list, and lines inside a scene that are none of heading, speaker, speech or direction are unclassified
+pub fn parse_gutenberg(gutenberg_text: &str, join_lines: bool) -> Draft {
+    let mut draft = Draft::new();
+    let source_lines: Vec<&str> = gutenberg_text.trim_start_matches('\u{feff}').lines().collect();
+
+    //only read between the start and end markers when the file has them
+    let body_start = match source_lines.iter().position(|a_line| a_line.trim_start().starts_with(START_MARKER)) {
+        Some(marker_idx) => {
+            for a_line in source_lines[..marker_idx].iter() {
+                if let Some(play_title) = a_line.trim().strip_prefix(TITLE_PREFIX) {
+                    draft.play_title = play_title.trim().to_string();
+                } else if let Some(author) = a_line.trim().strip_prefix(AUTHOR_PREFIX) {
+                    draft.author = author.trim().to_string();
+                }
+            }
+            marker_idx + 1
+        }
+        None => 0,
+    };
+    let body_end = source_lines.iter().rposition(|a_line| a_line.trim_start().starts_with(END_MARKER)).filter(|end_idx| *end_idx >= body_start).unwrap_or(source_lines.len());
+
+    let mut cur_act: Option<usize> = None;
+    let mut in_scene = false;
+    let mut cur_speaker: Option<String> = None;
+    let mut speech_lines: Vec<String> = Vec::new();
+    let mut open_direction: Option<(usize, String)> = None; //a bracketed direction running over several lines
+    let mut lines_in_fragment: usize = 0;
+
+    //adds the speech read so far to the draft
+    let flush_speech = |draft: &mut Draft, cur_speaker: &Option<String>, speech_lines: &mut Vec<String>, lines_in_fragment: &mut usize| {
+        if let Some(char_name) = cur_speaker {
+            if join_lines && !speech_lines.is_empty() {
+                draft.add_line(char_name, &speech_lines.join(" "));
+                *lines_in_fragment += 1;
+            } else {
+                for a_line in speech_lines.iter() {
+                    draft.add_line(char_name, a_line);
+                    *lines_in_fragment += 1;
+                }
+            }
+        }
+        speech_lines.clear();
+    };
+
+    for (line_idx, a_line) in source_lines.iter().enumerate().take(body_end).skip(body_start) {
+        let source_line_num = line_idx + 1;
+        let trimmed = a_line.trim();
+
+        if let Some((start_num, mut direction)) = open_direction.take() {
+            direction.push(' ');
+            direction.push_str(trimmed);
+            if trimmed.contains(DIRECTION_CLOSE) {
+                draft.skipped.push((start_num, STAGE_DIRECTION_KIND.to_string(), direction_text(&direction)));
+            } else {
+                open_direction = Some((start_num, direction));
+            }
+            continue;
+        }
+        if trimmed.is_empty() {
+            //a blank line ends a speech
+            flush_speech(&mut draft, &cur_speaker, &mut speech_lines, &mut lines_in_fragment);
+            cur_speaker = None;
+            continue;
+        }
+
+        //"ACT II", "ACT II. SCENE I. A room" or "SCENE I. A room in Polonius's house."
+        let mut scene_heading = split_heading(trimmed, SCENE_MARK);
+        if let Some((act_num, after_act)) = split_heading(trimmed, ACT_MARK) {
+            cur_act = Some(act_num);
+            scene_heading = split_heading(after_act, SCENE_MARK);
+            if scene_heading.is_none() {
+                flush_speech(&mut draft, &cur_speaker, &mut speech_lines, &mut lines_in_fragment);
+                cur_speaker = None;
+                continue;
+            }
+        }
+        if let Some((scene_num, location)) = scene_heading {
+            flush_speech(&mut draft, &cur_speaker, &mut speech_lines, &mut lines_in_fragment);
+            cur_speaker = None;
+            let scene_title = draft.scene_title(cur_act, scene_num, location.trim_end_matches('.'));
+            draft.start_scene(&scene_title);
+            in_scene = true;
+            lines_in_fragment = 0;
+            continue;
+        }
+
+        if !in_scene {
+            draft.skipped.push((source_line_num, FRONT_MATTER_KIND.to_string(), trimmed.to_string()));
+            continue;
+        }
+
+        //a whole line in brackets, or starting one that closes on a later line
+        if trimmed.starts_with(DIRECTION_OPEN) {
+            if trimmed.contains(DIRECTION_CLOSE) {
+                draft.skipped.push((source_line_num, STAGE_DIRECTION_KIND.to_string(), direction_text(trimmed)));
+            } else {
+                open_direction = Some((source_line_num, trimmed.to_string()));
+            }
+            continue;
+        }
+        let first_word = trimmed.split(|a_char: char| a_char.is_whitespace() || a_char == '.').next().unwrap_or_default();
+        if DIRECTION_WORDS.contains(&first_word) {
+            flush_speech(&mut draft, &cur_speaker, &mut speech_lines, &mut lines_in_fragment);
+            //someone walking on after lines have been spoken changes who is on stage, so it starts a fragment
+            if ENTER_WORDS.contains(&first_word) && lines_in_fragment > 0 {
+                draft.start_fragment();
+                lines_in_fragment = 0;
+            }
+            draft.skipped.push((source_line_num, STAGE_DIRECTION_KIND.to_string(), direction_text(trimmed)));
+            continue;
+        }
+
+        if let Some((speaker, first_text)) = split_speaker(trimmed) {
+            flush_speech(&mut draft, &cur_speaker, &mut speech_lines, &mut lines_in_fragment);
+            cur_speaker = Some(speaker_to_char_name(speaker));
+            if !first_text.is_empty() {
+                let (spoken, directions) = split_inline_directions(first_text);
+                for a_direction in directions {
+                    draft.skipped.push((source_line_num, STAGE_DIRECTION_KIND.to_string(), a_direction));
+                }
+                if !spoken.is_empty() {
+                    speech_lines.push(spoken);
+                }
+            }
+            continue;
+        }
+
+        if cur_speaker.is_some() {
+            let (spoken, directions) = split_inline_directions(trimmed);
+            for a_direction in directions {
+                draft.skipped.push((source_line_num, STAGE_DIRECTION_KIND.to_string(), a_direction));
+            }
+            if !spoken.is_empty() {
+                speech_lines.push(spoken);
+            }
+            continue;
+        }
+        draft.skipped.push((source_line_num, UNCLASSIFIED_KIND.to_string(), trimmed.to_string()));
+    }
+    flush_speech(&mut draft, &cur_speaker, &mut speech_lines, &mut lines_in_fragment);
+    if let Some((start_num, direction)) = open_direction {
+        draft.skipped.push((start_num, UNCLASSIFIED_KIND.to_string(), direction));
+    }
+    draft
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_TEXT: &str = "\u{feff}Title: Hamlet, Prince of Denmark
+Author: William Shakespeare
+
+*** START OF THE PROJECT GUTENBERG EBOOK HAMLET ***
+
+Dramatis Personae
+
+POLONIUS, Lord Chamberlain.
+
+ACT II
+
+SCENE I. A room in Polonius's house.
+
+Enter Polonius and Reynaldo.
+
+POLONIUS. Give him this money and these notes,
+Reynaldo.
+
+REYNALDO. I will, my lord. [_Going._]
+
+[_Exit Reynaldo, and
+after him a servant._]
+
+Enter Ophelia.
+
+OPHELIA. O, my lord, my lord,
+I have been so affrighted!
+
+ROSENCRANTZ AND GUILDENSTERN.
+We both obey.
+
+*** END OF THE PROJECT GUTENBERG EBOOK HAMLET ***
+Trailing licence text.
+";
+
+    #[test]
+    fn speakers_are_capitals_before_a_stop() {
+        assert_eq!(split_speaker("POLONIUS. Give him this money"), Some(("POLONIUS", "Give him this money")));
+        assert_eq!(split_speaker("ROSENCRANTZ and GUILDENSTERN."), Some(("ROSENCRANTZ and GUILDENSTERN", "")));
+        assert_eq!(split_speaker("Polonius. Give him"), None);
+        assert_eq!(split_speaker("ACT II."), None);
+        assert_eq!(split_speaker("IV. A room"), None);
+        assert_eq!(split_speaker("A. Someone"), None);
+    }
+
+    #[test]
+    fn headings_and_inline_directions() {
+        assert_eq!(split_heading("SCENE I. A room in Polonius's house.", SCENE_MARK), Some((1, "A room in Polonius's house.")));
+        assert_eq!(split_heading("ACT II. SCENE I. A room", ACT_MARK), Some((2, "SCENE I. A room")));
+        assert_eq!(split_heading("Actors enter", ACT_MARK), None);
+        assert_eq!(split_inline_directions("I will, my lord. [_Going._]  Farewell [Aside.]"),
+            ("I will, my lord. Farewell".to_string(), vec!["Going.".to_string(), "Aside.".to_string()]));
+        assert_eq!(split_inline_directions("an open [bracket").0, "an open [bracket");
+    }
+
+    #[test]
+    fn sample_play_is_read() {
+        let draft = parse_gutenberg(SAMPLE_TEXT, false);
+        assert_eq!(draft.play_title, "Hamlet, Prince of Denmark");
+        assert_eq!(draft.author, "William Shakespeare");
+        assert_eq!(draft.scenes.len(), 1);
+        assert!(draft.scenes[0].title.contains("ACT II Scene I A room in Polonius's house"));
+
+        //Ophelia walking on after lines have been spoken starts the second fragment
+        let fragments = &draft.scenes[0].fragments;
+        assert_eq!(fragments.len(), 2);
+        let first_lines: Vec<(&str, &str)> = fragments[0].iter().map(|(char_name, line_text)| (char_name.as_str(), line_text.as_str())).collect();
+        assert_eq!(first_lines, vec![("Polonius", "Give him this money and these notes,"), ("Polonius", "Reynaldo."), ("Reynaldo", "I will, my lord.")]);
+        assert_eq!(fragments[1].last().map(|(char_name, _)| char_name.as_str()), Some("Rosencrantz+Guildenstern"));
+
+        let kinds: Vec<&str> = draft.skipped.iter().map(|(_, a_kind, _)| a_kind.as_str()).collect();
+        assert_eq!(kinds.iter().filter(|a_kind| **a_kind == FRONT_MATTER_KIND).count(), 2);
+        assert!(!kinds.contains(&UNCLASSIFIED_KIND));
+        assert!(draft.skipped.iter().any(|(_, _, a_text)| a_text == "Exit Reynaldo, and after him a servant."));
+        assert!(!draft.skipped.iter().any(|(_, _, a_text)| a_text.contains("licence")));
+    }
+
+    #[test]
+    fn joined_lines_make_one_line_per_speech() {
+        let draft = parse_gutenberg(SAMPLE_TEXT, true);
+        assert_eq!(draft.scenes[0].fragments[0][0].1, "Give him this money and these notes, Reynaldo.");
+        assert_eq!(draft.scenes[0].fragments[1][0].1, "O, my lord, my lord, I have been so affrighted!");
+    }
+}
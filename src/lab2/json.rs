@@ -0,0 +1,345 @@
+//json.rs declares a small JsonValue type used by the commands with JSON output, along with rendering it as indented JSON text and parsing JSON text back into it. Aman Verma, Johnny Huang, Hanson Li
+
+use super::declarations::GENERATION_FAILURE;
+use std::io::{self, Write};
+
+pub const JSON_INDENT: &str = "  ";
+pub const MAX_JSON_DEPTH: usize = 128; //nesting deeper than this is rejected rather than risk the stack
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>), //keys are kept in insertion order
+}
+
+impl JsonValue {
+    pub fn from_text(text: &str) -> Self {
+        JsonValue::Str(text.to_string())
+    }
+
+    pub fn from_usize(number: usize) -> Self {
+        JsonValue::Number(number as f64)
+    }
+
+    //the value of a field of an object, None for a missing field or a value that is not an object
+    pub fn get(&self, field_name: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(fields) => fields.iter().find(|(a_key, _)| a_key == field_name).map(|(_, a_value)| a_value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::Str(a_string) => Some(a_string),
+            _ => None,
+        }
+    }
+
+    //a whole, non negative number, such as a line number
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            JsonValue::Number(a_number) if a_number.fract() == 0.0 && *a_number >= 0.0 && *a_number < 1e15 => Some(*a_number as usize),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == JsonValue::Null
+    }
+
+    //parses JSON text. A mistake is reported with the line and column it was found at in source_name, and fails with GENERATION_FAILURE
+    pub fn parse(json_text: &str, source_name: &str) -> Result<JsonValue, u8> {
+        let mut parser = JsonParser { json_chars: json_text.trim_start_matches('\u{feff}').chars().collect(), pos: 0 };
+        let parsed = parser.parse_value(0).and_then(|a_value| {
+            parser.skip_whitespace();
+            match parser.peek() {
+                None => Ok(a_value),
+                Some(a_char) => Err(format!("unexpected '{}' after the end of the document", a_char)),
+            }
+        });
+        parsed.map_err(|e_msg| {
+            let (line_num, column_num) = parser.line_and_column();
+            let _ = writeln!(io::stderr().lock(), "Error: {} line {} column {}: {}", source_name, line_num, column_num, e_msg);
+            GENERATION_FAILURE
+        })
+    }
+
+    //renders the value as indented JSON text
+    pub fn render(&self) -> String {
+        let mut json_text = String::new();
+        self.render_into(&mut json_text, 0);
+        json_text
+    }
+
+    fn render_into(&self, json_text: &mut String, depth: usize) {
+        match self {
+            JsonValue::Null => json_text.push_str("null"),
+            JsonValue::Bool(a_bool) => json_text.push_str(&a_bool.to_string()),
+            JsonValue::Number(a_number) => {
+                //whole numbers are written without a fraction so line numbers stay integers
+                if a_number.fract() == 0.0 && a_number.abs() < 1e15 {
+                    json_text.push_str(&format!("{}", *a_number as i64));
+                } else {
+                    json_text.push_str(&a_number.to_string());
+                }
+            }
+            JsonValue::Str(a_string) => json_text.push_str(&escape_json(a_string)),
+            JsonValue::Array(items) => {
+                if items.is_empty() {
+                    json_text.push_str("[]");
+                    return;
+                }
+                json_text.push('[');
+                for (item_idx, an_item) in items.iter().enumerate() {
+                    if item_idx > 0 {
+                        json_text.push(',');
+                    }
+                    json_text.push('\n');
+                    json_text.push_str(&JSON_INDENT.repeat(depth + 1));
+                    an_item.render_into(json_text, depth + 1);
+                }
+                json_text.push('\n');
+                json_text.push_str(&JSON_INDENT.repeat(depth));
+                json_text.push(']');
+            }
+            JsonValue::Object(fields) => {
+                if fields.is_empty() {
+                    json_text.push_str("{}");
+                    return;
+                }
+                json_text.push('{');
+                for (field_idx, (a_key, a_value)) in fields.iter().enumerate() {
+                    if field_idx > 0 {
+                        json_text.push(',');
+                    }
+                    json_text.push('\n');
+                    json_text.push_str(&JSON_INDENT.repeat(depth + 1));
+                    json_text.push_str(&escape_json(a_key));
+                    json_text.push_str(": ");
+                    a_value.render_into(json_text, depth + 1);
+                }
+                json_text.push('\n');
+                json_text.push_str(&JSON_INDENT.repeat(depth));
+                json_text.push('}');
+            }
+        }
+    }
+}
+
+//reads JSON text one character at a time, errors are messages for the position the parser stopped at
+struct JsonParser {
+    json_chars: Vec<char>,
+    pos: usize,
+}
+
+impl JsonParser {
+    fn peek(&self) -> Option<char> {
+        self.json_chars.get(self.pos).copied()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let a_char = self.peek();
+        if a_char.is_some() {
+            self.pos += 1;
+        }
+        a_char
+    }
+
+    //line and column of the parser's position, both counted from 1
+    fn line_and_column(&self) -> (usize, usize) {
+        let before = &self.json_chars[..self.pos.min(self.json_chars.len())];
+        let line_num = before.iter().filter(|a_char| **a_char == '\n').count() + 1;
+        let column_num = before.iter().rev().take_while(|a_char| **a_char != '\n').count() + 1;
+        (line_num, column_num)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|a_char| a_char == ' ' || a_char == '\t' || a_char == '\n' || a_char == '\r') {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.peek() {
+            Some(a_char) if a_char == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(a_char) => Err(format!("expected '{}' but found '{}'", expected, a_char)),
+            None => Err(format!("expected '{}' but the document ended", expected)),
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<JsonValue, String> {
+        if depth > MAX_JSON_DEPTH {
+            return Err(format!("values are nested more than {} deep", MAX_JSON_DEPTH));
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.parse_object(depth),
+            Some('[') => self.parse_array(depth),
+            Some('"') => self.parse_string().map(JsonValue::Str),
+            Some('-') | Some('0'..='9') => self.parse_number(),
+            Some(_) => {
+                for (a_word, a_value) in [("null", JsonValue::Null), ("true", JsonValue::Bool(true)), ("false", JsonValue::Bool(false))] {
+                    let word_chars: Vec<char> = a_word.chars().collect();
+                    if self.json_chars[self.pos..].starts_with(&word_chars) {
+                        self.pos += word_chars.len();
+                        return Ok(a_value);
+                    }
+                }
+                Err(format!("unexpected '{}' where a value should start", self.peek().unwrap_or_default()))
+            }
+            None => Err("the document ended where a value should start".to_string()),
+        }
+    }
+
+    fn parse_object(&mut self, depth: usize) -> Result<JsonValue, String> {
+        self.expect('{')?;
+        let mut fields: Vec<(String, JsonValue)> = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(JsonValue::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err("expected a field name in double quotes".to_string());
+            }
+            let field_name = self.parse_string()?;
+            if fields.iter().any(|(a_key, _)| *a_key == field_name) {
+                return Err(format!("field \"{}\" appears more than once", field_name));
+            }
+            self.skip_whitespace();
+            self.expect(':')?;
+            let field_value = self.parse_value(depth + 1)?;
+            fields.push((field_name, field_value));
+            self.skip_whitespace();
+            match self.next_char() {
+                Some(',') => continue,
+                Some('}') => return Ok(JsonValue::Object(fields)),
+                Some(a_char) => return Err(format!("expected ',' or '}}' but found '{}'", a_char)),
+                None => return Err("the document ended inside an object".to_string()),
+            }
+        }
+    }
+
+    fn parse_array(&mut self, depth: usize) -> Result<JsonValue, String> {
+        self.expect('[')?;
+        let mut items: Vec<JsonValue> = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.parse_value(depth + 1)?);
+            self.skip_whitespace();
+            match self.next_char() {
+                Some(',') => continue,
+                Some(']') => return Ok(JsonValue::Array(items)),
+                Some(a_char) => return Err(format!("expected ',' or ']' but found '{}'", a_char)),
+                None => return Err("the document ended inside an array".to_string()),
+            }
+        }
+    }
+
+    //four hex digits of a \u escape
+    fn parse_hex4(&mut self) -> Result<u32, String> {
+        let mut code_point: u32 = 0;
+        for _ in 0..4 {
+            let hex_digit = self.next_char().and_then(|a_char| a_char.to_digit(16)).ok_or("expected four hex digits after \\u")?;
+            code_point = code_point * 16 + hex_digit;
+        }
+        Ok(code_point)
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut parsed = String::new();
+        loop {
+            match self.next_char() {
+                Some('"') => return Ok(parsed),
+                Some('\\') => match self.next_char() {
+                    Some('"') => parsed.push('"'),
+                    Some('\\') => parsed.push('\\'),
+                    Some('/') => parsed.push('/'),
+                    Some('b') => parsed.push('\u{8}'),
+                    Some('f') => parsed.push('\u{c}'),
+                    Some('n') => parsed.push('\n'),
+                    Some('r') => parsed.push('\r'),
+                    Some('t') => parsed.push('\t'),
+                    Some('u') => {
+                        let mut code_point = self.parse_hex4()?;
+                        //characters outside the basic plane are written as a surrogate pair
+                        if (0xD800..0xDC00).contains(&code_point) {
+                            if self.next_char() != Some('\\') || self.next_char() != Some('u') {
+                                return Err("expected the second half of a surrogate pair".to_string());
+                            }
+                            let low_half = self.parse_hex4()?;
+                            if !(0xDC00..0xE000).contains(&low_half) {
+                                return Err("expected the second half of a surrogate pair".to_string());
+                            }
+                            code_point = 0x10000 + ((code_point - 0xD800) << 10) + (low_half - 0xDC00);
+                        }
+                        parsed.push(char::from_u32(code_point).ok_or("\\u escape is not a character")?);
+                    }
+                    Some(a_char) => return Err(format!("unknown escape '\\{}'", a_char)),
+                    None => return Err("the document ended inside a string".to_string()),
+                },
+                Some(a_control) if (a_control as u32) < 0x20 => return Err("control characters must be escaped inside a string".to_string()),
+                Some(a_char) => parsed.push(a_char),
+                None => return Err("the document ended inside a string".to_string()),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue, String> {
+        let start_pos = self.pos;
+        if self.peek() == Some('-') {
+            self.pos += 1;
+        }
+        while self.peek().is_some_and(|a_char| a_char.is_ascii_digit() || a_char == '.' || a_char == 'e' || a_char == 'E' || a_char == '+' || a_char == '-') {
+            self.pos += 1;
+        }
+        let number_text: String = self.json_chars[start_pos..self.pos].iter().collect();
+        match number_text.parse::<f64>() {
+            Ok(a_number) if a_number.is_finite() => Ok(JsonValue::Number(a_number)),
+            _ => {
+                self.pos = start_pos;
+                Err(format!("'{}' is not a number", number_text))
+            }
+        }
+    }
+}
+
+//quotes a string for JSON, escaping quotes, backslashes and control characters
+pub fn escape_json(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for a_char in text.chars() {
+        match a_char {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            a_control if (a_control as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", a_control as u32)),
+            _ => escaped.push(a_char),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
@@ -0,0 +1,155 @@
+//line_num.rs declares LineNum, the number of a line in a part file. Besides whole numbers a line can be numbered 12.1, 12.2 and so on to go between
+//lines 12 and 13 without renumbering every later line, and 12.1.1 to go between 12.1 and 12.2. Johnny Huang, Aman Verma, Hanson Li
+
+use super::json::JsonValue;
+use std::fmt;
+
+pub const SUB_NUMBER_DELIM: char = '.';
+pub const MAX_NUMBER_LEVELS: usize = 4; //the whole line number and up to three levels of sub-numbers, 12.1.1.1
+pub const LINE_NUMBERS_OPTION: &str = "line-numbers"; //export --line-numbers as-is|flat
+pub const AS_IS_NUMBERS: &str = "as-is";
+pub const FLAT_NUMBERS: &str = "flat";
+
+//levels past the last sub-number are 0, so comparing the levels in order sorts 12 < 12.1 < 12.1.1 < 12.2 < 13
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineNum([usize; MAX_NUMBER_LEVELS]);
+
+impl LineNum {
+    //parses 12 or 12.1. Sub-numbers count from 1, so 12.0 and 12. are not line numbers
+    pub fn parse(num_text: &str) -> Option<Self> {
+        let mut levels = [0; MAX_NUMBER_LEVELS];
+        for (level_idx, level_text) in num_text.split(SUB_NUMBER_DELIM).enumerate() {
+            if level_idx >= MAX_NUMBER_LEVELS || (level_idx > 0 && !level_text.chars().all(|a_char| a_char.is_ascii_digit())) {
+                return None;
+            }
+            levels[level_idx] = level_text.parse::<usize>().ok()?;
+            if level_idx > 0 && levels[level_idx] == 0 {
+                return None;
+            }
+        }
+        Some(Self(levels))
+    }
+
+    //the levels as they were written, 12.1 gives [12, 1]
+    pub fn levels(&self) -> &[usize] {
+        let level_count = self.0.iter().skip(1).position(|a_level| *a_level == 0).map_or(MAX_NUMBER_LEVELS, |zero_idx| zero_idx + 1);
+        &self.0[..level_count]
+    }
+
+    //the line from its levels as levels() gives them, None if there are too many or a sub-number is 0
+    pub fn from_levels(levels: &[usize]) -> Option<Self> {
+        if levels.is_empty() || levels.len() > MAX_NUMBER_LEVELS || levels[1..].contains(&0) {
+            return None;
+        }
+        let mut all_levels = [0; MAX_NUMBER_LEVELS];
+        all_levels[..levels.len()].copy_from_slice(levels);
+        Some(Self(all_levels))
+    }
+
+    //the last number that line whole_num or one of its sub-lines can have, so a range ending at it takes in 12.1 and 12.2 as well as 12
+    pub fn through_sub_lines(whole_num: usize) -> Self {
+        let mut levels = [usize::MAX; MAX_NUMBER_LEVELS];
+        levels[0] = whole_num;
+        Self(levels)
+    }
+
+    //the whole line number, 12 for 12.1
+    pub fn whole(&self) -> usize {
+        self.0[0]
+    }
+
+    pub fn is_sub_line(&self) -> bool {
+        self.0[1] != 0
+    }
+
+    //a whole line number as a JSON number, and a sub-line as a JSON string since 12.10 is not the number 12.1
+    pub fn to_json(&self) -> JsonValue {
+        if self.is_sub_line() {
+            JsonValue::from_text(&self.to_string())
+        } else {
+            JsonValue::from_usize(self.whole())
+        }
+    }
+
+    pub fn from_json(line_json: &JsonValue) -> Option<Self> {
+        match line_json.as_usize() {
+            Some(whole_num) => Some(Self::from(whole_num)),
+            None => Self::parse(line_json.as_str()?),
+        }
+    }
+}
+
+impl From<usize> for LineNum {
+    fn from(whole_num: usize) -> Self {
+        let mut levels = [0; MAX_NUMBER_LEVELS];
+        levels[0] = whole_num;
+        Self(levels)
+    }
+}
+
+impl fmt::Display for LineNum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let level_texts: Vec<String> = self.levels().iter().map(|a_level| a_level.to_string()).collect();
+        write!(f, "{}", level_texts.join(&SUB_NUMBER_DELIM.to_string()))
+    }
+}
+
+//whole line numbers for a fragment whose lines are numbered with sub-lines: each sub-line takes the next whole number and every line after it moves
+//down by one, so 12, 12.1, 12.2, 13 becomes 12, 13, 14, 15. sub_lines are the distinct sub-line numbers of the fragment. A fragment without
+//sub-lines keeps its numbers
+pub fn flat_line_num(line_num: LineNum, sub_lines: &[LineNum]) -> usize {
+    line_num.whole() + sub_lines.iter().filter(|a_sub_line| **a_sub_line <= line_num).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(num_text: &str) -> LineNum {
+        LineNum::parse(num_text).unwrap()
+    }
+
+    #[test]
+    fn sub_lines_sort_between_whole_lines() {
+        let in_order = ["12", "12.1", "12.1.1", "12.1.1.1", "12.1.2", "12.2", "12.10", "13"];
+        for pair in in_order.windows(2) {
+            assert!(line(pair[0]) < line(pair[1]), "{} should come before {}", pair[0], pair[1]);
+        }
+        assert_eq!(line("12"), LineNum::from(12));
+        assert!(line("12.9") < LineNum::through_sub_lines(12));
+        assert!(LineNum::through_sub_lines(12) < line("13"));
+    }
+
+    #[test]
+    fn parse_rejects_zero_and_missing_sub_numbers() {
+        for bad_text in ["12.0", "12.", ".1", "12.1.0", "1.2.3.4.5", "12.+1", "x", ""] {
+            assert_eq!(LineNum::parse(bad_text), None, "{} should not parse", bad_text);
+        }
+    }
+
+    #[test]
+    fn display_and_levels_give_back_what_was_parsed() {
+        for num_text in ["0", "12", "12.1", "12.10", "3.1.4.1"] {
+            assert_eq!(line(num_text).to_string(), num_text);
+            assert_eq!(LineNum::from_levels(line(num_text).levels()), Some(line(num_text)));
+        }
+        assert!(line("12.1").is_sub_line());
+        assert!(!line("12").is_sub_line());
+        assert_eq!(line("12.1.1").whole(), 12);
+    }
+
+    #[test]
+    fn json_keeps_sub_lines_as_text() {
+        for num_text in ["7", "12.1", "12.10"] {
+            assert_eq!(LineNum::from_json(&line(num_text).to_json()), Some(line(num_text)));
+        }
+    }
+
+    #[test]
+    fn flat_numbers_move_later_lines_down() {
+        let sub_lines = [line("12.1"), line("12.2")];
+        let flattened: Vec<usize> = ["11", "12", "12.1", "12.2", "13", "20"].iter().map(|num_text| flat_line_num(line(num_text), &sub_lines)).collect();
+        assert_eq!(flattened, vec![11, 12, 13, 14, 15, 22]);
+        assert_eq!(flat_line_num(line("5"), &[]), 5);
+    }
+}
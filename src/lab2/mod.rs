@@ -1,7 +1,30 @@
 //mod.rs exposes our modules. Aman Verma Johnny Huang Hanson Li
+pub mod actors;
+pub mod cache;
+pub mod cast;
+pub mod cmd_line;
+pub mod concordance;
 pub mod declarations;
+pub mod diff;
+pub mod doubling;
+pub mod draft;
+pub mod excerpt;
+pub mod fountain;
+pub mod gutenberg;
+pub mod json;
+pub mod line_num;
 pub mod script_gen;
 pub mod play;
+pub mod play_json;
+pub mod personae;
 pub mod player;
+pub mod renumber;
 pub mod return_wrapper;
-pub mod scene_fragments;
\ No newline at end of file
+pub mod scene_fragments;
+pub mod scene_meta;
+pub mod search;
+pub mod ssml;
+pub mod subtitles;
+pub mod tokens;
+pub mod variables;
+pub mod watch;
\ No newline at end of file
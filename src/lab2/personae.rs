@@ -0,0 +1,66 @@
+//personae.rs declares the Persona struct summarizing one character across the whole play, and prints the dramatis personae list shown before the first scene. Hanson Li, Johnny Huang, Aman Verma
+
+use std::io::{self, Write};
+
+pub const PERSONAE_HEADING: &str = "Dramatis Personae";
+pub const FIRST_APPEARANCE_ORDER: &str = "first";
+pub const LINE_COUNT_ORDER: &str = "lines";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PersonaeOrder {
+    FirstAppearance, //order in which characters are first on stage
+    LineCount,       //most lines first, ties broken by first appearance
+}
+
+impl PersonaeOrder {
+    pub fn from_name(order_name: &str) -> Option<Self> {
+        match order_name {
+            FIRST_APPEARANCE_ORDER => Some(PersonaeOrder::FirstAppearance),
+            LINE_COUNT_ORDER => Some(PersonaeOrder::LineCount),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Persona {
+    pub char_key: String,
+    pub display_name: String,
+    pub description: String,
+    pub first_appearance: (usize, usize), //fragment index and position among that fragment's characters
+    pub line_count: usize,                //lines spoken, shared lines count for every member
+    pub scenes: Vec<String>,              //titles of the scenes the character appears in, in play order
+}
+
+//sorts the collected personae in place
+pub fn order_personae(personae: &mut [Persona], order: PersonaeOrder) {
+    match order {
+        PersonaeOrder::FirstAppearance => personae.sort_by_key(|a_persona| a_persona.first_appearance),
+        PersonaeOrder::LineCount => personae.sort_by(|a_persona, b_persona| {
+            b_persona.line_count.cmp(&a_persona.line_count).then(a_persona.first_appearance.cmp(&b_persona.first_appearance))
+        }),
+    }
+}
+
+//one character's entry in the cast list, e.g. "Polonius, Lord Chamberlain, father of Ophelia (52 lines)". Every export writes it this way
+pub fn persona_line(a_persona: &Persona) -> String {
+    if a_persona.description.is_empty() {
+        format!("{} ({} lines)", a_persona.display_name, a_persona.line_count)
+    } else {
+        format!("{}, {} ({} lines)", a_persona.display_name, a_persona.description, a_persona.line_count)
+    }
+}
+
+//prints the cast list in the same plain text style as the recital
+pub fn print_personae(personae: &[Persona]) {
+    let mut stdout = io::stdout().lock();
+
+    let _ = writeln!(stdout,"{:?}", PERSONAE_HEADING);
+    for a_persona in personae.iter() {
+        let _ = writeln!(stdout,"{}", persona_line(a_persona));
+        for a_scene in a_persona.scenes.iter() {
+            let _ = writeln!(stdout,"    {}", a_scene);
+        }
+    }
+    let _ = writeln!(stdout); //new line to separate the first scene
+}
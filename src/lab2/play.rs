@@ -1,10 +1,19 @@
 //play.rs declares the Play struct that holds vector of SceneFragments. It contains associated functions for processing the script files and structuring the line delivery. Hanson Li, Johnny Haung, Aman Verma
 
 use super::scene_fragments::SceneFragment;
-use super::declarations::{WHINGE,GENERATION_FAILURE};
+use super::cast::Cast;
+use super::excerpt::Excerpt;
+use super::personae::{Persona, PersonaeOrder, order_personae};
+use super::scene_meta::{Act, Scene, SceneMeta, parse_numeral, to_roman};
+use super::declarations::{WHINGE,GENERATION_FAILURE,USE_CACHE};
+use super::cache::{load_cached_play, write_play_cache};
 use std::sync::atomic::Ordering;
-use super::script_gen::grab_trimmed_file_lines;
+use super::script_gen::{any_decode_fallback, grab_file_lines};
+use super::tokens::{LineToken, TokenError, split_tokens};
+use super::variables::{SET_DIRECTIVE, SET_END, is_valid_name, reset_script_values, set_value, substitute_token};
+use std::fs;
 use std::io::{self, Write};
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 
 pub const TITLE_IDX: usize = 0;             //index of the line giving the title of the play
@@ -14,22 +23,132 @@ pub const FILE_NAME_TOKEN_POS: usize = 1;      //index of the file containing th
 pub const EXPECTED_TOKENS: usize = 2;       //expected number of tokens in a character line
 
 
-pub type ScriptConfig = Vec<(bool, String)>; 
+pub const SCENE_DIRECTIVE: &str = "[scene]";
+pub const CAST_DIRECTIVE: &str = "[cast]";
+pub const TITLE_DIRECTIVE: &str = "[title]";
+pub const ACT_DIRECTIVE: &str = "[act]";
+pub const LOCATION_DIRECTIVE: &str = "[location]";
+pub const AUTHOR_DIRECTIVE: &str = "[author]";
+pub const INCLUDE_DIRECTIVE: &str = "[include]";
+pub const INCLUDE_CHAIN_DELIM: &str = " -> "; //between the files of an include cycle when it is reported
+
+//one meaningful line of the script file
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptDirective {
+    SceneTitle(String),  //[scene] title for the fragments that follow
+    ConfigFile(String),  //config file for a new fragment
+    CastFile(String),    //[cast] file with display names for the characters
+    PlayTitle(String),   //[title] of the play, kept for every scene that follows
+    Act(String),         //[act] numeral, kept for every scene that follows
+    Location(String),    //[location] of the next scene only
+    Author(String),      //[author] of the play, kept for every scene that follows
+    Include(String),     //[include] another script file, replaced by its directives while the script is read
+}
+
+pub type ScriptConfig = Vec<ScriptDirective>; 
 pub type Fragments = Vec<Arc<Mutex<SceneFragment>>>; 
+pub type PlayCounts = (usize, usize, usize, usize); //scenes, fragments, characters and lines
 
 
 pub struct Play{
-    fragments: Fragments
+    fragments: Fragments, //every fragment in script order
+    acts: Vec<Act>,       //the same fragments grouped into acts and scenes
+    cast: Cast,
+    script_file: String,  //script file the play was read from, empty for a play built in memory
+    includes: Vec<String>, //script files pulled in with [include], in the order they were first read
 }
 
 impl Play{
     pub fn new() -> Self {
         Self {
             fragments: Vec::new(),
+            acts: Vec::new(),
+            cast: Cast::new(),
+            script_file: String::new(),
+            includes: Vec::new(),
         }
     }
 
 
+    pub fn fragments(&self) -> &Fragments {
+        &self.fragments
+    }
+
+    pub fn cast(&self) -> &Cast {
+        &self.cast
+    }
+
+    pub fn acts(&self) -> &[Act] {
+        &self.acts
+    }
+
+    pub fn script_file(&self) -> &str {
+        &self.script_file
+    }
+
+    pub fn includes(&self) -> &[String] {
+        &self.includes
+    }
+
+    //gives a play built in memory the cast and script file it was originally read from
+    pub fn set_provenance(&mut self, script_file: &str, cast: Cast) {
+        self.script_file = script_file.to_string();
+        self.cast = cast;
+    }
+
+    //starts a new scene in the tree, and a new act if the act number changed
+    fn add_scene(&mut self, scene_meta: SceneMeta) {
+        let starts_new_act = match self.acts.last() {
+            Some(last_act) => last_act.act != scene_meta.act,
+            None => true,
+        };
+        if starts_new_act {
+            self.acts.push(Act { act: scene_meta.act, scenes: Vec::new() });
+        }
+        let first_fragment_idx = self.fragments.len();
+        if let Some(cur_act) = self.acts.last_mut() {
+            cur_act.scenes.push(Scene { meta: scene_meta, fragments: Vec::new(), first_fragment_idx });
+        }
+    }
+
+    //starts a scene built in memory, e.g. by an importer, rather than read from a script file
+    pub fn push_scene(&mut self, scene_meta: SceneMeta) {
+        self.add_scene(scene_meta);
+    }
+
+    //adds a fragment built in memory to the last scene, its players are prepared the same way as ones read from a config file.
+    //like a fragment read from a script, only the first fragment of a scene carries the scene title
+    pub fn push_fragment(&mut self, mut fragment: SceneFragment) {
+        if self.acts.is_empty() {
+            self.add_scene(SceneMeta::default());
+        }
+        fragment.prepare_players();
+        let fragment_ref = Arc::new(Mutex::new(fragment));
+        if let Some(cur_scene) = self.acts.last_mut().and_then(|cur_act| cur_act.scenes.last_mut()) {
+            if let Ok(mut scene_ref) = fragment_ref.lock() {
+                scene_ref.scene_title = if cur_scene.fragments.is_empty() { cur_scene.meta.full_title.to_string() } else { String::new() };
+            }
+            cur_scene.fragments.push(Arc::clone(&fragment_ref));
+        }
+        self.fragments.push(fragment_ref);
+    }
+
+    //runs the checks prepare makes after reading a script, for a play built with push_scene and push_fragment
+    pub fn finish_import(&mut self) -> Result<(), u8> {
+        let mut stderr = io::stderr().lock();
+        if self.fragments.is_empty() {
+            let _ = writeln!(stderr,"Error: the imported play has no lines to recite.");
+            return Err(GENERATION_FAILURE);
+        }
+        if self.acts.first().and_then(|first_act| first_act.scenes.first()).is_none_or(|first_scene| first_scene.meta.full_title.split_whitespace().next().is_none()) {
+            let _ = writeln!(stderr, "Error: First scene must have a title.");
+            return Err(GENERATION_FAILURE);
+        }
+        self.check_cast();
+        self.check_sequence();
+        Ok(())
+    }
+
     //the process_config function here reads in the script.txt file, iterate through the scene title and listed config file paths, and call SceneFragment's prepare function on the config file paths.
     pub fn process_config(&mut self, play_cfg: &ScriptConfig) -> Result<(), u8> {
         //variable to keep track of index of fragment in self.fragments after insertion
@@ -37,16 +156,78 @@ impl Play{
         let mut title_str = String::new();
         let mut stderr = io::stderr().lock();
 
+        //explicit directives override what is parsed from a [scene] title. Title, author and act carry over to later scenes, location does not
+        let mut title_directive: Option<String> = None;
+        let mut author_directive: Option<String> = None;
+        let mut act_directive: Option<usize> = None;
+        let mut location_directive: Option<String> = None;
+        let mut last_meta = SceneMeta::default();
+
         //note: iter yeilds immutable refs in rusts
         for a_cfg in play_cfg.iter() {
-            //example from Expressions slide: match t {(x, y) => do_func(x,y);}
-            match a_cfg {(is_title, text_field) => { //both is_title and text_field are refs
-                if *is_title{
+            match a_cfg {
+                ScriptDirective::SceneTitle(text_field) => {
                     title_str = text_field.to_string();
-                }else{
+
+                    let mut scene_meta = SceneMeta::from_title(text_field);
+                    if let Some(play_title) = title_directive.as_ref() {
+                        scene_meta.play_title = play_title.to_string();
+                    } else if scene_meta.play_title.is_empty() {
+                        scene_meta.play_title = last_meta.play_title.to_string();
+                    }
+                    if let Some(author) = author_directive.as_ref() {
+                        scene_meta.set_author(author);
+                    } else if scene_meta.author.is_empty() {
+                        scene_meta.author = last_meta.author.to_string();
+                    }
+                    if let Some(act_num) = act_directive.take() {
+                        scene_meta.act = Some(act_num);
+                    } else if scene_meta.act.is_none() {
+                        scene_meta.act = last_meta.act;
+                    }
+                    if let Some(location) = location_directive.take() {
+                        scene_meta.location = location;
+                    }
+
+                    last_meta = scene_meta.clone();
+                    self.add_scene(scene_meta);
+                },
+                ScriptDirective::PlayTitle(text_field) => {
+                    title_directive = Some(text_field.to_string());
+                },
+                ScriptDirective::Author(text_field) => {
+                    author_directive = Some(text_field.to_string());
+                },
+                ScriptDirective::Location(text_field) => {
+                    location_directive = Some(text_field.to_string());
+                },
+                ScriptDirective::Act(text_field) => {
+                    //add_config has already whinged about an [act] that is not a numeral, naming the file it is in
+                    if let Some(act_num) = parse_numeral(text_field) {
+                        act_directive = Some(act_num);
+                    }
+                },
+                ScriptDirective::Include(_) => {
+                    //read_config replaces every [include] with the included directives, so there are none left here
+                },
+                ScriptDirective::CastFile(text_field) => {
+                    if let Err(e_code) = self.cast.prepare(text_field) {
+                        let _ = writeln!(stderr,"Error from process config of Play after reading cast file: {}", e_code);
+                        return Err(GENERATION_FAILURE);
+                    }
+                },
+                ScriptDirective::ConfigFile(text_field) => {
+                    //a config before any [scene] still needs a scene to belong to, prepare reports the missing title
+                    if self.acts.is_empty() {
+                        self.add_scene(SceneMeta::default());
+                    }
+
                     //1: push title_str init SceneFrag to vec of fragments and update idx
                     self.fragments.push(Arc::new(Mutex::new(SceneFragment::new(&title_str))));
                     latest_frag_idx = self.fragments.len() - 1;
+                    if let Some(cur_scene) = self.acts.last_mut().and_then(|cur_act| cur_act.scenes.last_mut()) {
+                        cur_scene.fragments.push(Arc::clone(&self.fragments[latest_frag_idx]));
+                    }
                     //2: reset title_str to empty
                     title_str = String::new();
                     //3: pass text_field to new fragment prepare method method and 4 match result on errors
@@ -72,77 +253,193 @@ impl Play{
                         }
                     }
                     
-                }
-            }}
+                },
+            }
         }
         Ok (())
     }
 
-    //for a line in the script file, determine if it is a scene line or a line with config file paths and add a bool val to the vector of lines
-    pub fn add_config(&self, cfg_line: &String, play_cfg: &mut ScriptConfig){
-        //split_whitespace gives an iterable, and collect turns that into a collection
-
-        let cfg_items: Vec<&str> = cfg_line.split_whitespace().collect(); 
+    //for a line in the script file, determine if it is a scene line or a line with config file paths and add a bool val to the vector of lines.
+    //line_source says where the line is, e.g. "./data/act_1.txt line 3", since with [include] a play is read from several files.
+    //Err with the column if the line cannot be split into tokens or uses a ${NAME} that is not defined
+    pub fn add_config(&self, cfg_line: &String, play_cfg: &mut ScriptConfig, line_source: &str) -> Result<(), TokenError> {
+        //split_tokens drops comments and resolves quotes and escapes, so a quoted path can have spaces in it
+        let cfg_tokens = split_tokens(cfg_line)?;
+        let cfg_items: Vec<&str> = cfg_tokens.iter().map(|(token_text, _)| token_text.as_str()).collect();
         let mut stderr = io::stderr().lock();
 
         if cfg_items.is_empty() {
-            return;
+            return Ok(());
         }
 
-        if cfg_items[0] == "[scene]" {
+        if cfg_items[0] == SET_DIRECTIVE {
+            //[set NAME value], the value is the rest of the line up to the closing ]
+            let mut set_tokens: Vec<LineToken> = cfg_tokens[1..].to_vec();
+            match set_tokens.last_mut() {
+                Some((last_text, _)) if last_text.ends_with(SET_END) => {
+                    last_text.pop();
+                }
+                _ => return Err((cfg_tokens[0].1, format!("{} is never closed with {}", SET_DIRECTIVE, SET_END))),
+            }
+            set_tokens.retain(|(token_text, _)| !token_text.is_empty());
+            let Some((var_name, name_column)) = set_tokens.first() else {
+                return Err((cfg_tokens[0].1, format!("{} needs a variable name and a value", SET_DIRECTIVE)));
+            };
+            if !is_valid_name(var_name) {
+                return Err((*name_column, format!("'{}' is not a variable name, names are letters, digits and _", var_name)));
+            }
+            if set_tokens.len() == 1 {
+                return Err((*name_column, format!("{} {} is missing its value", SET_DIRECTIVE, var_name)));
+            }
+            let mut value_parts: Vec<String> = Vec::new();
+            for a_token in set_tokens[1..].iter() {
+                value_parts.push(substitute_token(a_token)?);
+            }
+            set_value(var_name, &value_parts.join(" "));
+        } else if cfg_items[0] == SCENE_DIRECTIVE {
             if cfg_items.len() == 1 {
                 //[scene] alone, skip line and whinge
                 if WHINGE.load(Ordering::SeqCst) {
-                    let _ = writeln!(stderr,"Whinge Warning: [scene] directive missing title");
+                    let _ = writeln!(stderr,"Whinge Warning: {}: [scene] directive missing title", line_source);
                 }
             } else {
                 //contains other tokens with [scene], concat from 1st element and up
                 let scene_title = cfg_items[1..].join(" ");
-                play_cfg.push((true, scene_title));
+                play_cfg.push(ScriptDirective::SceneTitle(scene_title));
+            }
+        } else if cfg_items[0] == CAST_DIRECTIVE || cfg_items[0] == ACT_DIRECTIVE || cfg_items[0] == INCLUDE_DIRECTIVE {
+            //directives taking a single token
+            if cfg_items.len() == 1 {
+                if WHINGE.load(Ordering::SeqCst) {
+                    let _ = writeln!(stderr,"Whinge Warning: {}: {} directive missing its value", line_source, cfg_items[0]);
+                }
+            } else {
+                let directive_value = cfg_items[1].to_string();
+                match cfg_items[0] {
+                    CAST_DIRECTIVE => play_cfg.push(ScriptDirective::CastFile(substitute_token(&cfg_tokens[1])?)),
+                    INCLUDE_DIRECTIVE => play_cfg.push(ScriptDirective::Include(substitute_token(&cfg_tokens[1])?)),
+                    _ => {
+                        if parse_numeral(&directive_value).is_none() && WHINGE.load(Ordering::SeqCst) {
+                            let _ = writeln!(stderr,"Whinge Warning: {}: [act] '{}' is not a roman or arabic numeral, ignoring it", line_source, directive_value);
+                        }
+                        play_cfg.push(ScriptDirective::Act(directive_value));
+                    }
+                }
+                if cfg_items.len() > 2 && WHINGE.load(Ordering::SeqCst) {
+                    let _ = writeln!(stderr,"Whinge Warning: {}: there are additional tokens after {} '{}'", line_source, cfg_items[0], cfg_items[1]);
+                }
+            }
+        } else if cfg_items[0] == TITLE_DIRECTIVE || cfg_items[0] == LOCATION_DIRECTIVE || cfg_items[0] == AUTHOR_DIRECTIVE {
+            //directives whose value is the rest of the line
+            if cfg_items.len() == 1 {
+                if WHINGE.load(Ordering::SeqCst) {
+                    let _ = writeln!(stderr,"Whinge Warning: {}: {} directive missing its value", line_source, cfg_items[0]);
+                }
+            } else {
+                let directive_value = cfg_items[1..].join(" ");
+                match cfg_items[0] {
+                    TITLE_DIRECTIVE => play_cfg.push(ScriptDirective::PlayTitle(directive_value)),
+                    LOCATION_DIRECTIVE => play_cfg.push(ScriptDirective::Location(directive_value)),
+                    _ => play_cfg.push(ScriptDirective::Author(directive_value)),
+                }
             }
         } else {
             //config file case
             //since using &str, need to do .to_string when inserting into play_cfg because it is of type <String, String>
-            play_cfg.push((false, cfg_items[0].to_string()));
+            play_cfg.push(ScriptDirective::ConfigFile(substitute_token(&cfg_tokens[0])?));
             
             if cfg_items.len() > 1 && WHINGE.load(Ordering::SeqCst) {
-                let _ = writeln!(stderr,"Whinge Warning: there are additional tokens after config file name '{}'", cfg_items[0]);
+                let _ = writeln!(stderr,"Whinge Warning: {}: there are additional tokens after config file name '{}'", line_source, cfg_items[0]);
             }
         }
+        Ok(())
     }
     
-    //read in the script file with grab_trimmed_file_lines function 
-    pub fn read_config(&self, cfg_fname: &String, play_cfg: &mut ScriptConfig) -> Result<(), u8> {
-        //play_title param is now a struct attribute self.scene_title
+    //read in the script file with grab_file_lines, replacing each [include] with the directives of the file it names
+    pub fn read_config(&mut self, cfg_fname: &String, play_cfg: &mut ScriptConfig) -> Result<(), u8> {
+        let mut include_chain: Vec<(String, String)> = Vec::new();
+        self.read_script_file(cfg_fname, play_cfg, &mut include_chain)
+    }
+
+    //an included path is relative to the directory of the file including it, unless it is absolute
+    fn include_path(including_fname: &str, include_fname: &str) -> String {
+        match Path::new(including_fname).parent() {
+            Some(parent_dir) if Path::new(include_fname).is_relative() => parent_dir.join(include_fname).display().to_string(),
+            _ => include_fname.to_string(),
+        }
+    }
+
+    //include_chain holds each file being read, as its canonical path and the name it was given, from the script down to this file
+    fn read_script_file(&mut self, cfg_fname: &String, play_cfg: &mut ScriptConfig, include_chain: &mut Vec<(String, String)>) -> Result<(), u8> {
         let mut stderr = io::stderr().lock();
 
+        //files are compared by canonical path, so ./data/act_1.txt and data/act_1.txt are the same file
+        let file_key = fs::canonicalize(cfg_fname).map(|a_path| a_path.display().to_string()).unwrap_or_else(|_| cfg_fname.to_string());
+        if include_chain.iter().any(|(chain_key, _)| *chain_key == file_key) {
+            let mut chain_names: Vec<&str> = include_chain.iter().map(|(_, chain_name)| chain_name.as_str()).collect();
+            chain_names.push(cfg_fname);
+            let _ = writeln!(stderr,"Error: include cycle: {}", chain_names.join(INCLUDE_CHAIN_DELIM));
+            return Err(GENERATION_FAILURE);
+        }
+
         let mut cfg_lines: Vec<String> = Vec::new();
-        match grab_trimmed_file_lines(&cfg_fname, &mut cfg_lines) {
+        match grab_file_lines(&cfg_fname, &mut cfg_lines) {
             Ok(_) => {//don't really need to read the ok code so use _
                 if cfg_lines.is_empty() {
                     let _ = writeln!(stderr,"Error: no lines read from script file '{}'", cfg_fname);
                     return Err(GENERATION_FAILURE);
                 }
- 
-                for a_cfg_line in cfg_lines.iter() {
-                    //iter should already make a_cfg_line of &String type
-
-                    self.add_config(a_cfg_line, play_cfg)
-                }
             },
             Err(_) => {
                 let _ = writeln!(stderr,"Error: could not open or read script file '{}'", cfg_fname);
                 return Err(GENERATION_FAILURE);
             }
         }
+        include_chain.push((file_key, cfg_fname.to_string()));
+
+        for (line_idx, a_cfg_line) in cfg_lines.iter().enumerate() {
+            //iter should already make a_cfg_line of &String type
+            let line_source = format!("{} line {}", cfg_fname, line_idx + 1);
+            if let Err((column, problem)) = self.add_config(a_cfg_line, play_cfg, &line_source) {
+                let _ = writeln!(stderr,"Error: {} column {}: {}", line_source, column, problem);
+                return Err(GENERATION_FAILURE);
+            }
+
+            //the included file's directives go where the [include] was
+            if let Some(ScriptDirective::Include(include_fname)) = play_cfg.last().cloned() {
+                play_cfg.pop();
+                let include_fname = Self::include_path(cfg_fname, &include_fname);
+                if !self.includes.contains(&include_fname) {
+                    self.includes.push(include_fname.to_string());
+                }
+                if let Err(e_code) = self.read_script_file(&include_fname, play_cfg, include_chain) {
+                    let _ = writeln!(stderr,"Error: in {}, [include] {} failed with error code {}", line_source, include_fname, e_code);
+                    return Err(GENERATION_FAILURE);
+                }
+            }
+        }
+        include_chain.pop();
         Ok(())
     }
 
     //calls the read_config and process_config in order 
     pub fn prepare(&mut self, cfg_fname: &String) -> Result<(), u8> {
+        //a cache left by an earlier run is used while none of the files it was read from has changed.
+        //with whinge on the files are always read, since the warnings come from reading them
+        let use_cache = USE_CACHE.load(Ordering::SeqCst);
+        if use_cache && !WHINGE.load(Ordering::SeqCst) {
+            if let Some(cached_play) = load_cached_play(cfg_fname) {
+                *self = cached_play;
+                return Ok(());
+            }
+        }
+
         //change the original script gen params: play_title: &mut String, play_vec: &mut Play to fields from Play struct
         let mut playcfg_var = ScriptConfig::new();
         let mut stderr = io::stderr().lock();
+        self.script_file = cfg_fname.to_string();
+        self.includes.clear();
+        reset_script_values();
 
         if let Err(e_code) = self.read_config(cfg_fname, &mut playcfg_var) {
             // read_config now prints its own errors, but we still need to stop execution
@@ -175,16 +472,213 @@ impl Play{
             }
         }
 
+        self.check_cast();
+        self.check_sequence();
+        //a cache that cannot be written only costs the next run its speed up. A play with lines that were not UTF-8 is not cached,
+        //so the warning naming them is printed every run until the files are fixed
+        if use_cache && !any_decode_fallback() && write_play_cache(self).is_err() && WHINGE.load(Ordering::SeqCst) {
+            let _ = writeln!(stderr,"Whinge Warning: could not write the cache for script file '{}'", cfg_fname);
+        }
         Ok(())
     }
 
+    //whinges about acts and scenes that go backwards, repeat or skip a number
+    fn check_sequence(&self) {
+        if !WHINGE.load(Ordering::SeqCst) {
+            return;
+        }
+        let mut stderr = io::stderr().lock();
+        let mut prev_act: Option<usize> = None;
+
+        for (act_idx, an_act) in self.acts.iter().enumerate() {
+            if let (Some(prev_num), Some(cur_num)) = (prev_act, an_act.act) {
+                if cur_num <= prev_num {
+                    let _ = writeln!(stderr,"Whinge Warning: act {} comes after act {}, acts are out of sequence", to_roman(cur_num), to_roman(prev_num));
+                } else if cur_num > prev_num + 1 {
+                    let _ = writeln!(stderr,"Whinge Warning: act {} follows act {}, skipping act {}", to_roman(cur_num), to_roman(prev_num), to_roman(prev_num + 1));
+                }
+            }
+            if an_act.act.is_some() {
+                prev_act = an_act.act;
+            }
+
+            let mut prev_scene: Option<usize> = None;
+            for a_scene in an_act.scenes.iter() {
+                let act_label = an_act.act.map(to_roman).unwrap_or_default();
+                match (prev_scene, a_scene.meta.scene) {
+                    (Some(prev_num), Some(cur_num)) if cur_num <= prev_num => {
+                        let _ = writeln!(stderr,"Whinge Warning: scene {} comes after scene {} in act {}, scenes are out of sequence", cur_num, prev_num, act_label);
+                    }
+                    (Some(prev_num), Some(cur_num)) if cur_num > prev_num + 1 => {
+                        let _ = writeln!(stderr,"Whinge Warning: scene {} follows scene {} in act {}, skipping scene {}", cur_num, prev_num, act_label, prev_num + 1);
+                    }
+                    //a script may start partway through its first act, later acts should start at scene 1
+                    (None, Some(cur_num)) if act_idx > 0 && cur_num != 1 => {
+                        let _ = writeln!(stderr,"Whinge Warning: act {} starts at scene {} instead of scene 1", act_label, cur_num);
+                    }
+                    _ => {}
+                }
+                if a_scene.meta.scene.is_some() {
+                    prev_scene = a_scene.meta.scene;
+                }
+            }
+        }
+    }
+
+    //short title of the scene each fragment belongs to, e.g. "II.1 A room in Polonius house", in fragment order
+    pub fn fragment_scene_titles(&self) -> Result<Vec<String>, u8> {
+        let mut scene_titles: Vec<String> = Vec::new();
+        for an_act in self.acts.iter() {
+            for a_scene in an_act.scenes.iter() {
+                for _ in a_scene.fragments.iter() {
+                    scene_titles.push(a_scene.meta.short_title());
+                }
+            }
+        }
+        Ok(scene_titles)
+    }
+
+    //collects every character appearing in any fragment with their line counts and scenes, for the cast list printed before the first scene
+    pub fn dramatis_personae(&self, order: PersonaeOrder) -> Result<Vec<Persona>, u8> {
+        let mut stderr = io::stderr().lock();
+        let scene_titles = self.fragment_scene_titles()?;
+        let mut personae: Vec<Persona> = Vec::new();
+
+        for (fragment_idx, a_fragment) in self.fragments.iter().enumerate() {
+            let scene_ref = match a_fragment.lock() {
+                Ok(scene_ref) => scene_ref,
+                Err(_) => {
+                    let _ = writeln!(stderr, "Error: Failed to acquire lock on ith scene fragment");
+                    return Err(GENERATION_FAILURE);
+                }
+            };
+
+            for (name_idx, a_name) in scene_ref.names_in_fragment().iter().enumerate() {
+                //shared lines count towards every member of the group
+                let fragment_lines: usize = scene_ref.chars_in_play.iter()
+                    .filter(|a_player| a_player.members.contains(a_name))
+                    .map(|a_player| a_player.char_lines.len())
+                    .sum();
+
+                match personae.iter_mut().find(|a_persona| a_persona.char_key == *a_name) {
+                    Some(a_persona) => {
+                        a_persona.line_count += fragment_lines;
+                        if !a_persona.scenes.contains(&scene_titles[fragment_idx]) {
+                            a_persona.scenes.push(scene_titles[fragment_idx].to_string());
+                        }
+                    }
+                    None => personae.push(Persona {
+                        char_key: a_name.to_string(),
+                        display_name: self.cast.display_name(a_name),
+                        description: self.cast.description(a_name),
+                        first_appearance: (fragment_idx, name_idx),
+                        line_count: fragment_lines,
+                        scenes: vec![scene_titles[fragment_idx].to_string()],
+                    }),
+                }
+            }
+        }
+
+        order_personae(&mut personae, order);
+        Ok(personae)
+    }
+
+    //whinges about cast entries that no config file uses, usually a spelling difference between the two
+    fn check_cast(&self) {
+        if !WHINGE.load(Ordering::SeqCst) {
+            return;
+        }
+        let mut stderr = io::stderr().lock();
+
+        let mut play_names: Vec<String> = Vec::new();
+        for a_fragment in self.fragments.iter() {
+            if let Ok(scene_ref) = a_fragment.lock() {
+                play_names.extend(scene_ref.names_in_fragment());
+            }
+        }
+        for a_member in self.cast.members.iter() {
+            if !play_names.contains(&a_member.char_key) {
+                let _ = writeln!(stderr,"Whinge Warning: cast file lists '{}' but no config file uses that character", a_member.char_key);
+            }
+        }
+    }
 
+    //runs the line number checks recite would make on every fragment without delivering any lines. Returns the number of scenes, fragments,
+    //characters and lines read
+    pub fn validate(&self) -> Result<PlayCounts, u8> {
+        let mut stderr = io::stderr().lock();
+        let scene_count: usize = self.acts.iter().map(|an_act| an_act.scenes.len()).sum();
+        let mut char_names: Vec<String> = Vec::new();
+        let mut line_count: usize = 0;
+
+        for a_fragment in self.fragments.iter() {
+            let scene_ref = match a_fragment.lock() {
+                Ok(scene_ref) => scene_ref,
+                Err(_) => {
+                    let _ = writeln!(stderr, "Error: Failed to acquire lock on ith scene fragment");
+                    return Err(GENERATION_FAILURE);
+                }
+            };
+            scene_ref.check_line_numbers();
+            line_count += scene_ref.chars_in_play.iter().map(|a_player| a_player.char_lines.len()).sum::<usize>();
+            for a_name in scene_ref.names_in_fragment() {
+                if !char_names.contains(&a_name) {
+                    char_names.push(a_name);
+                }
+            }
+        }
+        Ok((scene_count, self.fragments.len(), char_names.len(), line_count))
+    }
 
+    //gives every fragment whole line numbers in place of sub-line numbers, see SceneFragment::flatten_line_numbers
+    pub fn flatten_line_numbers(&mut self) -> Result<(), u8> {
+        for a_fragment in self.fragments.iter() {
+            match a_fragment.lock() {
+                Ok(mut scene_ref) => scene_ref.flatten_line_numbers(),
+                Err(_) => {
+                    let _ = writeln!(io::stderr().lock(), "Error: Failed to acquire lock on ith scene fragment");
+                    return Err(GENERATION_FAILURE);
+                }
+            }
+        }
+        Ok(())
+    }
 
+    //first and last fragment index selected by the excerpt's scene and fragment number. The selection is always one contiguous run of fragments
+    pub fn excerpt_fragments(&self, excerpt: &Excerpt) -> Result<(usize, usize), u8> {
+        let mut stderr = io::stderr().lock();
+        let mut first_idx: usize = 0;
+        let mut last_idx: usize = self.fragments.len().saturating_sub(1);
+
+        if let Some(scene_label) = excerpt.scene_label.as_ref() {
+            let matching_scene = self.acts.iter().flat_map(|an_act| an_act.scenes.iter()).find(|a_scene| a_scene.meta.matches_label(scene_label) && !a_scene.fragments.is_empty());
+            match matching_scene {
+                Some(a_scene) => {
+                    first_idx = a_scene.first_fragment_idx;
+                    last_idx = a_scene.first_fragment_idx + a_scene.fragments.len() - 1;
+                }
+                None => {
+                    let _ = writeln!(stderr,"Error: no scene in the play matches '{}'", scene_label);
+                    return Err(GENERATION_FAILURE);
+                }
+            }
+        }
 
+        if let Some(fragment_num) = excerpt.fragment_num {
+            //fragments are numbered from 1 over the whole play
+            if fragment_num == 0 || fragment_num - 1 < first_idx || fragment_num - 1 > last_idx {
+                let _ = writeln!(stderr,"Error: fragment {} is not in the selected part of the play", fragment_num);
+                return Err(GENERATION_FAILURE);
+            }
+            first_idx = fragment_num - 1;
+            last_idx = fragment_num - 1;
+        }
+        Ok((first_idx, last_idx))
+    }
 
-    //formats the character speech parts in scene-structure by calling entry then fragment's recite, and exit for each SceneFragment in vector
-    pub fn recite(&mut self) -> Result<(), u8> {
+    //formats the character speech parts in scene-structure by calling entry then fragment's recite, and exit for each SceneFragment in vector.
+    //only the fragments, lines and speakers selected by the excerpt are recited
+    pub fn recite(&mut self, excerpt: &Excerpt, actor_threads: bool) -> Result<(), u8> {
         let mut stderr = io::stderr().lock();
 
         let num_fragments = self.fragments.len();
@@ -193,23 +687,25 @@ impl Play{
         if num_fragments == 0 {
             return Ok(());
         }
+
+        let (first_fragment_idx, last_fragment_idx) = self.excerpt_fragments(excerpt)?;
         
-        for cur_fragment_idx in 0..num_fragments{
+        for cur_fragment_idx in first_fragment_idx..=last_fragment_idx{
 
             // lock current fragment
             match self.fragments[cur_fragment_idx].lock() {
             Ok(mut scene_ref) => {
                 //stuff goies here now
 
-                if cur_fragment_idx == 0{ //first fragment, call enter all
-                    scene_ref.enter_all();
+                if cur_fragment_idx == first_fragment_idx{ //first fragment, call enter all
+                    scene_ref.enter_all(&self.cast, excerpt);
                 }else{ //save to do -1 to get prev index
                     let prev_fragment_idx = cur_fragment_idx - 1;
 
                     //NESTED FOR PREV SCENE
                     match self.fragments[prev_fragment_idx].lock() { //get reference to previous fragment
                     Ok(prev_scene_ref) => {
-                        scene_ref.enter(&*prev_scene_ref); //do the enter call
+                        scene_ref.enter(&prev_scene_ref, &self.cast, excerpt); //do the enter call
                     }
                     Err(_) => {
                         let _ = writeln!(stderr, "Error: Failed to acquire lock on ith scene fragment");
@@ -218,15 +714,16 @@ impl Play{
                 }
                 }
 
-                //do the actual recite call on the SceneFragment
-                if let Err(e_code) = scene_ref.recite(){
+                //do the actual recite call on the SceneFragment, with a thread per player if asked for
+                let recited = if actor_threads { scene_ref.recite_actors(&self.cast, excerpt) } else { scene_ref.recite(&self.cast, excerpt) };
+                if let Err(e_code) = recited {
                     let _ = writeln!(stderr,"Error from recite in Play.rs: unsucessful fragment recite call with error code {}", e_code);
                     return Err(GENERATION_FAILURE);
                 }
 
                 //block for exit name calls
-                if cur_fragment_idx == num_fragments-1 {
-                    scene_ref.exit_all();
+                if cur_fragment_idx == last_fragment_idx {
+                    scene_ref.exit_all(&self.cast, excerpt);
                 }else{
                     //safe to do +1 here to the fragment index
                     let next_fragment_idx = cur_fragment_idx + 1;
@@ -234,7 +731,7 @@ impl Play{
                     //NESTED FOR NEXT SCENE
                     match self.fragments[next_fragment_idx].lock() { //get reference to next fragment
                     Ok(next_scene_ref) => {
-                        scene_ref.exit(&*next_scene_ref); //do the exit call
+                        scene_ref.exit(&next_scene_ref, &self.cast, excerpt); //do the exit call
                     }
                     Err(_) => {
                         let _ = writeln!(stderr, "Error: Failed to acquire lock on ith scene fragment");
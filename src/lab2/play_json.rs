@@ -0,0 +1,269 @@
+//play_json.rs writes a prepared Play as JSON, with the scenes, fragments, players and their numbered lines and the files each came from, and reads such a file back into a Play through the same checks as a script. Hanson Li, Johnny Huang, Aman Verma
+//
+//the document is one object:
+//  format       "lab2-play", version 2 (version 1, from before personae, is still read)
+//  script_file  script the play was read from, cast_file the cast file or ""
+//  cast         [{key, display_name, speech_prefix, description}] in cast file order
+//  scenes       [{title, play_title, act, scene, location, author, label, fragments}] in script order. title is the [scene] line as written,
+//               act and scene are numbers or null, label (e.g. "II.1") is only written for readers and ignored when loading
+//  fragments    [{config_file, players}] in scene order
+//  players      [{name, members, part_file, enter_at, exit_at, lines}], enter_at/exit_at a line number or null. members (e.g. both halves
+//               of Rosencrantz+Guildenstern, or everyone else for ALL) is only written for readers and worked out again when loading
+//  lines        [{line, text}] in line number order. line is a number, or a string such as "12.1" for a sub-line, as are enter_at and exit_at
+//  personae     [{key, display_name, description, line_count, scenes}] in the order asked for, only written with --personae and ignored when
+//               loading. scenes are the labels of the scenes the character speaks in
+
+use super::cast::{Cast, CastMember};
+use super::declarations::GENERATION_FAILURE;
+use super::json::JsonValue;
+use super::line_num::LineNum;
+use super::personae::Persona;
+use super::play::Play;
+use super::player::{Player, PlayLines, ENTER_MARK, EXIT_MARK, PRESENCE_DELIM};
+use super::scene_fragments::SceneFragment;
+use super::scene_meta::SceneMeta;
+use super::script_gen::read_text_file;
+use std::io::{self, Write};
+
+pub const JSON_FORMAT: &str = "json";
+pub const PLAY_FORMAT_NAME: &str = "lab2-play"; //value of the format field, so other JSON files are not mistaken for a play
+pub const PLAY_FORMAT_VERSION: usize = 2;
+const OLDEST_FORMAT_VERSION: usize = 1;
+
+fn optional_usize(number: Option<usize>) -> JsonValue {
+    number.map(JsonValue::from_usize).unwrap_or(JsonValue::Null)
+}
+
+fn optional_line_num(line_num: Option<LineNum>) -> JsonValue {
+    line_num.map(|line_num| line_num.to_json()).unwrap_or(JsonValue::Null)
+}
+
+fn player_to_json(plyr: &Player) -> JsonValue {
+    let lines_json: Vec<JsonValue> = plyr.char_lines.iter().map(|(line_num, line_text)| JsonValue::Object(vec![
+        ("line".to_string(), line_num.to_json()),
+        ("text".to_string(), JsonValue::from_text(line_text)),
+    ])).collect();
+    JsonValue::Object(vec![
+        ("name".to_string(), JsonValue::from_text(&plyr.char_name)),
+        ("members".to_string(), JsonValue::Array(plyr.members.iter().map(|a_member| JsonValue::from_text(a_member)).collect())),
+        ("part_file".to_string(), JsonValue::from_text(&plyr.part_file)),
+        ("enter_at".to_string(), optional_line_num(plyr.enter_at)),
+        ("exit_at".to_string(), optional_line_num(plyr.exit_at)),
+        ("lines".to_string(), JsonValue::Array(lines_json)),
+    ])
+}
+
+fn persona_to_json(a_persona: &Persona) -> JsonValue {
+    JsonValue::Object(vec![
+        ("key".to_string(), JsonValue::from_text(&a_persona.char_key)),
+        ("display_name".to_string(), JsonValue::from_text(&a_persona.display_name)),
+        ("description".to_string(), JsonValue::from_text(&a_persona.description)),
+        ("line_count".to_string(), JsonValue::from_usize(a_persona.line_count)),
+        ("scenes".to_string(), JsonValue::Array(a_persona.scenes.iter().map(|a_scene| JsonValue::from_text(a_scene)).collect())),
+    ])
+}
+
+//the whole play as a JSON document, see the top of this file for its fields
+pub fn play_to_json(play: &Play, personae: &[Persona]) -> Result<JsonValue, u8> {
+    let mut stderr = io::stderr().lock();
+    let cast = play.cast();
+    let cast_json: Vec<JsonValue> = cast.members.iter().map(|a_member| JsonValue::Object(vec![
+        ("key".to_string(), JsonValue::from_text(&a_member.char_key)),
+        ("display_name".to_string(), JsonValue::from_text(&a_member.display_name)),
+        ("speech_prefix".to_string(), JsonValue::from_text(&a_member.speech_prefix)),
+        ("description".to_string(), JsonValue::from_text(&a_member.description)),
+    ])).collect();
+
+    let mut scenes_json: Vec<JsonValue> = Vec::new();
+    for an_act in play.acts().iter() {
+        for a_scene in an_act.scenes.iter() {
+            let mut fragments_json: Vec<JsonValue> = Vec::new();
+            for a_fragment in a_scene.fragments.iter() {
+                let Ok(scene_ref) = a_fragment.lock() else {
+                    let _ = writeln!(stderr, "Error: Failed to acquire lock on ith scene fragment");
+                    return Err(GENERATION_FAILURE);
+                };
+                fragments_json.push(JsonValue::Object(vec![
+                    ("config_file".to_string(), JsonValue::from_text(&scene_ref.config_file)),
+                    ("players".to_string(), JsonValue::Array(scene_ref.chars_in_play.iter().map(player_to_json).collect())),
+                ]));
+            }
+            let scene_meta = &a_scene.meta;
+            scenes_json.push(JsonValue::Object(vec![
+                ("title".to_string(), JsonValue::from_text(&scene_meta.full_title)),
+                ("play_title".to_string(), JsonValue::from_text(&scene_meta.play_title)),
+                ("act".to_string(), optional_usize(scene_meta.act)),
+                ("scene".to_string(), optional_usize(scene_meta.scene)),
+                ("location".to_string(), JsonValue::from_text(&scene_meta.location)),
+                ("author".to_string(), JsonValue::from_text(&scene_meta.author)),
+                ("label".to_string(), JsonValue::from_text(&scene_meta.label())),
+                ("fragments".to_string(), JsonValue::Array(fragments_json)),
+            ]));
+        }
+    }
+
+    let mut play_fields = vec![
+        ("format".to_string(), JsonValue::from_text(PLAY_FORMAT_NAME)),
+        ("version".to_string(), JsonValue::from_usize(PLAY_FORMAT_VERSION)),
+        ("script_file".to_string(), JsonValue::from_text(play.script_file())),
+        ("cast_file".to_string(), JsonValue::from_text(&cast.cast_file)),
+        ("cast".to_string(), JsonValue::Array(cast_json)),
+        ("scenes".to_string(), JsonValue::Array(scenes_json)),
+    ];
+    if !personae.is_empty() {
+        play_fields.push(("personae".to_string(), JsonValue::Array(personae.iter().map(persona_to_json).collect())));
+    }
+    Ok(JsonValue::Object(play_fields))
+}
+
+pub fn export_json(play: &Play, personae: &[Persona]) -> Result<String, u8> {
+    let mut json_text = play_to_json(play, personae)?.render();
+    json_text.push('\n');
+    Ok(json_text)
+}
+
+//reads the fields of a loaded document, reporting a field that is missing or of the wrong type by its path, e.g. scenes[0].fragments[1].players[2].name
+struct JsonReader<'a> {
+    source_name: &'a str,
+}
+
+impl JsonReader<'_> {
+    fn fail(&self, field_path: &str, problem: &str) -> u8 {
+        let _ = writeln!(io::stderr().lock(), "Error: {} {}: {}", self.source_name, field_path, problem);
+        GENERATION_FAILURE
+    }
+
+    fn field_path(parent_path: &str, field_name: &str) -> String {
+        if parent_path.is_empty() { field_name.to_string() } else { format!("{}.{}", parent_path, field_name) }
+    }
+
+    fn field<'v>(&self, parent: &'v JsonValue, parent_path: &str, field_name: &str) -> Result<&'v JsonValue, u8> {
+        parent.get(field_name).ok_or_else(|| self.fail(&Self::field_path(parent_path, field_name), "missing field"))
+    }
+
+    fn text(&self, parent: &JsonValue, parent_path: &str, field_name: &str) -> Result<String, u8> {
+        let a_value = self.field(parent, parent_path, field_name)?;
+        a_value.as_str().map(|a_string| a_string.to_string()).ok_or_else(|| self.fail(&Self::field_path(parent_path, field_name), "expected a string"))
+    }
+
+    //a string field that may be left out, as an empty string
+    fn optional_text(&self, parent: &JsonValue, parent_path: &str, field_name: &str) -> Result<String, u8> {
+        match parent.get(field_name) {
+            None | Some(JsonValue::Null) => Ok(String::new()),
+            Some(_) => self.text(parent, parent_path, field_name),
+        }
+    }
+
+    //a line number field that may be left out or null
+    fn optional_number(&self, parent: &JsonValue, parent_path: &str, field_name: &str) -> Result<Option<usize>, u8> {
+        match parent.get(field_name) {
+            None | Some(JsonValue::Null) => Ok(None),
+            Some(a_value) => a_value.as_usize().map(Some).ok_or_else(|| self.fail(&Self::field_path(parent_path, field_name), "expected a whole number or null")),
+        }
+    }
+
+    //a line number field that may be left out or null, written as a number or as a string for a sub-line
+    fn optional_line_num(&self, parent: &JsonValue, parent_path: &str, field_name: &str) -> Result<Option<LineNum>, u8> {
+        match parent.get(field_name) {
+            None | Some(JsonValue::Null) => Ok(None),
+            Some(a_value) => LineNum::from_json(a_value).map(Some).ok_or_else(|| self.fail(&Self::field_path(parent_path, field_name), "expected a line number or null")),
+        }
+    }
+
+    fn items<'v>(&self, parent: &'v JsonValue, parent_path: &str, field_name: &str) -> Result<&'v [JsonValue], u8> {
+        let a_value = self.field(parent, parent_path, field_name)?;
+        a_value.as_array().ok_or_else(|| self.fail(&Self::field_path(parent_path, field_name), "expected an array"))
+    }
+}
+
+//builds a player from its JSON object the way a config line and part file would, so enter/exit annotations are checked again
+fn player_from_json(reader: &JsonReader, player_json: &JsonValue, player_path: &str) -> Result<Player, u8> {
+    let char_name = reader.text(player_json, player_path, "name")?;
+    if char_name.is_empty() || char_name.contains(char::is_whitespace) {
+        return Err(reader.fail(&format!("{}.name", player_path), "a character name must be one token, as in a config file"));
+    }
+    let mut char_lines: PlayLines = Vec::new();
+    for (line_idx, line_json) in reader.items(player_json, player_path, "lines")?.iter().enumerate() {
+        let line_path = format!("{}.lines[{}]", player_path, line_idx);
+        let line_num = LineNum::from_json(reader.field(line_json, &line_path, "line")?).ok_or_else(|| reader.fail(&format!("{}.line", line_path), "expected a line number"))?;
+        char_lines.push((line_num, reader.text(line_json, &line_path, "text")?));
+    }
+
+    let mut new_player = Player::new(&char_name);
+    new_player.prepare_lines(&reader.optional_text(player_json, player_path, "part_file")?, char_lines);
+    if let Some(line_num) = reader.optional_line_num(player_json, player_path, "enter_at")? {
+        new_player.add_presence(&format!("{}{}{}", ENTER_MARK, PRESENCE_DELIM, line_num));
+    }
+    if let Some(line_num) = reader.optional_line_num(player_json, player_path, "exit_at")? {
+        new_player.add_presence(&format!("{}{}{}", EXIT_MARK, PRESENCE_DELIM, line_num));
+    }
+    Ok(new_player)
+}
+
+//builds a Play from a document written by export_json, then runs the checks prepare makes on a script
+pub fn play_from_json(play_json: &JsonValue, source_name: &str) -> Result<Play, u8> {
+    let reader = JsonReader { source_name };
+    if play_json.get("format").and_then(|a_value| a_value.as_str()) != Some(PLAY_FORMAT_NAME) {
+        return Err(reader.fail("format", &format!("expected \"{}\", this is not a play exported as JSON", PLAY_FORMAT_NAME)));
+    }
+    match play_json.get("version").and_then(|a_value| a_value.as_usize()) {
+        Some(OLDEST_FORMAT_VERSION..=PLAY_FORMAT_VERSION) => {}
+        _ => return Err(reader.fail("version", &format!("only versions {} to {} can be read", OLDEST_FORMAT_VERSION, PLAY_FORMAT_VERSION))),
+    }
+
+    //cast entries get the cast file's check for a character listed twice, but are not joined into a cast line since a field can hold '|'
+    let mut cast = Cast::new();
+    for (member_idx, member_json) in reader.items(play_json, "", "cast")?.iter().enumerate() {
+        let member_path = format!("cast[{}]", member_idx);
+        let char_key = reader.text(member_json, &member_path, "key")?;
+        if char_key.is_empty() {
+            return Err(reader.fail(&format!("{}.key", member_path), "a cast member needs a character key"));
+        }
+        cast.add_member(CastMember::new(&char_key,
+            &reader.optional_text(member_json, &member_path, "display_name")?,
+            &reader.optional_text(member_json, &member_path, "speech_prefix")?,
+            &reader.optional_text(member_json, &member_path, "description")?));
+    }
+    cast.cast_file = reader.optional_text(play_json, "", "cast_file")?;
+
+    let mut play = Play::new();
+    play.set_provenance(&reader.optional_text(play_json, "", "script_file")?, cast);
+
+    for (scene_idx, scene_json) in reader.items(play_json, "", "scenes")?.iter().enumerate() {
+        let scene_path = format!("scenes[{}]", scene_idx);
+        //like [title] or [act] directives in a script, the separate fields override what is parsed from the title
+        let mut scene_meta = SceneMeta::from_title(&reader.text(scene_json, &scene_path, "title")?);
+        for (field_name, meta_field) in [("play_title", &mut scene_meta.play_title), ("location", &mut scene_meta.location), ("author", &mut scene_meta.author)] {
+            if scene_json.get(field_name).is_some() {
+                *meta_field = reader.optional_text(scene_json, &scene_path, field_name)?;
+            }
+        }
+        if scene_json.get("act").is_some() {
+            scene_meta.act = reader.optional_number(scene_json, &scene_path, "act")?;
+        }
+        if scene_json.get("scene").is_some() {
+            scene_meta.scene = reader.optional_number(scene_json, &scene_path, "scene")?;
+        }
+        let scene_title = scene_meta.full_title.to_string();
+        play.push_scene(scene_meta);
+
+        for (fragment_idx, fragment_json) in reader.items(scene_json, &scene_path, "fragments")?.iter().enumerate() {
+            let fragment_path = format!("{}.fragments[{}]", scene_path, fragment_idx);
+            let mut fragment = SceneFragment::new(&scene_title);
+            fragment.config_file = reader.optional_text(fragment_json, &fragment_path, "config_file")?;
+            for (player_idx, player_json) in reader.items(fragment_json, &fragment_path, "players")?.iter().enumerate() {
+                fragment.add_player(player_from_json(&reader, player_json, &format!("{}.players[{}]", fragment_path, player_idx))?);
+            }
+            play.push_fragment(fragment);
+        }
+    }
+    play.finish_import()?;
+    Ok(play)
+}
+
+//reads a play exported as JSON, used by every command in place of a script file with a .json extension
+pub fn read_json_play(json_fname: &str) -> Result<Play, u8> {
+    let json_text = read_text_file(json_fname)?;
+    let play_json = JsonValue::parse(&json_text, json_fname)?;
+    play_from_json(&play_json, json_fname)
+}
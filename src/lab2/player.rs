@@ -4,15 +4,57 @@ use std::sync::atomic;
 use std::cmp::Ordering;
 use super::declarations::{WHINGE,GENERATION_FAILURE, ZERO_IDX};
 use super::script_gen::grab_trimmed_file_lines;
+use super::cast::Cast;
+use super::line_num::LineNum;
 use std::io::{self, Write};
+use std::sync::Mutex;
 
-pub type PlayLines = Vec<(usize, String)>; //per line, holds information about the line number and the text.
+pub type PlayLines = Vec<(LineNum, String)>; //per line, holds information about the line number and the text.
+
+//presence annotations in a config file look like enter@12 or exit@last
+pub const PRESENCE_DELIM: char = '@';
+pub const ENTER_MARK: &str = "enter";
+pub const EXIT_MARK: &str = "exit";
+pub const FIRST_LINE_MARK: &str = "first"; //resolves to the character's first line number
+pub const LAST_LINE_MARK: &str = "last";   //resolves to the character's last line number
+
+//shared lines are configured as Rosencrantz+Guildenstern, or ALL for everyone else in the fragment
+pub const GROUP_DELIM: char = '+';
+pub const ALL_GROUP: &str = "ALL";
+
+//with --continuation a part file line without a number carries on the numbered line before it
+pub const CONTINUATION_JOIN: &str = "join"; //appended to the line with a space, as if it had never wrapped
+pub const CONTINUATION_WRAP: &str = "wrap"; //kept as a sub-line, delivered on its own line after the rest
+pub const SUB_LINE_DELIM: char = '\n';     //between the sub-lines of a wrapped line
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Continuation {
+    Join,
+    Wrap,
+}
+
+impl Continuation {
+    pub fn from_name(mode_name: &str) -> Option<Self> {
+        match mode_name {
+            CONTINUATION_JOIN => Some(Continuation::Join),
+            CONTINUATION_WRAP => Some(Continuation::Wrap),
+            _ => None,
+        }
+    }
+}
+
+//set from --continuation, None leaves unnumbered lines out with a whinge as before
+pub static CONTINUATION: Mutex<Option<Continuation>> = Mutex::new(None);
 
 #[derive(Debug)]
 pub struct Player{
     pub char_name: String, //character name
+    pub members: Vec<String>, //characters speaking these lines, more than one for shared or choral lines
     pub char_lines: PlayLines, //vector of tuple of (line number, line text)
+    pub part_file: String, //part file the lines were read from
     pub cur_entry_idx: usize, //current line number spoken by character
+    pub enter_at: Option<LineNum>, //enters just before this line is spoken, None if on stage when the fragment begins
+    pub exit_at: Option<LineNum>, //exits after this line is spoken, None if still on stage when the fragment ends
 }
 
 impl Player{
@@ -20,28 +62,141 @@ impl Player{
     pub fn new(char_name: &String) -> Self {
          Self {
             char_name: char_name.to_string(),
+            members: Self::split_members(char_name),
             char_lines: Vec::new(),
+            part_file: String::new(),
             cur_entry_idx : ZERO_IDX,
+            enter_at: None,
+            exit_at: None,
          }
     }
 
-    //adds a line parsed from self.prepare to our chars_lines vector
-    fn add_script_line(&mut self, unparsed_line: &String){
+    //splits a group name like Rosencrantz+Guildenstern into its members. ALL has no members until the fragment fills them in
+    fn split_members(char_name: &str) -> Vec<String> {
+        if char_name == ALL_GROUP {
+            return Vec::new();
+        }
+        char_name.split(GROUP_DELIM).filter(|a_name| !a_name.is_empty()).map(|a_name| a_name.to_string()).collect()
+    }
+
+    //true if this player delivers lines on behalf of several characters
+    pub fn is_group(&self) -> bool {
+        self.char_name == ALL_GROUP || self.members.len() > 1
+    }
+
+    //the name printed above a speech using the cast's speech prefixes, e.g. "Rosencrantz and Guildenstern" for a shared line
+    pub fn speaker_header(&self, cast: &Cast) -> String {
+        if self.char_name == ALL_GROUP || self.members.len() < 2 {
+            return cast.speech_prefix(&self.char_name);
+        }
+        let member_prefixes: Vec<String> = self.members.iter().map(|a_member| cast.speech_prefix(a_member)).collect();
+        let (last_prefix, other_prefixes) = member_prefixes.split_last().unwrap_or((&self.char_name, &[]));
+        format!("{} and {}", other_prefixes.join(", "), last_prefix)
+    }
+
+    //parses an enter@N or exit@N annotation from a config line. Must be called after prepare so first/last can be resolved from char_lines
+    pub fn add_presence(&mut self, annotation: &String) {
+        let mut stderr = io::stderr().lock();
+
+        let Some((direction, position)) = annotation.split_once(PRESENCE_DELIM) else {
+            if WHINGE.load(atomic::Ordering::SeqCst){
+                let _ = writeln!(stderr,"Whinge Warning: presence annotation '{}' for {} is missing '{}'", annotation, self.char_name, PRESENCE_DELIM);
+            }
+            return;
+        };
+
+        let line_num = match position {
+            FIRST_LINE_MARK => self.char_lines.first().map(|a_tuple| a_tuple.0),
+            LAST_LINE_MARK => self.char_lines.last().map(|a_tuple| a_tuple.0),
+            _ => LineNum::parse(position),
+        };
+
+        let Some(line_num) = line_num else {
+            if WHINGE.load(atomic::Ordering::SeqCst){
+                let _ = writeln!(stderr,"Whinge Warning: '{}' in presence annotation '{}' for {} does not name a line", position, annotation, self.char_name);
+            }
+            return;
+        };
+
+        match direction {
+            ENTER_MARK => {
+                self.enter_at = Some(line_num);
+                //a character speaking before they walk on is almost always a typo in the config
+                if WHINGE.load(atomic::Ordering::SeqCst) && self.char_lines.iter().any(|(a_line_num, _)| *a_line_num < line_num) {
+                    let _ = writeln!(stderr,"Whinge Warning: {} speaks before entering at line {}", self.char_name, line_num);
+                }
+            },
+            EXIT_MARK => {
+                self.exit_at = Some(line_num);
+                if WHINGE.load(atomic::Ordering::SeqCst) && self.char_lines.iter().any(|(a_line_num, _)| *a_line_num > line_num) {
+                    let _ = writeln!(stderr,"Whinge Warning: {} speaks after exiting at line {}", self.char_name, line_num);
+                }
+            },
+            _ => {
+                if WHINGE.load(atomic::Ordering::SeqCst){
+                    let _ = writeln!(stderr,"Whinge Warning: unknown presence annotation '{}' for {}, expecting {}{}N or {}{}N", annotation, self.char_name, ENTER_MARK, PRESENCE_DELIM, EXIT_MARK, PRESENCE_DELIM);
+                }
+            },
+        }
+    }
+
+    //true if the character is already on stage when their fragment begins
+    pub fn on_stage_at_start(&self) -> bool {
+        self.on_stage_from(None)
+    }
+
+    //true if the character is still on stage when their fragment ends
+    pub fn on_stage_at_end(&self) -> bool {
+        self.on_stage_until(None)
+    }
+
+    //true if the character is on stage when recitation starts at from_line, None meaning the start of the fragment.
+    //an entrance just before from_line is still to be announced, so it does not count. An excerpt's range takes in the sub-lines of its last line
+    pub fn on_stage_from(&self, from_line: Option<usize>) -> bool {
+        match from_line {
+            Some(from_line) => self.enter_at.is_none_or(|enter_line| enter_line.whole() < from_line) && self.exit_at.is_none_or(|exit_line| exit_line.whole() >= from_line),
+            None => self.enter_at.is_none(),
+        }
+    }
+
+    //true if the character is on stage when recitation stops after to_line, None meaning the end of the fragment
+    pub fn on_stage_until(&self, to_line: Option<usize>) -> bool {
+        match to_line {
+            Some(to_line) => self.exit_at.is_none_or(|exit_line| exit_line.whole() > to_line) && self.enter_at.is_none_or(|enter_line| enter_line.whole() <= to_line),
+            None => self.exit_at.is_none(),
+        }
+    }
+
+    //adds a line parsed from self.prepare to our chars_lines vector. file_line_num is where it is in the part file, counting from 1
+    fn add_script_line(&mut self, unparsed_line: &String, file_line_num: usize, continuation: Option<Continuation>){
         let mut stderr = io::stderr().lock();
 
         if unparsed_line.len() > 0 {
-            if let Some((first_token, remain_token)) = unparsed_line.split_once(char::is_whitespace) {
-                let line_extract = remain_token.trim(); //this will return &str, so we need to_string when pushing to Play
-                
-                //using if let for error handling. Base case is if parse returns anything other than Ok
-                if let Ok(line_num) = first_token.parse::<usize>() {
-                self.char_lines.push((line_num, line_extract.to_string()));
-                } else {
-                    if WHINGE.load(atomic::Ordering::SeqCst){
-                        let _ = writeln!(stderr,"Whinge Warning: the first token of the passed in line '{}' does not represent a valid usize value!", unparsed_line);
-                    }
+            let (first_token, remain_token) = unparsed_line.split_once(char::is_whitespace).unwrap_or((unparsed_line, ""));
+            let line_extract = remain_token.trim(); //this will return &str, so we need to_string when pushing to Play
 
+            //using if let for error handling. Base case is if parse returns anything other than Ok
+            if let Some(line_num) = LineNum::parse(first_token) {
+                //a number alone is only kept when continuation lines could still give it text, empty lines are dropped once the file is read
+                if !line_extract.is_empty() || continuation.is_some() {
+                    self.char_lines.push((line_num, line_extract.to_string()));
+                }
+            } else if let Some(a_mode) = continuation {
+                match self.char_lines.last_mut() {
+                    Some((_, prev_text)) => {
+                        if !prev_text.is_empty() {
+                            prev_text.push(if a_mode == Continuation::Wrap { SUB_LINE_DELIM } else { ' ' });
+                        }
+                        prev_text.push_str(unparsed_line);
+                    }
+                    None => {
+                        if WHINGE.load(atomic::Ordering::SeqCst){
+                            let _ = writeln!(stderr,"Whinge Warning: {} line {}: continuation line '{}' comes before any numbered line, skipping it", self.part_file, file_line_num, unparsed_line);
+                        }
+                    }
                 }
+            } else if !remain_token.is_empty() && WHINGE.load(atomic::Ordering::SeqCst) {
+                let _ = writeln!(stderr,"Whinge Warning: the first token of the passed in line '{}' does not represent a valid usize value!", unparsed_line);
             }
         }
     }
@@ -51,43 +206,67 @@ impl Player{
 
         let mut stdout = io::stdout().lock(); // Get a locked handle to stdout
 
+        self.part_file = part_name.to_string();
+
         let mut cur_file_line_vec: Vec::<String> = Vec::new();
         if let Err(e_code) = grab_trimmed_file_lines(&part_name, &mut cur_file_line_vec) {
             let _ = writeln!(stdout,"Error: process_script unsucessfully called grab_trimmed_file_lines with error code {}", e_code);
             return Err(GENERATION_FAILURE);
         } 
 
-        for a_line in cur_file_line_vec.iter() {
-            self.add_script_line(a_line)
+        let continuation = CONTINUATION.lock().map(|a_mode| *a_mode).unwrap_or(None);
+        for (line_idx, a_line) in cur_file_line_vec.iter().enumerate() {
+            self.add_script_line(a_line, line_idx + 1, continuation)
         }
+        self.char_lines.retain(|(_, line_text)| !line_text.is_empty());
         self.char_lines.sort_by_key(|a_tuple| a_tuple.0); //need to use sort by key on the line nume (1st tuple pos) to correctly sort out of order lines
         Ok (())
     }
 
-    //delivers the lines using self.char_lines
-    pub fn speak(&mut self, most_recent_speaker: &mut String){
+    //takes lines already split into number and text, e.g. loaded from JSON, in place of reading them from the part file
+    pub fn prepare_lines(&mut self, part_name: &str, char_lines: PlayLines) {
+        self.part_file = part_name.to_string();
+        self.char_lines = char_lines;
+        self.char_lines.sort_by_key(|a_tuple| a_tuple.0);
+    }
 
-        let mut stdout = io::stdout().lock(); // Lock stderr
+    //delivers the lines using self.char_lines
+    pub fn speak(&mut self, most_recent_speaker: &mut String, cast: &Cast){
 
         if self.cur_entry_idx < self.char_lines.len(){
-            //check if passed in name same as struct char name
-            if *most_recent_speaker != self.char_name {
-                *most_recent_speaker = self.char_name.to_string();
-                let _ = writeln!(stdout);
-                let _ = writeln!(stdout,"Speaker: {}", most_recent_speaker);
-            }
-            
-            //'either case should print out text and inc index'
-            let _ = writeln!(stdout,"{:?}", self.char_lines[self.cur_entry_idx].1);
+            self.deliver_line(self.cur_entry_idx, most_recent_speaker, cast);
             self.cur_entry_idx += 1
-
-
         } 
     }  
+
+    //prints one line, with the speaker header first if someone else spoke last. Does not move cur_entry_idx, so an actor thread can deliver
+    //lines while the fragment only lends it the player to read
+    pub fn deliver_line(&self, line_idx: usize, most_recent_speaker: &mut String, cast: &Cast) {
+        let mut stdout = io::stdout().lock(); // Lock stdout
+
+        //check if passed in name same as struct char name, the key is compared so display names never affect this
+        if *most_recent_speaker != self.char_name {
+            *most_recent_speaker = self.char_name.to_string();
+            let _ = writeln!(stdout);
+            let _ = writeln!(stdout,"Speaker: {}", self.speaker_header(cast));
+        }
+
+        //'either case should print out text'. A wrapped line is delivered one sub-line at a time
+        for a_sub_line in self.char_lines[line_idx].1.split(SUB_LINE_DELIM) {
+            let _ = writeln!(stdout,"{:?}", a_sub_line);
+        }
+    }
     
     
+    //moves past the next line without delivering it, used when reciting an excerpt
+    pub fn skip_line(&mut self) {
+        if self.cur_entry_idx < self.char_lines.len() {
+            self.cur_entry_idx += 1
+        }
+    }
+
     //checks if the current character still has a next line, return line num if yes None if doesn't
-    pub fn next_line(&self) -> Option<usize> {
+    pub fn next_line(&self) -> Option<LineNum> {
         if self.cur_entry_idx < self.char_lines.len(){
             return Some(self.char_lines[self.cur_entry_idx].0)
         }else{
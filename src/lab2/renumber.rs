@@ -0,0 +1,418 @@
+//renumber.rs works out contiguous line numbers for a prepared Play from the merged speaking order of each fragment or scene, and rewrites the part files with them, in place or into another directory, or shows the changes without writing. Aman Verma, Hanson Li, Johnny Huang
+
+use super::declarations::{CHECK_FAILURE, GENERATION_FAILURE};
+use super::line_num::LineNum;
+use super::play::{Fragments, Play};
+use super::player::SUB_LINE_DELIM;
+use super::script_gen::{decode_file_lines, encode_file_lines};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_FIRST_LINE: usize = 0; //recite expects every fragment to start at line 0
+
+pub type RenumberedLine = (LineNum, String, usize); //old line number, line text and new line number
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenumberScope {
+    Fragment, //numbering starts again in every fragment
+    Scene,    //numbering runs on through all the fragments of a scene
+}
+
+impl RenumberScope {
+    pub fn from_name(scope_name: &str) -> Option<Self> {
+        match scope_name {
+            "fragment" => Some(RenumberScope::Fragment),
+            "scene" => Some(RenumberScope::Scene),
+            _ => None,
+        }
+    }
+}
+
+//what to do with lines that share a number within a fragment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DuplicatePolicy {
+    Sequence, //each line gets its own number, in the order recite delivers them
+    Share,    //lines that shared a number keep sharing the new one
+    Fail,     //list the duplicates and change nothing
+}
+
+impl DuplicatePolicy {
+    pub fn from_name(policy_name: &str) -> Option<Self> {
+        match policy_name {
+            "sequence" => Some(DuplicatePolicy::Sequence),
+            "share" => Some(DuplicatePolicy::Share),
+            "fail" => Some(DuplicatePolicy::Fail),
+            _ => None,
+        }
+    }
+}
+
+//the new numbers for one part file's lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartRewrite {
+    pub part_file: String,
+    pub lines: Vec<RenumberedLine>,
+}
+
+//whether numbered_text, the text on a numbered line of a part file, is the start of line_text as it was read
+fn starts_line_text(line_text: &str, numbered_text: &str) -> bool {
+    match line_text.strip_prefix(numbered_text) {
+        Some(carried_on) => carried_on.is_empty() || numbered_text.is_empty() || carried_on.starts_with([' ', SUB_LINE_DELIM]),
+        None => false,
+    }
+}
+
+impl PartRewrite {
+    pub fn changed_lines(&self) -> usize {
+        self.lines.iter().filter(|(old_num, _, new_num)| *old_num != LineNum::from(*new_num)).count()
+    }
+
+    //the part file's lines with each numbered line given its new number, other lines are kept as they are
+    pub fn rewritten_lines(&self, original_lines: &[String]) -> Vec<String> {
+        let mut unused: Vec<bool> = vec![true; self.lines.len()];
+        let mut rewritten: Vec<String> = Vec::new();
+        for a_line in original_lines.iter() {
+            let trimmed = a_line.trim();
+            let (first_token, remain_token) = trimmed.split_once(char::is_whitespace).unwrap_or((trimmed, ""));
+            let new_line = LineNum::parse(first_token).and_then(|old_num| {
+                //lines are matched by number and text, so repeated lines are taken in file order. With --continuation the text
+                //read also has the lines that carried it on, so only its start has to be on the numbered line
+                let numbered_text = remain_token.trim();
+                let line_idx = self.lines.iter().enumerate().position(|(line_idx, (a_num, a_text, _))| {
+                    unused[line_idx] && *a_num == old_num && a_text == numbered_text
+                }).or_else(|| self.lines.iter().enumerate().position(|(line_idx, (a_num, a_text, _))| {
+                    unused[line_idx] && *a_num == old_num && starts_line_text(a_text, numbered_text)
+                }))?;
+                unused[line_idx] = false;
+                //only the number is replaced, so indentation and trailing spaces are kept
+                let number_start = a_line.len() - a_line.trim_start().len();
+                Some(format!("{}{}{}", &a_line[..number_start], self.lines[line_idx].2, &a_line[number_start + first_token.len()..]))
+            });
+            rewritten.push(new_line.unwrap_or_else(|| a_line.to_string()));
+        }
+        rewritten
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Renumbering {
+    pub scope: RenumberScope,
+    pub policy: DuplicatePolicy,
+    pub first_line: usize,
+    pub force: bool, //renumber even fragments with enter@/exit@ annotations, whose numbers in the config files are left as they are
+}
+
+impl Renumbering {
+    pub fn new() -> Self {
+        Self {
+            scope: RenumberScope::Fragment,
+            policy: DuplicatePolicy::Sequence,
+            first_line: DEFAULT_FIRST_LINE,
+            force: false,
+        }
+    }
+
+    //numbers the lines of a run of fragments that share one count, adding them to the part files' rewrites.
+    //returns the duplicate line numbers found, as messages, and how many fragments have enter@/exit@ annotations
+    fn number_fragments(&self, fragments: &Fragments, scene_title: &str, rewrites: &mut Vec<PartRewrite>) -> Result<(Vec<String>, usize), u8> {
+        let mut stderr = io::stderr().lock();
+        let mut duplicates: Vec<String> = Vec::new();
+        let mut next_num = self.first_line;
+        let mut annotated_count: usize = 0;
+
+        for (fragment_idx, a_fragment) in fragments.iter().enumerate() {
+            if self.scope == RenumberScope::Fragment {
+                next_num = self.first_line;
+            }
+            let scene_ref = match a_fragment.lock() {
+                Ok(scene_ref) => scene_ref,
+                Err(_) => {
+                    let _ = writeln!(stderr, "Error: Failed to acquire lock on ith scene fragment");
+                    return Err(GENERATION_FAILURE);
+                }
+            };
+
+            //an annotation naming a line by number would point at the old numbering afterwards
+            if scene_ref.chars_in_play.iter().any(|a_player| a_player.enter_at.is_some() || a_player.exit_at.is_some()) {
+                annotated_count += 1;
+                let _ = writeln!(stderr, "Warning: {} fragment {} has enter@/exit@ annotations in {}, their line numbers are not renumbered",
+                    scene_title, fragment_idx + 1, scene_ref.config_file);
+            }
+
+            //new line numbers for each player's lines, in the order of their char_lines
+            let mut new_numbers: Vec<Vec<usize>> = scene_ref.chars_in_play.iter().map(|a_player| vec![0; a_player.char_lines.len()]).collect();
+            let speaking_order = scene_ref.speaking_order();
+            let mut prev_old_num: Option<LineNum> = None;
+            for (line_num, player_idx, line_idx) in speaking_order.iter() {
+                let shares_number = prev_old_num == Some(*line_num);
+                if shares_number {
+                    duplicates.push(format!("{} fragment {}: line {} is used more than once ({})",
+                        scene_title, fragment_idx + 1, line_num, scene_ref.chars_in_play[*player_idx].part_file));
+                }
+                if shares_number && self.policy == DuplicatePolicy::Share {
+                    new_numbers[*player_idx][*line_idx] = next_num - 1;
+                } else {
+                    new_numbers[*player_idx][*line_idx] = next_num;
+                    next_num += 1;
+                }
+                prev_old_num = Some(*line_num);
+            }
+
+            for (a_player, player_numbers) in scene_ref.chars_in_play.iter().zip(new_numbers) {
+                let part_lines: Vec<RenumberedLine> = a_player.char_lines.iter().zip(player_numbers)
+                    .map(|((old_num, a_text), new_num)| (*old_num, a_text.to_string(), new_num)).collect();
+                let a_rewrite = PartRewrite { part_file: a_player.part_file.to_string(), lines: part_lines };
+
+                //a part file used by several fragments can only be rewritten if they all agree on its numbers
+                match rewrites.iter().find(|an_existing| an_existing.part_file == a_rewrite.part_file) {
+                    Some(an_existing) if *an_existing != a_rewrite => {
+                        let _ = writeln!(stderr, "Error: part file {} is used by more than one fragment and cannot be given one numbering", a_rewrite.part_file);
+                        return Err(GENERATION_FAILURE);
+                    }
+                    Some(_) => {}
+                    None => rewrites.push(a_rewrite),
+                }
+            }
+        }
+        Ok((duplicates, annotated_count))
+    }
+
+    //works out the new numbers for every part file of the play. Fails with CHECK_FAILURE under the fail policy if any line numbers are shared,
+    //and without force if any fragment has enter@/exit@ annotations
+    pub fn plan(&self, play: &Play) -> Result<Vec<PartRewrite>, u8> {
+        let mut stderr = io::stderr().lock();
+        let mut rewrites: Vec<PartRewrite> = Vec::new();
+        let mut duplicates: Vec<String> = Vec::new();
+        let mut annotated_count: usize = 0;
+
+        for an_act in play.acts().iter() {
+            for a_scene in an_act.scenes.iter() {
+                let (scene_duplicates, scene_annotated) = self.number_fragments(&a_scene.fragments, &a_scene.meta.short_title(), &mut rewrites)?;
+                duplicates.extend(scene_duplicates);
+                annotated_count += scene_annotated;
+            }
+        }
+
+        if annotated_count > 0 && !self.force {
+            let _ = writeln!(stderr, "Error: {} fragment(s) have enter@/exit@ annotations, nothing was renumbered. Use --force to renumber anyway and fix the annotations by hand", annotated_count);
+            return Err(CHECK_FAILURE);
+        }
+
+        if self.policy == DuplicatePolicy::Fail && !duplicates.is_empty() {
+            for a_duplicate in duplicates.iter() {
+                let _ = writeln!(stderr, "Duplicate: {}", a_duplicate);
+            }
+            let _ = writeln!(stderr, "Error: {} duplicate line number(s), nothing was renumbered", duplicates.len());
+            return Err(CHECK_FAILURE);
+        }
+        Ok(rewrites)
+    }
+}
+
+impl Default for Renumbering {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//shows each line of a part file that would change, with its line in the file
+fn print_part_diff(part_file: &str, original_lines: &[String], rewritten_lines: &[String]) {
+    let mut stdout = io::stdout().lock();
+    let _ = writeln!(stdout, "--- {}", part_file);
+    let _ = writeln!(stdout, "+++ {}", part_file);
+    for (file_line_idx, (old_line, new_line)) in original_lines.iter().zip(rewritten_lines.iter()).enumerate() {
+        if old_line != new_line {
+            let _ = writeln!(stdout, "@@ line {} @@", file_line_idx + 1);
+            let _ = writeln!(stdout, "-{}", old_line);
+            let _ = writeln!(stdout, "+{}", new_line);
+        }
+    }
+}
+
+//where a part file is written, into output_dir by file name if given or over the original
+fn target_file(part_file: &str, output_dir: Option<&str>) -> PathBuf {
+    match output_dir {
+        Some(dir_name) => Path::new(dir_name).join(Path::new(part_file).file_name().unwrap_or_default()),
+        None => Path::new(part_file).to_path_buf(),
+    }
+}
+
+//two part files with the same name in different directories, e.g. data/acts/one/Polonius.txt and data/acts/two/Polonius.txt, would
+//be written over each other in output_dir. Returns the first such pair
+fn colliding_parts<'r>(rewrites: &'r [PartRewrite], output_dir: Option<&str>) -> Option<(&'r str, &'r str, PathBuf)> {
+    let mut targets: Vec<(&str, PathBuf)> = Vec::new();
+    for a_rewrite in rewrites.iter().filter(|a_rewrite| a_rewrite.changed_lines() > 0) {
+        let a_target = target_file(&a_rewrite.part_file, output_dir);
+        if let Some((other_part, _)) = targets.iter().find(|(_, other_target)| *other_target == a_target) {
+            return Some((other_part, &a_rewrite.part_file, a_target));
+        }
+        targets.push((&a_rewrite.part_file, a_target));
+    }
+    None
+}
+
+//writes the renumbered part files, into output_dir if given or over the originals. With dry_run only the changes are printed.
+//returns how many part files changed
+pub fn apply_rewrites(rewrites: &[PartRewrite], output_dir: Option<&str>, dry_run: bool) -> Result<usize, u8> {
+    let mut stdout = io::stdout().lock();
+    let mut stderr = io::stderr().lock();
+    let mut changed_files: usize = 0;
+    let mut changed_lines: usize = 0;
+
+    //checked before anything is written, so a clash does not leave half the part files renumbered
+    if let (Some((first_part, second_part, a_target)), false) = (colliding_parts(rewrites, output_dir), dry_run) {
+        let _ = writeln!(stderr, "Error: part files {} and {} would both be written to {}, nothing was renumbered. Renumber in place or give them different names",
+            first_part, second_part, a_target.display());
+        return Err(GENERATION_FAILURE);
+    }
+
+    if let (Some(dir_name), false) = (output_dir, dry_run) {
+        if let Err(e_msg) = fs::create_dir_all(dir_name) {
+            let _ = writeln!(stderr, "Error: could not create output directory {}: {}", dir_name, e_msg);
+            return Err(GENERATION_FAILURE);
+        }
+    }
+
+    for a_rewrite in rewrites.iter().filter(|a_rewrite| a_rewrite.changed_lines() > 0) {
+        //read the way the play was, so a Latin-1 part file or one with \r\n or \r line endings is rewritten as it is
+        let file_bytes = match fs::read(&a_rewrite.part_file) {
+            Ok(file_bytes) => file_bytes,
+            Err(e_msg) => {
+                let _ = writeln!(stderr, "Error: could not read part file {}: {}", a_rewrite.part_file, e_msg);
+                return Err(GENERATION_FAILURE);
+            }
+        };
+        let original_lines = decode_file_lines(&a_rewrite.part_file, &file_bytes)?;
+        let rewritten_lines = a_rewrite.rewritten_lines(&original_lines);
+        changed_files += 1;
+        changed_lines += a_rewrite.changed_lines();
+
+        if dry_run {
+            print_part_diff(&a_rewrite.part_file, &original_lines, &rewritten_lines);
+            continue;
+        }
+        let target_file = target_file(&a_rewrite.part_file, output_dir);
+        if let Err(e_msg) = fs::write(&target_file, encode_file_lines(&a_rewrite.part_file, &file_bytes, &original_lines, &rewritten_lines)) {
+            let _ = writeln!(stderr, "Error: could not write part file {}: {}", target_file.display(), e_msg);
+            return Err(GENERATION_FAILURE);
+        }
+        let _ = writeln!(stdout, "Renumbered {}", target_file.display());
+    }
+
+    let _ = writeln!(stdout, "{} line(s) renumbered in {} part file(s){}", changed_lines, changed_files, if dry_run { " (dry run, nothing written)" } else { "" });
+    Ok(changed_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::player::Player;
+    use super::super::scene_fragments::SceneFragment;
+    use super::super::scene_meta::SceneMeta;
+
+    //a player reading their lines from part_file, with lines as (old number, text)
+    fn player(char_name: &str, part_file: &str, char_lines: &[(usize, &str)]) -> Player {
+        let mut plyr = Player::new(&char_name.to_string());
+        plyr.part_file = part_file.to_string();
+        plyr.char_lines = char_lines.iter().map(|(line_num, line_text)| (LineNum::from(*line_num), line_text.to_string())).collect();
+        plyr
+    }
+
+    //one scene with a fragment for each list of players
+    fn play(fragments: Vec<Vec<Player>>) -> Play {
+        let mut play = Play::new();
+        play.push_scene(SceneMeta::from_title("Hamlet ACT II Scene I A room"));
+        for fragment_players in fragments {
+            let mut fragment = SceneFragment::new(&"Hamlet ACT II Scene I A room".to_string());
+            for a_player in fragment_players {
+                fragment.add_player(a_player);
+            }
+            play.push_fragment(fragment);
+        }
+        play
+    }
+
+    fn new_numbers(rewrites: &[PartRewrite], part_file: &str) -> Vec<usize> {
+        rewrites.iter().find(|a_rewrite| a_rewrite.part_file == part_file).expect("part file should be planned").lines.iter().map(|(_, _, new_num)| *new_num).collect()
+    }
+
+    #[test]
+    fn lines_are_numbered_in_speaking_order() {
+        let a_play = play(vec![
+            vec![player("Polonius", "pol_a.txt", &[(2, "Give him this money"), (10, "Farewell")]), player("Reynaldo", "rey_a.txt", &[(5, "I will, my lord")])],
+            vec![player("Ophelia", "oph_b.txt", &[(3, "O, my lord")])],
+        ]);
+        let rewrites = Renumbering::new().plan(&a_play).unwrap();
+        assert_eq!(new_numbers(&rewrites, "pol_a.txt"), vec![0, 2]);
+        assert_eq!(new_numbers(&rewrites, "rey_a.txt"), vec![1]);
+        assert_eq!(new_numbers(&rewrites, "oph_b.txt"), vec![0]);
+
+        //per scene the count runs on into the second fragment, from the start given
+        let mut renumbering = Renumbering::new();
+        renumbering.scope = RenumberScope::Scene;
+        renumbering.first_line = 1;
+        let rewrites = renumbering.plan(&a_play).unwrap();
+        assert_eq!(new_numbers(&rewrites, "pol_a.txt"), vec![1, 3]);
+        assert_eq!(new_numbers(&rewrites, "oph_b.txt"), vec![4]);
+    }
+
+    #[test]
+    fn shared_numbers_follow_the_policy() {
+        let a_play = play(vec![vec![player("Polonius", "pol_a.txt", &[(1, "Marry, well said"), (2, "very well said")]), player("Reynaldo", "rey_a.txt", &[(1, "My lord?")])]]);
+        let mut renumbering = Renumbering::new();
+        let rewrites = renumbering.plan(&a_play).unwrap();
+        let mut sequenced: Vec<usize> = [new_numbers(&rewrites, "pol_a.txt"), new_numbers(&rewrites, "rey_a.txt")].concat();
+        sequenced.sort();
+        assert_eq!(sequenced, vec![0, 1, 2]);
+        renumbering.policy = DuplicatePolicy::Share;
+        let rewrites = renumbering.plan(&a_play).unwrap();
+        assert_eq!(new_numbers(&rewrites, "pol_a.txt")[0], new_numbers(&rewrites, "rey_a.txt")[0]);
+        assert_eq!(new_numbers(&rewrites, "pol_a.txt")[1], 1);
+        renumbering.policy = DuplicatePolicy::Fail;
+        assert_eq!(renumbering.plan(&a_play), Err(CHECK_FAILURE));
+    }
+
+    #[test]
+    fn annotations_and_shared_part_files_stop_the_plan() {
+        let mut annotated = player("Reynaldo", "rey_a.txt", &[(1, "My lord?")]);
+        annotated.exit_at = Some(LineNum::from(1));
+        let a_play = play(vec![vec![player("Polonius", "pol_a.txt", &[(0, "Give him this money")]), annotated]]);
+        let mut renumbering = Renumbering::new();
+        assert_eq!(renumbering.plan(&a_play), Err(CHECK_FAILURE));
+        renumbering.force = true;
+        assert!(renumbering.plan(&a_play).is_ok());
+
+        //one part file in two fragments is fine while both number it the same way
+        let same_play = play(vec![vec![player("Polonius", "pol.txt", &[(0, "Farewell")])], vec![player("Polonius", "pol.txt", &[(0, "Farewell")])]]);
+        assert_eq!(Renumbering::new().plan(&same_play).map(|rewrites| rewrites.len()), Ok(1));
+        let clash_play = play(vec![
+            vec![player("Polonius", "pol.txt", &[(4, "Farewell")])],
+            vec![player("Ophelia", "oph.txt", &[(0, "O, my lord")]), player("Polonius", "pol.txt", &[(4, "Farewell")])],
+        ]);
+        assert_eq!(Renumbering::new().plan(&clash_play), Err(GENERATION_FAILURE));
+    }
+
+    #[test]
+    fn only_the_number_is_rewritten() {
+        let a_rewrite = PartRewrite { part_file: "pol.txt".to_string(), lines: vec![
+            (LineNum::from(4), "Farewell".to_string(), 0),
+            (LineNum::from(4), "Farewell".to_string(), 7),
+            (LineNum::from(9), "Give him this money and these notes".to_string(), 1),
+        ]};
+        let original_lines: Vec<String> = ["  4 Farewell  ", "# a note", "4 Farewell", "9 Give him this money", "and these notes"].iter().map(|a_line| a_line.to_string()).collect();
+        assert_eq!(a_rewrite.rewritten_lines(&original_lines), vec!["  0 Farewell  ", "# a note", "7 Farewell", "1 Give him this money", "and these notes"]);
+        assert_eq!(a_rewrite.changed_lines(), 3);
+    }
+
+    #[test]
+    fn same_named_parts_collide_in_an_output_dir() {
+        let rewrites = vec![
+            PartRewrite { part_file: "acts/one/Polonius.txt".to_string(), lines: vec![(LineNum::from(3), "Farewell".to_string(), 0)] },
+            PartRewrite { part_file: "acts/two/Polonius.txt".to_string(), lines: vec![(LineNum::from(5), "Farewell".to_string(), 0)] },
+        ];
+        assert!(colliding_parts(&rewrites, None).is_none());
+        let (first_part, second_part, _) = colliding_parts(&rewrites, Some("out")).expect("the two parts should collide");
+        assert_eq!((first_part, second_part), ("acts/one/Polonius.txt", "acts/two/Polonius.txt"));
+    }
+}
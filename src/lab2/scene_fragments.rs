@@ -1,11 +1,18 @@
 //scene_fragments.rs. Declares the SceneFragment struct that holds a vec of players in a scene, with asscoiated functions for annoucning entrances/exists, and processing the config files and reciting the lines of each character stored in the SceneFragment as well as processing the config files. Johnny Huang, Hanson Li, Aman Verma
 
-use super::player::Player;
+use super::player::{Player, PRESENCE_DELIM, ALL_GROUP};
+use super::cast::Cast;
+use super::actors::{Stage, perform};
+use super::excerpt::Excerpt;
+use super::line_num::{LineNum, flat_line_num};
 use super::declarations::{WHINGE,GENERATION_FAILURE};
 use std::sync::atomic::Ordering;
-use super::script_gen::grab_trimmed_file_lines;
+use super::script_gen::grab_file_lines;
+use super::tokens::{TokenError, split_tokens};
+use super::variables::substitute_token;
 use std::collections::HashSet; //need hashset for checking duplicate lines
 use std::io::{self, Write};
+use std::thread;
 
 pub const TITLE_IDX: usize = 0;             //index of the line giving the title of the play
 pub const PART_FILE_IDX: usize = 1; //index of the first line containing character info
@@ -13,11 +20,13 @@ pub const CHAR_NAME_POS: usize = 0; //index of the character's name in a line
 pub const FILE_NAME_TOKEN_POS: usize = 1;      //index of the file containing the character's lines
 pub const EXPECTED_TOKENS: usize = 2;       //expected number of tokens in a character line
 
-pub type PlayConfig = Vec<(String, String)>;
+pub type SpeakingOrder = Vec<(LineNum, usize, usize)>; //line number, player index and index into that player's char_lines, in the order recite delivers them
+pub type PlayConfig = Vec<(String, String, Vec<String>)>; //character name, part file, and any enter@N/exit@N presence annotations
 
 pub struct SceneFragment{
     pub scene_title: String,
     pub chars_in_play: Vec<Player>,
+    pub config_file: String, //config file the fragment was read from, empty for a fragment built in memory
 }
 
 impl SceneFragment{
@@ -26,6 +35,7 @@ impl SceneFragment{
          Self {
             scene_title: fragment_title.to_string(),
             chars_in_play: Vec::new(),
+            config_file: String::new(),
          }
     }
 
@@ -36,13 +46,18 @@ impl SceneFragment{
 
         for a_cfg in play_cfg.iter() {
             //example from Expressions slide: match t {(x, y) => do_func(x,y);}
-            match a_cfg {(char_name, speak_file) => {
+            match a_cfg {(char_name, speak_file, annotations) => {
               let mut new_player = Player::new(&char_name); //need mut since prepare take mut &self
 
               if let Err(e) = new_player.prepare(speak_file){ //TODO: confirm if this is the prepare function he wants us to call and if we should call this before or after push to vec?
                 let _ = writeln!(stderr,"Error from process_config of SceneFragment: {}", e);
                 return Err(GENERATION_FAILURE);
               }
+
+              //presence annotations can refer to first/last, so they are resolved once the lines are read
+              for an_annotation in annotations.iter() {
+                new_player.add_presence(an_annotation);
+              }
               
               self.chars_in_play.push(new_player);
             }}
@@ -51,25 +66,37 @@ impl SceneFragment{
     }
 
     // add parsed config line to a vector (PlayConfig) holding the lines split by character name and the config file path
-    pub fn add_config(&self, cfg_line: &String, play_cfg: &mut PlayConfig){
-        //split_whitespace gives an iterable, and collect turns that into a collection
+    //Err with the column if the line cannot be split into tokens or its part file uses a ${NAME} that is not defined
+    pub fn add_config(&self, cfg_line: &String, play_cfg: &mut PlayConfig) -> Result<(), TokenError> {
+        //split_tokens drops comments and resolves quotes and escapes, so "Player King" is one character name
         //since using &str, need to do .to_string when inserting into play_cfg because it is of type <String, String>
-        let cfg_items: Vec<&str> = cfg_line.split_whitespace().collect(); 
+        let cfg_tokens = split_tokens(cfg_line)?;
+        let cfg_items: Vec<&str> = cfg_tokens.iter().map(|(token_text, _)| token_text.as_str()).collect();
         let mut stderr = io::stderr().lock();
 
-        if cfg_items.len() > EXPECTED_TOKENS {
-            if WHINGE.load(Ordering::SeqCst) {
-                let _ = writeln!(stderr,"Error: expecting config line to have 2 items but got more than 2 items, pushing first 2 elements");
-            }
-            play_cfg.push((cfg_items[CHAR_NAME_POS].to_string(), cfg_items[FILE_NAME_TOKEN_POS].to_string()))
-        } else if cfg_items.len() < EXPECTED_TOKENS {
+        //a blank or comment only line is not a config line
+        if cfg_items.is_empty() {
+            return Ok(());
+        }
+        if cfg_items.len() < EXPECTED_TOKENS {
             if WHINGE.load(Ordering::SeqCst) {
                 let _ = writeln!(stderr,"Error: expecting config line to have 2 items but got less than 2 items. Not pushing anything");
             }
-        } else {
-            play_cfg.push((cfg_items[CHAR_NAME_POS].to_string(), cfg_items[FILE_NAME_TOKEN_POS].to_string()))
+            return Ok(());
+        }
+
+        //anything past the part file is either a presence annotation or a stray token we whinge about and drop
+        let mut annotations: Vec<String> = Vec::new();
+        for extra_item in cfg_items[EXPECTED_TOKENS..].iter() {
+            if extra_item.contains(PRESENCE_DELIM) {
+                annotations.push(extra_item.to_string());
+            } else if WHINGE.load(Ordering::SeqCst) {
+                let _ = writeln!(stderr,"Error: unexpected token '{}' after the part file in config line, ignoring it", extra_item);
+            }
         }
 
+        play_cfg.push((cfg_items[CHAR_NAME_POS].to_string(), substitute_token(&cfg_tokens[FILE_NAME_TOKEN_POS])?, annotations));
+        Ok(())
     }
     
     // calls grab_trimmed_file_lines to populate a vector of strings holding the unsplit character and config file path, then call add_config on each of those lines to split and store into the PlayConfig 
@@ -80,7 +107,7 @@ impl SceneFragment{
         let mut stdout = io::stdout().lock(); // Lock stderr
 
 
-        match grab_trimmed_file_lines(&cfg_fname, &mut cfg_lines) {
+        match grab_file_lines(&cfg_fname, &mut cfg_lines) {
             Ok(_) => { //don't really need to read the ok code so use _
                 
                 // A config file can have 1 line, so we just check if it's empty
@@ -89,9 +116,12 @@ impl SceneFragment{
                     return Err(GENERATION_FAILURE);
                 }
             
-                for a_cfg_line in cfg_lines.iter() {
+                for (line_idx, a_cfg_line) in cfg_lines.iter().enumerate() {
                     //iter should already make a_cfg_line of &String type
-                    self.add_config(a_cfg_line, play_cfg)
+                    if let Err((column, problem)) = self.add_config(a_cfg_line, play_cfg) {
+                        let _ = writeln!(stdout,"Error: {} line {} column {}: {}", cfg_fname, line_idx + 1, column, problem);
+                        return Err(GENERATION_FAILURE);
+                    }
                 }
             },
             Err(e_code) => {
@@ -110,6 +140,7 @@ impl SceneFragment{
         //change the original script gen params: play_title: &mut String, play_vec: &mut SceneFragment to fields from SceneFragment struct
         let mut playcfg_var = PlayConfig::new();
         let mut stderr = io::stderr().lock();
+        self.config_file = cfg_fname.to_string();
 
         if let Err(e_code) = self.read_config(cfg_fname, &mut playcfg_var) {
             let _ = writeln!(stderr,"Error: in script_gen, read_config call failed with error code {}", e_code);
@@ -121,108 +152,389 @@ impl SceneFragment{
             return Err(GENERATION_FAILURE);
         }
 
-        self.chars_in_play.sort();
+        self.prepare_players();
         Ok(())
     }
 
-    //For each player stored in the vector of Player, we print their lines in order by extracting their line number (first pos in tuple) with the index of the Player struct in the vector and store in a vector of <usize, usize>, then we sort that vector by line number, which gives us the correct order of who should be speaking. 
-    pub fn recite(&mut self) -> Result<(), u8> {
+    //adds a player built in memory, e.g. by an importer, rather than read from a config file
+    pub fn add_player(&mut self, plyr: Player) {
+        self.chars_in_play.push(plyr);
+    }
 
-        let mut most_recent_speaker = String::new();
-        //we can store the character's line number and the Player object's idx in a vector. Sort it by line number, and loop through this vector and call .speak
-        let mut linenum_and_speaker_vec: Vec<(usize, usize)> = Vec::new();
-        let mut linenum_set: HashSet<usize> = HashSet::new(); //use hashset to track dupe lines. If we insert dupe, it returns false so we use that to trigger whinge
+    //gives every line, entrance and exit a whole number in place of any sub-line numbers, for exports that want 12, 13, 14 rather than 12, 12.1, 13
+    pub fn flatten_line_numbers(&mut self) {
+        let mut sub_lines: Vec<LineNum> = self.chars_in_play.iter()
+            .flat_map(|a_player| a_player.char_lines.iter().map(|(line_num, _)| *line_num))
+            .filter(|line_num| line_num.is_sub_line())
+            .collect();
+        sub_lines.sort();
+        sub_lines.dedup(); //a shared line spoken by several players takes one number
+        for a_player in self.chars_in_play.iter_mut() {
+            for (line_num, _) in a_player.char_lines.iter_mut() {
+                *line_num = LineNum::from(flat_line_num(*line_num, &sub_lines));
+            }
+            a_player.enter_at = a_player.enter_at.map(|enter_line| LineNum::from(flat_line_num(enter_line, &sub_lines)));
+            a_player.exit_at = a_player.exit_at.map(|exit_line| LineNum::from(flat_line_num(exit_line, &sub_lines)));
+        }
+    }
+
+    //fills in ALL groups and puts players in order of their first line, once every player has been added
+    pub fn prepare_players(&mut self) {
+        self.resolve_all_group();
+        self.chars_in_play.sort();
+    }
+
+    //an ALL player speaks for every other character configured in the fragment
+    fn resolve_all_group(&mut self) {
         let mut stderr = io::stderr().lock();
+        let everyone = self.names_in_fragment();
 
-        for (player_idx, a_player) in self.chars_in_play.iter().enumerate(){
-            for (line_num, _)in a_player.char_lines.iter(){
-                
-                linenum_and_speaker_vec.push((*line_num, player_idx));
-                
-                //insert again into linenume_set to check for dupes
-                let linenum_insert_status = linenum_set.insert(*line_num);
-                if !linenum_insert_status{
-                    if WHINGE.load(Ordering::SeqCst){
-                        let _ = writeln!(stderr,"WHINGE Warning: duplicate line detected for line number: {}", line_num);
-                    }
-                }
+        for plyr in self.chars_in_play.iter_mut().filter(|a_player| a_player.char_name == ALL_GROUP) {
+            if everyone.is_empty() && WHINGE.load(Ordering::SeqCst) {
+                let _ = writeln!(stderr,"Whinge Warning: {} lines are configured but no other character is in the fragment", ALL_GROUP);
             }
+            plyr.members = everyone.clone();
+        }
+    }
+
+    //every character in the fragment by name, in player order and without repeats
+    pub fn names_in_fragment(&self) -> Vec<String> {
+        self.names_where(|_| true)
+    }
+
+    //characters on stage when the fragment begins
+    pub fn names_at_start(&self) -> Vec<String> {
+        self.names_where(|a_player| a_player.on_stage_at_start())
+    }
+
+    //characters still on stage when the fragment ends
+    pub fn names_at_end(&self) -> Vec<String> {
+        self.names_where(|a_player| a_player.on_stage_at_end())
+    }
+
+    //first and last line numbers during which the character is on stage, 0 and usize::MAX stand for the fragment boundaries. None if they are not in the fragment
+    pub fn presence_range(&self, char_name: &String) -> Option<(LineNum, LineNum)> {
+        let mut presence: Option<(LineNum, LineNum)> = None;
+        for plyr in self.chars_in_play.iter().filter(|a_player| a_player.char_name != ALL_GROUP && a_player.members.contains(char_name)) {
+            let enter_line = plyr.enter_at.unwrap_or_default();
+            let exit_line = plyr.exit_at.unwrap_or(LineNum::from(usize::MAX));
+            //a character in several parts is on stage from the earliest entrance to the latest exit
+            presence = match presence {
+                Some((first_line, last_line)) => Some((first_line.min(enter_line), last_line.max(exit_line))),
+                None => Some((enter_line, exit_line)),
+            };
         }
+        presence
+    }
+
+    //characters on stage when recitation starts at from_line, None meaning the start of the fragment
+    pub fn names_on_stage_from(&self, from_line: Option<usize>) -> Vec<String> {
+        self.names_where(|a_player| a_player.on_stage_from(from_line))
+    }
 
-        //sort by line_num
-        linenum_and_speaker_vec.sort_by_key(|a_tuple| a_tuple.0);
+    //characters on stage when recitation stops after to_line, None meaning the end of the fragment
+    pub fn names_on_stage_until(&self, to_line: Option<usize>) -> Vec<String> {
+        self.names_where(|a_player| a_player.on_stage_until(to_line))
+    }
 
-        //Whinge if the first line doesn't start at 0 or there are duplicate lines
-        if WHINGE.load(Ordering::SeqCst) {
-            if linenum_and_speaker_vec[0].0 != 0{
-                let _ = writeln!(stderr,"WHINGE Warning: line number should start at 0!");
+    //collects the members of the matching players, a group player contributes each of its members
+    //ALL is skipped since its members are only ever on stage through their own parts
+    fn names_where<F: Fn(&Player) -> bool>(&self, keep_player: F) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for plyr in self.chars_in_play.iter().filter(|a_player| a_player.char_name != ALL_GROUP && keep_player(a_player)) {
+            for a_member in plyr.members.iter() {
+                if !names.contains(a_member) {
+                    names.push(a_member.to_string());
+                }
+            }
+        }
+        names
+    }
+
+    //every line of the fragment in the order recite delivers them, lines sharing a number keep player order
+    pub fn speaking_order(&self) -> SpeakingOrder {
+        let mut speaking_order: SpeakingOrder = Vec::new();
+        for (player_idx, a_player) in self.chars_in_play.iter().enumerate() {
+            for (line_idx, (line_num, _)) in a_player.char_lines.iter().enumerate() {
+                speaking_order.push((*line_num, player_idx, line_idx));
             }
-            
         }
+        speaking_order.sort_by_key(|a_tuple| a_tuple.0); //stable, so ties stay in player order like in recite
+        speaking_order
+    }
+
+    //For each player stored in the vector of Player, we print their lines in order by extracting their line number (first pos in tuple) with the index of the Player struct in the vector and store in a vector of <usize, usize>, then we sort that vector by line number, which gives us the correct order of who should be speaking. 
+    //only the lines and speakers the excerpt selects are delivered, the rest are skipped over
+    pub fn recite(&mut self, cast: &Cast, excerpt: &Excerpt) -> Result<(), u8> {
+
+        let mut most_recent_speaker = String::new();
+        //we can store the character's line number and the Player object's idx in a vector. Sort it by line number, and loop through this vector and call .speak
+        let linenum_and_speaker_vec = self.cue_order();
+
+        self.check_line_numbers();
+        let (mut entered, mut exited) = self.presence_from(excerpt);
+        //exits are announced before the first line past the excerpt, or at the end of the fragment. Sub-lines of to_line are still in the excerpt
+        let excerpt_end = excerpt.to_line.map(|to_line| LineNum::from(to_line + 1));
+
         //loop through vector to get player idx and call speak
-        for (line_num_speak, player_idx) in linenum_and_speaker_vec.iter(){ //line_num_speak are the line numbers a character is suppoed to speak according to our sorting. Use this with next_line to prevent character from speaking all their lines.
+        for (speak_idx, (line_num_speak, player_idx)) in linenum_and_speaker_vec.iter().enumerate(){ //line_num_speak are the line numbers a character is suppoed to speak according to our sorting. Use this with next_line to prevent character from speaking all their lines.
+            let in_excerpt = excerpt.line_in_range(*line_num_speak);
+            if in_excerpt {
+                self.announce_entrances(*line_num_speak, &mut entered, &exited, &mut most_recent_speaker, cast);
+            }
+
+            let delivers_player = excerpt.delivers(&self.chars_in_play[*player_idx]);
             while let Some(line_num) = self.chars_in_play[*player_idx].next_line(){  //.iter.enumerate gives reference
                 if line_num <= *line_num_speak{ 
-                    self.chars_in_play[*player_idx].speak(&mut most_recent_speaker);
+                    if delivers_player && excerpt.line_in_range(line_num) {
+                        self.chars_in_play[*player_idx].speak(&mut most_recent_speaker, cast);
+                    } else {
+                        self.chars_in_play[*player_idx].skip_line();
+                    }
 
                 }else{
                     break
                 }
             }
+
+            if in_excerpt {
+                self.announce_exits(Self::next_cue_line(&linenum_and_speaker_vec, speak_idx, excerpt_end), &entered, &mut exited, &mut most_recent_speaker, cast);
+            }
+        }
+
+        //presence lines past the last spoken line still get announced before the fragment (or excerpt) ends
+        self.announce_entrances(LineNum::through_sub_lines(excerpt.to_line.unwrap_or(usize::MAX)), &mut entered, &exited, &mut most_recent_speaker, cast);
+        self.announce_exits(excerpt_end, &entered, &mut exited, &mut most_recent_speaker, cast);
+
+        Ok(())
+
+    }
+
+    //recites the fragment like recite, but with every player performed by a thread of its own. This thread is the stage manager: it makes the
+    //announcements and cues one actor at a time with the line it is to speak up to, then waits for that actor to hand the stage back
+    pub fn recite_actors(&mut self, cast: &Cast, excerpt: &Excerpt) -> Result<(), u8> {
+        self.check_line_numbers();
+        let stage = Stage::new();
+
+        let this = &*self;
+        let performed: Result<Vec<usize>, u8> = thread::scope(|scope| {
+            let actors: Vec<_> = this.chars_in_play.iter().enumerate()
+                .map(|(actor_idx, plyr)| {
+                    let stage = &stage;
+                    scope.spawn(move || perform(plyr, actor_idx, stage, excerpt.delivers(plyr), excerpt, cast))
+                })
+                .collect();
+
+            //the curtain comes down even if the stage manager fails, so no actor is left waiting for a cue
+            let managed = this.manage_stage(&stage, cast, excerpt);
+            stage.lower_curtain();
+
+            //each actor returns how far through its lines it got, a thread that panicked fails the recital
+            let mut performed: Vec<usize> = Vec::new();
+            for an_actor in actors {
+                match an_actor.join() {
+                    Ok(line_count) => performed.push(line_count),
+                    Err(_) => return Err(GENERATION_FAILURE),
+                }
+            }
+            managed.map(|_| performed)
+        });
+
+        match performed {
+            Ok(performed) => {
+                //leave the players where recite would have, past every line they were cued for
+                for (plyr, line_count) in self.chars_in_play.iter_mut().zip(performed) {
+                    plyr.cur_entry_idx = line_count;
+                }
+                Ok(())
+            }
+            Err(e_code) => {
+                let _ = writeln!(io::stderr().lock(), "Error: the actor threads of '{}' could not finish the recital", self.scene_title);
+                Err(e_code)
+            }
+        }
+    }
+
+    //the stage manager's side of recite_actors, the same walk through the lines as recite with each player's turn handed to its actor
+    fn manage_stage(&self, stage: &Stage, cast: &Cast, excerpt: &Excerpt) -> Result<(), u8> {
+        let cue_order = self.cue_order();
+        let (mut entered, mut exited) = self.presence_from(excerpt);
+        let excerpt_end = excerpt.to_line.map(|to_line| LineNum::from(to_line + 1));
+
+        //the stage manager holds the cue except while an actor has the stage, so announcements never interleave with a speech
+        let Ok(mut cue) = stage.cue.lock() else {
+            return Err(GENERATION_FAILURE);
+        };
+        for (cue_idx, (line_num_speak, actor_idx)) in cue_order.iter().enumerate() {
+            let in_excerpt = excerpt.line_in_range(*line_num_speak);
+            if in_excerpt {
+                self.announce_entrances(*line_num_speak, &mut entered, &exited, &mut cue.most_recent_speaker, cast);
+            }
+            cue = stage.cue_actor(cue, *actor_idx, *line_num_speak)?;
+            if in_excerpt {
+                self.announce_exits(Self::next_cue_line(&cue_order, cue_idx, excerpt_end), &entered, &mut exited, &mut cue.most_recent_speaker, cast);
+            }
         }
 
+        self.announce_entrances(LineNum::through_sub_lines(excerpt.to_line.unwrap_or(usize::MAX)), &mut entered, &exited, &mut cue.most_recent_speaker, cast);
+        self.announce_exits(excerpt_end, &entered, &mut exited, &mut cue.most_recent_speaker, cast);
         Ok(())
+    }
+
+    //the line number and player of every line in the order they are spoken
+    fn cue_order(&self) -> Vec<(LineNum, usize)> {
+        self.speaking_order().iter().map(|(line_num, player_idx, _)| (*line_num, *player_idx)).collect()
+    }
+
+    //who has already walked on and who has already walked off when recitation starts. An excerpt starting partway through treats anyone who
+    //walked on or off before its first line as having done so already
+    fn presence_from(&self, excerpt: &Excerpt) -> (Vec<bool>, Vec<bool>) {
+        //characters with an enter@/exit@ annotation walk on and off during the fragment rather than at its boundaries.
+        let entered: Vec<bool> = self.chars_in_play.iter().map(|a_player| match (a_player.enter_at, excerpt.from_line) {
+            (Some(enter_line), Some(from_line)) => enter_line.whole() < from_line,
+            (enter_line, _) => enter_line.is_none(),
+        }).collect();
+        let exited: Vec<bool> = self.chars_in_play.iter().map(|a_player| match (a_player.exit_at, excerpt.from_line) {
+            (Some(exit_line), Some(from_line)) => exit_line.whole() < from_line,
+            _ => false,
+        }).collect();
+        (entered, exited)
+    }
+
+    //the line exits after cue_idx are announced before: the next line spoken, or the first line past the excerpt if that comes sooner
+    fn next_cue_line(cue_order: &[(LineNum, usize)], cue_idx: usize, excerpt_end: Option<LineNum>) -> Option<LineNum> {
+        match (cue_order.get(cue_idx + 1), excerpt_end) {
+            (Some(next_tuple), Some(end_line)) => Some(next_tuple.0.min(end_line)),
+            (Some(next_tuple), None) => Some(next_tuple.0),
+            (None, end_line) => end_line,
+        }
+    }
+
+    //Whinge if the first line doesn't start at 0 or there are duplicate lines. recite runs these checks before delivering the fragment, validate runs them without delivering it
+    pub fn check_line_numbers(&self) {
+        if !WHINGE.load(Ordering::SeqCst) {
+            return;
+        }
+        let mut stderr = io::stderr().lock();
+        let mut linenum_set: HashSet<LineNum> = HashSet::new(); //use hashset to track dupe lines. If we insert dupe, it returns false so we use that to trigger whinge
+
+        for a_player in self.chars_in_play.iter() {
+            for (line_num, _) in a_player.char_lines.iter() {
+                if !linenum_set.insert(*line_num) {
+                    let _ = writeln!(stderr,"WHINGE Warning: duplicate line detected for line number: {}", line_num);
+                }
+            }
+        }
+        if linenum_set.iter().min().is_some_and(|first_line| *first_line != LineNum::from(0)) {
+            let _ = writeln!(stderr,"WHINGE Warning: line number should start at 0!");
+        }
+    }
+
+    //true if some player other than skip_idx that includes char_name is currently on stage
+    fn name_on_stage_elsewhere(&self, char_name: &String, skip_idx: usize, entered: &[bool], exited: &[bool]) -> bool {
+        self.chars_in_play.iter().enumerate().any(|(player_idx, a_player)| {
+            player_idx != skip_idx && a_player.char_name != ALL_GROUP && entered[player_idx] && !exited[player_idx] && a_player.members.contains(char_name)
+        })
+    }
+
+    //announces every character whose enter@ line comes at or before the line about to be spoken
+    fn announce_entrances(&self, line_num_speak: LineNum, entered: &mut [bool], exited: &[bool], most_recent_speaker: &mut String, cast: &Cast) {
+        let mut stdout = io::stdout().lock();
+
+        for (player_idx, plyr) in self.chars_in_play.iter().enumerate().filter(|(_, a_player)| a_player.char_name != ALL_GROUP) {
+            if let Some(enter_line) = plyr.enter_at {
+                if !entered[player_idx] && enter_line <= line_num_speak {
+                    //a member already on stage through another part file does not walk on twice
+                    for a_member in plyr.members.iter() {
+                        if !self.name_on_stage_elsewhere(a_member, player_idx, entered, exited) {
+                            let _ = writeln!(stdout);
+                            let _ = writeln!(stdout,"[Enter {:?}.]", cast.display_name(a_member));
+                            most_recent_speaker.clear(); //repeat the speaker header after a stage direction
+                        }
+                    }
+                    entered[player_idx] = true;
+                }
+            }
+        }
+    }
 
+    //announces every character whose exit@ line comes before next_line_num, None once the fragment is over
+    fn announce_exits(&self, next_line_num: Option<LineNum>, entered: &[bool], exited: &mut [bool], most_recent_speaker: &mut String, cast: &Cast) {
+        let mut stdout = io::stdout().lock();
+
+        for (player_idx, plyr) in self.chars_in_play.iter().enumerate().rev().filter(|(_, a_player)| a_player.char_name != ALL_GROUP) {
+            if let Some(exit_line) = plyr.exit_at {
+                let exit_now = match next_line_num {
+                    Some(next_line) => exit_line < next_line,
+                    None => true,
+                };
+                if !exited[player_idx] && exit_now {
+                    exited[player_idx] = true;
+                    //a member still on stage through another part file stays put
+                    for a_member in plyr.members.iter().rev() {
+                        if !self.name_on_stage_elsewhere(a_member, player_idx, entered, exited) {
+                            let _ = writeln!(stdout);
+                            let _ = writeln!(stdout,"[Exit {:?}.]", cast.display_name(a_member));
+                            most_recent_speaker.clear();
+                        }
+                    }
+                }
+            }
+        }
     }
 
     //announces who enters the scene that also checks against a previous scene fragment to prevent announcing someone already in the scene
-    pub fn enter(&self, prev_fragment: &SceneFragment) {
+    pub fn enter(&self, prev_fragment: &SceneFragment, cast: &Cast, excerpt: &Excerpt) {
         let mut stdout = io::stdout().lock();
 
         if self.scene_title.split_whitespace().next().is_some() {
             let _ = writeln!(stdout,"{:?}", self.scene_title);
         }
 
-        for plyr in &self.chars_in_play {
-            //to check if prev player is already in the current list of players by their character name. If not, print the [Enter name] statement
-            //followed this example using 'any' to check if elements in vec matches a condition: https://doc.rust-lang.org/std/iter/trait.Iterator.html#method.any
-            if !prev_fragment.chars_in_play.iter().any(|prev_plyr| prev_plyr.char_name == plyr.char_name) {
-                let _ = writeln!(stdout,"[Enter {:?}.]", plyr.char_name);
+        //compare by character key so members of a shared part count as present too, and a display name change never breaks continuity
+        //a character who exited during the previous fragment has to walk back on
+        let prev_names = prev_fragment.names_on_stage_until(excerpt.to_line);
+        for a_name in self.names_on_stage_from(excerpt.from_line) {
+            if !prev_names.contains(&a_name) {
+                let _ = writeln!(stdout,"[Enter {:?}.]", cast.display_name(&a_name));
             }
         }
     }
 
-    pub fn enter_all(&self) {
+    //announces everyone on stage where recitation begins, which is partway through the fragment for an excerpt with a line range
+    pub fn enter_all(&self, cast: &Cast, excerpt: &Excerpt) {
         let mut stdout = io::stdout().lock();
 
         if self.scene_title.split_whitespace().next().is_some() {
             let _ = writeln!(stdout,"{:?}!", self.scene_title);
         }
 
-        for plyr in &self.chars_in_play {
-            let _ = writeln!(stdout,"[Enter {:?}.]", plyr.char_name);
+        for a_name in self.names_on_stage_from(excerpt.from_line) {
+            let _ = writeln!(stdout,"[Enter {:?}.]", cast.display_name(&a_name));
         }
     }
 
     //announces who exits by checking if they will be in the next fragment or not
-    pub fn exit(&self, next_fragment: &SceneFragment) {
+    pub fn exit(&self, next_fragment: &SceneFragment, cast: &Cast, excerpt: &Excerpt) {
 
         let mut stdout = io::stdout().lock();
 
-        for plyr in self.chars_in_play.iter().rev() { //using rev to reverse iterator so we print exit names in reverse order
-            if !next_fragment.chars_in_play.iter().any(|next_plyr| next_plyr.char_name == plyr.char_name) {
-                let _ = writeln!(stdout,"[Exit {:?}.]", plyr.char_name);
+        //a character who only enters partway through the next fragment leaves the stage in between
+        let next_names = next_fragment.names_on_stage_from(excerpt.from_line);
+        for a_name in self.names_on_stage_until(excerpt.to_line).iter().rev() { //using rev to reverse iterator so we print exit names in reverse order
+            if !next_names.contains(a_name) {
+                let _ = writeln!(stdout,"[Exit {:?}.]", cast.display_name(a_name));
             }
         }
         let _ = writeln!(stdout); //new line to separate the next scene
     }
 
-    pub fn exit_all(&self) {
+    pub fn exit_all(&self, cast: &Cast, excerpt: &Excerpt) {
 
         let mut stdout = io::stdout().lock();
 
-        for plyr in self.chars_in_play.iter().rev() {
-            let _ = writeln!(stdout,"[Exit {:?}.]", plyr.char_name);
+        for a_name in self.names_on_stage_until(excerpt.to_line).iter().rev() {
+            let _ = writeln!(stdout,"[Exit {:?}.]", cast.display_name(a_name));
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,234 @@
+//scene_meta.rs declares the SceneMeta struct holding the play title, act, scene number, location and author of a scene, parsed from a [scene] title or set with [title]/[act]/[location]/[author] directives, along with the Act and Scene structs that group a Play's fragments. Aman Verma, Hanson Li, Johnny Huang
+
+use super::play::Fragments;
+
+pub const ACT_MARK: &str = "act";
+pub const SCENE_MARK: &str = "scene";
+pub const AUTHOR_MARK: &str = "by";
+const NOT_AN_AUTHOR: [&str; 3] = ["the", "a", "an"]; //"by the castle" is a place, not a person
+pub const LABEL_DELIM: char = '.'; //scene labels look like II.1
+
+//roman numeral letters and their values, largest first so formatting can be greedy
+const ROMAN_VALUES: [(&str, usize); 13] = [
+    ("M", 1000), ("CM", 900), ("D", 500), ("CD", 400), ("C", 100), ("XC", 90),
+    ("L", 50), ("XL", 40), ("X", 10), ("IX", 9), ("V", 5), ("IV", 4), ("I", 1),
+];
+
+//formats a number as an upper case roman numeral, 0 has no numeral so it is printed as is
+pub fn to_roman(mut number: usize) -> String {
+    if number == 0 {
+        return number.to_string();
+    }
+    let mut numeral = String::new();
+    for (letters, value) in ROMAN_VALUES.iter() {
+        while number >= *value {
+            numeral.push_str(letters);
+            number -= value;
+        }
+    }
+    numeral
+}
+
+//reads an arabic or roman numeral (any case), None if the token is neither
+pub fn parse_numeral(token: &str) -> Option<usize> {
+    if let Ok(number) = token.parse::<usize>() {
+        return Some(number);
+    }
+    let upper_token = token.to_uppercase();
+    if upper_token.is_empty() || !upper_token.chars().all(|a_char| "IVXLCDM".contains(a_char)) {
+        return None;
+    }
+
+    let mut number: usize = 0;
+    let mut remaining: &str = &upper_token;
+    for (letters, value) in ROMAN_VALUES.iter() {
+        while let Some(rest) = remaining.strip_prefix(letters) {
+            number += value;
+            remaining = rest;
+        }
+    }
+    //only accept numerals written the standard way, so words like "mix" or "dim" are not taken as numbers
+    if remaining.is_empty() && to_roman(number) == upper_token {
+        Some(number)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SceneMeta {
+    pub play_title: String,
+    pub act: Option<usize>,
+    pub scene: Option<usize>,
+    pub location: String,
+    pub author: String,
+    pub full_title: String, //the [scene] line as written
+}
+
+impl SceneMeta {
+    //splits a title like "Hamlet Prince of Denmark ACT II Scene I A room in Polonius house by William Shakespeare" into its parts.
+    //a title without ACT or Scene markers is kept whole as the location
+    pub fn from_title(full_title: &str) -> Self {
+        Self::split_title(full_title, true)
+    }
+
+    //with find_author unset everything after the numbers is the location
+    fn split_title(full_title: &str, find_author: bool) -> Self {
+        let title_tokens: Vec<&str> = full_title.split_whitespace().collect();
+        let mut scene_meta = SceneMeta {
+            full_title: full_title.to_string(),
+            ..Default::default()
+        };
+
+        //a marker only counts if a numeral follows it
+        let find_marker = |marker: &str| -> Option<(usize, usize)> {
+            title_tokens.iter().enumerate().find_map(|(token_idx, a_token)| {
+                if a_token.eq_ignore_ascii_case(marker) {
+                    title_tokens.get(token_idx + 1).and_then(|numeral| parse_numeral(numeral)).map(|number| (token_idx, number))
+                } else {
+                    None
+                }
+            })
+        };
+        let act_marker = find_marker(ACT_MARK);
+        let scene_marker = find_marker(SCENE_MARK);
+
+        let (title_end, location_start) = match (act_marker, scene_marker) {
+            (Some((act_idx, _)), Some((scene_idx, _))) => (act_idx.min(scene_idx), act_idx.max(scene_idx) + 2),
+            (Some((marker_idx, _)), None) | (None, Some((marker_idx, _))) => (marker_idx, marker_idx + 2),
+            (None, None) => (0, 0),
+        };
+        scene_meta.act = act_marker.map(|(_, number)| number);
+        scene_meta.scene = scene_marker.map(|(_, number)| number);
+        scene_meta.play_title = title_tokens[..title_end].join(" ");
+
+        //the author follows the last "by" after the numbers, if the next word looks like the start of a name: capitalised and not an article
+        let location_tokens = &title_tokens[location_start.min(title_tokens.len())..];
+        let name_like = |a_token: &&str| {
+            a_token.chars().next().is_some_and(|first_char| first_char.is_uppercase())
+                && !NOT_AN_AUTHOR.iter().any(|an_article| a_token.eq_ignore_ascii_case(an_article))
+        };
+        match location_tokens.iter().rposition(|a_token| a_token.eq_ignore_ascii_case(AUTHOR_MARK)) {
+            Some(by_idx) if find_author && location_tokens.get(by_idx + 1).is_some_and(name_like) => {
+                scene_meta.location = location_tokens[..by_idx].join(" ");
+                scene_meta.author = location_tokens[by_idx + 1..].join(" ");
+            }
+            _ => scene_meta.location = location_tokens.join(" "),
+        }
+        scene_meta
+    }
+
+    //an author given separately, e.g. by [author], replaces one read from the title. When they differ the words after "by" were not the
+    //author after all, so they go back into the location
+    pub fn set_author(&mut self, author: &str) {
+        if !self.author.is_empty() && self.author != author {
+            self.location = Self::split_title(&self.full_title, false).location;
+        }
+        self.author = author.to_string();
+    }
+
+    //short label like II.1, or just the scene number when there is no act. Empty when neither is known
+    pub fn label(&self) -> String {
+        match (self.act, self.scene) {
+            (Some(act_num), Some(scene_num)) => format!("{}{}{}", to_roman(act_num), LABEL_DELIM, scene_num),
+            (Some(act_num), None) => to_roman(act_num),
+            (None, Some(scene_num)) => scene_num.to_string(),
+            (None, None) => String::new(),
+        }
+    }
+
+    //label followed by the location, e.g. "II.1 A room in Polonius house". Falls back to the title as written when there is no act or scene number
+    pub fn short_title(&self) -> String {
+        let scene_label = self.label();
+        if scene_label.is_empty() {
+            return self.full_title.to_string();
+        }
+        if self.location.is_empty() {
+            return scene_label;
+        }
+        format!("{} {}", scene_label, self.location)
+    }
+
+    //a short tag for file names, the act and scene like ii_1 when both are known, otherwise the scene number or the scene's position
+    pub fn file_tag(&self, scene_idx: usize) -> String {
+        match (self.act, self.scene) {
+            (Some(act_num), Some(scene_num)) => format!("{}_{}", to_roman(act_num).to_lowercase(), scene_num),
+            (None, Some(scene_num)) => scene_num.to_string(),
+            _ => (scene_idx + 1).to_string(),
+        }
+    }
+
+    //true if a label such as II.1, 2.1 or II.I names this scene
+    pub fn matches_label(&self, scene_label: &str) -> bool {
+        let (act_part, scene_part) = match scene_label.split_once(LABEL_DELIM) {
+            Some((act_part, scene_part)) => (Some(act_part), scene_part),
+            None => (None, scene_label),
+        };
+        let act_matches = match act_part {
+            Some(act_text) => parse_numeral(act_text).is_some() && parse_numeral(act_text) == self.act,
+            None => true,
+        };
+        act_matches && parse_numeral(scene_part).is_some() && parse_numeral(scene_part) == self.scene
+    }
+}
+
+//one scene of the play and the fragments it is made of, the fragments are shared with the Play's flat list
+pub struct Scene {
+    pub meta: SceneMeta,
+    pub fragments: Fragments,
+    pub first_fragment_idx: usize, //index of the scene's first fragment in the Play's flat list
+}
+
+//one act of the play, scenes are kept in script order
+pub struct Act {
+    pub act: Option<usize>,
+    pub scenes: Vec<Scene>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_is_split_into_its_parts() {
+        let scene_meta = SceneMeta::from_title("Hamlet Prince of Denmark ACT II Scene I A room in Polonius house by William Shakespeare");
+        assert_eq!(scene_meta.play_title, "Hamlet Prince of Denmark");
+        assert_eq!((scene_meta.act, scene_meta.scene), (Some(2), Some(1)));
+        assert_eq!(scene_meta.location, "A room in Polonius house");
+        assert_eq!(scene_meta.author, "William Shakespeare");
+        assert_eq!(scene_meta.label(), "II.1");
+
+        //scene before act, arabic numbers and a marker without a numeral
+        let scene_meta = SceneMeta::from_title("Scene 3 act 4 The act of the play");
+        assert_eq!((scene_meta.act, scene_meta.scene), (Some(4), Some(3)));
+        assert_eq!(scene_meta.location, "The act of the play");
+
+        let scene_meta = SceneMeta::from_title("A room in the castle");
+        assert_eq!((scene_meta.act, scene_meta.scene), (None, None));
+        assert_eq!(scene_meta.location, "A room in the castle");
+        assert_eq!(scene_meta.short_title(), "A room in the castle");
+    }
+
+    #[test]
+    fn by_a_place_is_not_an_author() {
+        let scene_meta = SceneMeta::from_title("Hamlet ACT I Scene I A platform by the castle");
+        assert_eq!(scene_meta.location, "A platform by the castle");
+        assert!(scene_meta.author.is_empty());
+        assert_eq!(SceneMeta::from_title("Hamlet ACT I Scene II A room by a window").location, "A room by a window");
+        assert_eq!(SceneMeta::from_title("Hamlet ACT I Scene II A plain by night").location, "A plain by night");
+    }
+
+    #[test]
+    fn explicit_author_restores_the_location() {
+        let mut scene_meta = SceneMeta::from_title("Hamlet ACT I Scene V Another part of the platform by Elsinore");
+        assert_eq!(scene_meta.author, "Elsinore");
+        scene_meta.set_author("William Shakespeare");
+        assert_eq!(scene_meta.location, "Another part of the platform by Elsinore");
+        assert_eq!(scene_meta.author, "William Shakespeare");
+
+        //the same author as the title keeps the split
+        let mut scene_meta = SceneMeta::from_title("Hamlet ACT II Scene I A room by William Shakespeare");
+        scene_meta.set_author("William Shakespeare");
+        assert_eq!(scene_meta.location, "A room");
+    }
+}
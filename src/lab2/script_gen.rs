@@ -1,50 +1,314 @@
-//refactored script_gen.rs. Provides the grab_trimmed_file_lines used by other rs files to read in lines from a speak file. Aman Verma, Johnny Huang, Hanson Li
+//refactored script_gen.rs. Provides the grab_trimmed_file_lines used by other rs files to read in lines from a speak file, decoding files
+//that are not UTF-8 and any mix of line endings. Aman Verma, Johnny Huang, Hanson Li
 
-use std::fs::File;
-use std::io::BufReader;
-use std::io::BufRead;
+use std::fs;
+use std::path::Path;
 use super::declarations::GENERATION_FAILURE;
 use std::io::{self, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-pub fn grab_trimmed_file_lines(file_name: &String, file_line_vec: &mut Vec<String>) -> Result<(), u8>{
-    //note: in config files you must provide the relative or full path to the speak files
-    //using match since error code could be helpful here
+pub const AUTO_ENCODING: &str = "auto";     //UTF-8, falling back to Latin-1 for any line that is not UTF-8
+pub const UTF8_ENCODING: &str = "utf-8";    //UTF-8 only, anything else is an error
+pub const LATIN1_ENCODING: &str = "latin-1";
+pub const LOSSY_ENCODING: &str = "lossy";   //UTF-8, with any bytes that are not UTF-8 replaced by U+FFFD
+pub const ENCODING_FILE_DELIM: char = '='; //--encoding Polonius_hamlet_ii_1b.txt=latin-1 sets the encoding of one file
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextEncoding {
+    Auto,
+    Utf8,
+    Latin1,
+    Lossy,
+}
+
+impl TextEncoding {
+    pub fn from_name(encoding_name: &str) -> Option<Self> {
+        match encoding_name.to_lowercase().as_str() {
+            AUTO_ENCODING => Some(TextEncoding::Auto),
+            UTF8_ENCODING | "utf8" => Some(TextEncoding::Utf8),
+            LATIN1_ENCODING | "latin1" | "iso-8859-1" => Some(TextEncoding::Latin1),
+            LOSSY_ENCODING => Some(TextEncoding::Lossy),
+            _ => None,
+        }
+    }
+}
+
+//every file this run tried to read, in the order it first read them. --watch polls these for changes
+static FILES_READ: Mutex<Vec<String>> = Mutex::new(Vec::new());
+//encodings given with --encoding, an empty file name sets the encoding of every file without one of its own
+static ENCODINGS: Mutex<Vec<(String, TextEncoding)>> = Mutex::new(Vec::new());
+//set once any file had lines that were not UTF-8, so a play read from it is not cached and the warning shows again next run
+static DECODE_FALLBACK: AtomicBool = AtomicBool::new(false);
+
+//records a file as read, so readers that do not go through grab_trimmed_file_lines are watched too
+pub fn note_file_read(file_name: &str) {
+    if let Ok(mut files_read) = FILES_READ.lock() {
+        if !files_read.iter().any(|a_file| a_file == file_name) {
+            files_read.push(file_name.to_string());
+        }
+    }
+}
+
+pub fn files_read() -> Vec<String> {
+    FILES_READ.lock().map(|files_read| files_read.clone()).unwrap_or_default()
+}
+
+//takes one --encoding value, either an encoding name or file_name=encoding_name. Err if the encoding is not one we know
+pub fn set_encoding(encoding_spec: &str) -> Result<(), u8> {
+    let (file_name, encoding_name) = encoding_spec.rsplit_once(ENCODING_FILE_DELIM).unwrap_or(("", encoding_spec));
+    let Some(an_encoding) = TextEncoding::from_name(encoding_name.trim()) else {
+        println!("Error: unknown encoding '{}', expected {}, {}, {} or {}", encoding_name, AUTO_ENCODING, UTF8_ENCODING, LATIN1_ENCODING, LOSSY_ENCODING);
+        return Err(GENERATION_FAILURE);
+    };
+    if let Ok(mut encodings) = ENCODINGS.lock() {
+        encodings.push((file_name.trim().to_string(), an_encoding));
+    }
+    Ok(())
+}
 
+//the encoding a file is read with. A file can be named by its path or by its last components, the last matching --encoding wins
+pub fn encoding_for(file_name: &str) -> TextEncoding {
+    let Ok(encodings) = ENCODINGS.lock() else {
+        return TextEncoding::Auto;
+    };
+    let file_encoding = encodings.iter().rev()
+        .find(|(a_file, _)| !a_file.is_empty() && (a_file == file_name || Path::new(file_name).ends_with(a_file)));
+    let run_encoding = encodings.iter().rev().find(|(a_file, _)| a_file.is_empty());
+    file_encoding.or(run_encoding).map(|(_, an_encoding)| *an_encoding).unwrap_or(TextEncoding::Auto)
+}
+
+pub fn any_decode_fallback() -> bool {
+    DECODE_FALLBACK.load(Ordering::SeqCst)
+}
+
+//splits on \r\n, \n or a lone \r, keeping the line ending after each line. A line ending at the very end does not start another line,
+//and the last line has an empty ending if the file does not end with one
+fn split_line_ends(file_bytes: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut line_ends: Vec<(&[u8], &[u8])> = Vec::new();
+    let mut line_start: usize = 0;
+    let mut byte_idx: usize = 0;
+    while byte_idx < file_bytes.len() {
+        match file_bytes[byte_idx] {
+            b'\n' => {
+                line_ends.push((&file_bytes[line_start..byte_idx], &file_bytes[byte_idx..byte_idx + 1]));
+                line_start = byte_idx + 1;
+            }
+            b'\r' => {
+                let end_len = if file_bytes.get(byte_idx + 1) == Some(&b'\n') { 2 } else { 1 };
+                line_ends.push((&file_bytes[line_start..byte_idx], &file_bytes[byte_idx..byte_idx + end_len]));
+                byte_idx += end_len - 1;
+                line_start = byte_idx + 1;
+            }
+            _ => {}
+        }
+        byte_idx += 1;
+    }
+    if line_start < file_bytes.len() {
+        line_ends.push((&file_bytes[line_start..], &[]));
+    }
+    line_ends
+}
+
+fn split_byte_lines(file_bytes: &[u8]) -> Vec<&[u8]> {
+    split_line_ends(file_bytes).into_iter().map(|(line_bytes, _)| line_bytes).collect()
+}
+
+//a file starting with a UTF-16 byte order mark, unpaired surrogates become U+FFFD
+fn decode_utf16(file_bytes: &[u8], big_endian: bool) -> String {
+    let code_units = file_bytes.chunks(2).map(|a_pair| match (a_pair, big_endian) {
+        ([high_byte, low_byte], true) => u16::from_be_bytes([*high_byte, *low_byte]),
+        ([low_byte, high_byte], false) => u16::from_le_bytes([*low_byte, *high_byte]),
+        _ => 0xFFFD,
+    });
+    char::decode_utf16(code_units).map(|a_char| a_char.unwrap_or(char::REPLACEMENT_CHARACTER)).collect()
+}
+
+//decodes a whole file into lines without their line endings. A byte order mark picks the encoding over --encoding, since it says what the file is
+pub fn decode_file_lines(file_name: &str, file_bytes: &[u8]) -> Result<Vec<String>, u8> {
     let mut stdout = io::stdout().lock();
-    match File::open(file_name) {
-        Ok(file_obj) => {
-            let mut buf_reader = BufReader::new(file_obj);
-            let mut cur_read_str = String::new();
-
-            loop {
- 
-                cur_read_str.clear(); //clear first, then read_line
-                match buf_reader.read_line(&mut cur_read_str) {
-                    Ok(read_line_status) => {
-                        
-                        if read_line_status == 0 {
-                            break
-                        }
-
-                        file_line_vec.push(cur_read_str.trim().to_string()); //trim will return &str so we need to_string
-                    },
-                    Err(e_code) => {
-                        let _ = writeln!(stdout,"Error: in grab_trimmed_file_lines, BufReader failed with code {}", e_code);
+    let mut stderr = io::stderr().lock();
+
+    for (a_bom, big_endian) in [(UTF16_LE_BOM, false), (UTF16_BE_BOM, true)] {
+        if let Some(utf16_bytes) = file_bytes.strip_prefix(a_bom) {
+            let file_text = decode_utf16(utf16_bytes, big_endian);
+            return Ok(split_byte_lines(file_text.as_bytes()).iter().map(|a_line| String::from_utf8_lossy(a_line).to_string()).collect());
+        }
+    }
+    let file_bytes = file_bytes.strip_prefix(UTF8_BOM).unwrap_or(file_bytes);
+    let file_encoding = encoding_for(file_name);
+
+    let mut decoded_lines: Vec<String> = Vec::new();
+    let mut bad_lines: Vec<usize> = Vec::new(); //1-based numbers of the lines that were not UTF-8
+    for (line_idx, line_bytes) in split_byte_lines(file_bytes).into_iter().enumerate() {
+        if file_encoding == TextEncoding::Latin1 {
+            decoded_lines.push(line_bytes.iter().map(|a_byte| char::from(*a_byte)).collect());
+            continue;
+        }
+        match std::str::from_utf8(line_bytes) {
+            Ok(line_text) => decoded_lines.push(line_text.to_string()),
+            Err(utf8_error) => {
+                bad_lines.push(line_idx + 1);
+                match file_encoding {
+                    TextEncoding::Utf8 => {
+                        let bad_column = String::from_utf8_lossy(&line_bytes[..utf8_error.valid_up_to()]).chars().count() + 1;
+                        let _ = writeln!(stdout,"Error: {} line {} column {} is not valid UTF-8, try --encoding {}={} or --encoding {}",
+                            file_name, line_idx + 1, bad_column, file_name, LATIN1_ENCODING, LOSSY_ENCODING);
                         return Err(GENERATION_FAILURE);
-                    },
-                    
+                    }
+                    TextEncoding::Lossy => decoded_lines.push(String::from_utf8_lossy(line_bytes).to_string()),
+                    _ => decoded_lines.push(line_bytes.iter().map(|a_byte| char::from(*a_byte)).collect()),
                 }
+            }
+        }
+    }
+
+    //one warning per file, naming the first line that was not UTF-8
+    if let Some(first_bad) = bad_lines.first() {
+        DECODE_FALLBACK.store(true, Ordering::SeqCst);
+        let read_as = if file_encoding == TextEncoding::Lossy { "with the bad bytes replaced" } else { "as Latin-1" };
+        let _ = writeln!(stderr,"Warning: {} line {} is not valid UTF-8, read it and {} other line(s) {}",
+            file_name, first_bad, bad_lines.len() - 1, read_as);
+    }
+    Ok(decoded_lines)
+}
 
+//the bytes of a file after some of the lines decode_file_lines read from it were changed, for writing it back. Lines whose text is the same
+//keep their bytes, and every line keeps its line ending. A changed line is encoded the way it was read: Latin-1 if it was read as Latin-1,
+//else UTF-8. A UTF-16 file is written as UTF-16 again with its byte order mark and the line ending of its first line
+pub fn encode_file_lines(file_name: &str, file_bytes: &[u8], read_lines: &[String], new_lines: &[String]) -> Vec<u8> {
+    for (a_bom, big_endian) in [(UTF16_LE_BOM, false), (UTF16_BE_BOM, true)] {
+        if let Some(utf16_bytes) = file_bytes.strip_prefix(a_bom) {
+            let file_text = decode_utf16(utf16_bytes, big_endian);
+            let line_ending = ["\r\n", "\r", "\n"].into_iter().find(|an_ending| file_text.contains(an_ending)).unwrap_or("\n");
+            let mut new_text = new_lines.join(line_ending);
+            if file_text.ends_with(['\n', '\r']) {
+                new_text.push_str(line_ending);
+            }
+            let mut new_bytes = a_bom.to_vec();
+            for a_unit in new_text.encode_utf16() {
+                new_bytes.extend(if big_endian { a_unit.to_be_bytes() } else { a_unit.to_le_bytes() });
             }
-            
-            Ok(()) //only arrive here if we break from the loop
+            return new_bytes;
+        }
+    }
+    let body_bytes = file_bytes.strip_prefix(UTF8_BOM).unwrap_or(file_bytes);
+    let file_encoding = encoding_for(file_name);
 
+    let mut new_bytes = file_bytes[..file_bytes.len() - body_bytes.len()].to_vec();
+    for (line_idx, (line_bytes, line_end)) in split_line_ends(body_bytes).into_iter().enumerate() {
+        match new_lines.get(line_idx) {
+            Some(new_line) if read_lines.get(line_idx) != Some(new_line) => {
+                let read_as_latin1 = file_encoding == TextEncoding::Latin1 || (file_encoding == TextEncoding::Auto && std::str::from_utf8(line_bytes).is_err());
+                if read_as_latin1 {
+                    new_bytes.extend(new_line.chars().map(|a_char| u8::try_from(a_char).unwrap_or(b'?')));
+                } else {
+                    new_bytes.extend(new_line.as_bytes());
+                }
+            }
+            _ => new_bytes.extend(line_bytes),
+        }
+        new_bytes.extend(line_end);
+    }
+    new_bytes
+}
+
+//reads a whole file the way grab_trimmed_file_lines does, lines joined with \n, for the importers and readers that parse text themselves
+pub fn read_text_file(file_name: &str) -> Result<String, u8> {
+    note_file_read(file_name);
+    match fs::read(file_name) {
+        Ok(file_bytes) => {
+            let mut file_text = decode_file_lines(file_name, &file_bytes)?.join("\n");
+            file_text.push('\n');
+            Ok(file_text)
+        }
+        Err(e_msg) => {
+            let _ = writeln!(io::stderr().lock(), "Error: could not read {}: {}", file_name, e_msg);
+            Err(GENERATION_FAILURE)
+        }
+    }
+}
+
+//reads every line of a file as it is, for readers that report the column of a problem
+pub fn grab_file_lines(file_name: &String, file_line_vec: &mut Vec<String>) -> Result<(), u8>{
+    note_file_read(file_name); //noted even when it cannot be opened, so --watch sees it appear
+    match fs::read(file_name) {
+        Ok(file_bytes) => {
+            file_line_vec.extend(decode_file_lines(file_name, &file_bytes)?);
+            Ok(())
         }
         Err(e_code) => {
-            let _ = writeln!(stdout,"Error: in grab_trimmed_file_lines, failed to open file with error code: {} and file name: {}", e_code, file_name);
-            return Err(GENERATION_FAILURE);
+            let _ = writeln!(io::stdout().lock(),"Error: in grab_trimmed_file_lines, failed to open file with error code: {} and file name: {}", e_code, file_name);
+            Err(GENERATION_FAILURE)
         }
     }
 }
 
+pub fn grab_trimmed_file_lines(file_name: &String, file_line_vec: &mut Vec<String>) -> Result<(), u8>{
+    //note: in config files you must provide the relative or full path to the speak files
+    let mut file_lines: Vec<String> = Vec::new();
+    grab_file_lines(file_name, &mut file_lines)?;
+    for a_line in file_lines {
+        file_line_vec.push(a_line.trim().to_string()); //trim will return &str so we need to_string
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //decodes file_bytes, renumbers the first line from 1 to 0 and encodes the file again
+    fn renumber_first_line(file_name: &str, file_bytes: &[u8]) -> (Vec<String>, Vec<u8>) {
+        let read_lines = decode_file_lines(file_name, file_bytes).unwrap();
+        let mut new_lines = read_lines.clone();
+        new_lines[0] = new_lines[0].replacen('1', "0", 1);
+        (read_lines.clone(), encode_file_lines(file_name, file_bytes, &read_lines, &new_lines))
+    }
+
+    #[test]
+    fn line_endings_are_split_and_kept() {
+        let file_bytes = b"1 one\r\n2 two\r3 three\n\n4 four";
+        assert_eq!(split_line_ends(file_bytes), vec![(&b"1 one"[..], &b"\r\n"[..]), (b"2 two", b"\r"), (b"3 three", b"\n"), (b"", b"\n"), (b"4 four", b"")]);
+        let (read_lines, new_bytes) = renumber_first_line("endings_test.txt", file_bytes);
+        assert_eq!(read_lines, vec!["1 one", "2 two", "3 three", "", "4 four"]);
+        assert_eq!(new_bytes, b"0 one\r\n2 two\r3 three\n\n4 four");
+        //a line ending at the very end is not another line
+        assert_eq!(decode_file_lines("endings_test.txt", b"1 one\r").unwrap(), vec!["1 one"]);
+    }
+
+    #[test]
+    fn byte_order_marks_are_kept() {
+        let (read_lines, new_bytes) = renumber_first_line("utf8_bom_test.txt", b"\xEF\xBB\xBF1 caf\xC3\xA9\n2 two\n");
+        assert_eq!(read_lines, vec!["1 caf\u{e9}", "2 two"]);
+        assert_eq!(new_bytes, b"\xEF\xBB\xBF0 caf\xC3\xA9\n2 two\n");
+
+        let mut utf16_bytes: Vec<u8> = UTF16_LE_BOM.to_vec();
+        utf16_bytes.extend("1 caf\u{e9}\r\n2 two\r\n".encode_utf16().flat_map(|a_unit| a_unit.to_le_bytes()));
+        let (read_lines, new_bytes) = renumber_first_line("utf16_test.txt", &utf16_bytes);
+        assert_eq!(read_lines, vec!["1 caf\u{e9}", "2 two"]);
+        let mut expected_bytes: Vec<u8> = UTF16_LE_BOM.to_vec();
+        expected_bytes.extend("0 caf\u{e9}\r\n2 two\r\n".encode_utf16().flat_map(|a_unit| a_unit.to_le_bytes()));
+        assert_eq!(new_bytes, expected_bytes);
+    }
 
+    #[test]
+    fn latin1_lines_are_written_back_as_latin1() {
+        //by default only the line that is not UTF-8 is read as Latin-1
+        let (read_lines, new_bytes) = renumber_first_line("latin1_auto_test.txt", b"1 caf\xE9\n2 caf\xC3\xA9\n");
+        assert_eq!(read_lines, vec!["1 caf\u{e9}", "2 caf\u{e9}"]);
+        assert_eq!(new_bytes, b"0 caf\xE9\n2 caf\xC3\xA9\n");
+
+        set_encoding("latin1_set_test.txt=latin-1").unwrap();
+        let (read_lines, new_bytes) = renumber_first_line("latin1_set_test.txt", b"1 \xC3\xA9\r\n");
+        assert_eq!(read_lines, vec!["1 \u{c3}\u{a9}"]);
+        assert_eq!(new_bytes, b"0 \xC3\xA9\r\n");
+
+        set_encoding("utf8_only_test.txt=utf-8").unwrap();
+        assert_eq!(decode_file_lines("utf8_only_test.txt", b"1 caf\xE9\n"), Err(GENERATION_FAILURE));
+    }
+}
@@ -0,0 +1,194 @@
+//search.rs declares the SearchQuery struct and searches every line of a prepared Play for a word or phrase, printing each match with its scene, fragment, character, line number and part file and the dialogue around it. Johnny Huang, Hanson Li, Aman Verma
+
+use super::declarations::GENERATION_FAILURE;
+use super::play::Play;
+use super::player::SUB_LINE_DELIM;
+use std::io::{self, Write};
+
+pub const ANY_CHARS_WILDCARD: char = '*';
+pub const ONE_CHAR_WILDCARD: char = '?';
+pub const DEFAULT_CONTEXT_LINES: usize = 2;
+pub const MATCH_MARK: &str = ">";
+pub const CONTEXT_MARK: &str = " ";
+pub const SUB_LINE_INDENT: &str = "\n        ";
+
+//true if text matches the whole pattern, where * matches any run of characters and ? matches exactly one
+pub fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    let (mut pattern_idx, mut text_idx) = (0, 0);
+    //where to resume after the last *, so a failed match can let the * swallow one more character
+    let mut star_resume: Option<(usize, usize)> = None;
+
+    while text_idx < text.len() {
+        if pattern_idx < pattern.len() && (pattern[pattern_idx] == ONE_CHAR_WILDCARD || pattern[pattern_idx] == text[text_idx]) {
+            pattern_idx += 1;
+            text_idx += 1;
+        } else if pattern_idx < pattern.len() && pattern[pattern_idx] == ANY_CHARS_WILDCARD {
+            star_resume = Some((pattern_idx, text_idx));
+            pattern_idx += 1;
+        } else if let Some((star_idx, star_text_idx)) = star_resume {
+            pattern_idx = star_idx + 1;
+            text_idx = star_text_idx + 1;
+            star_resume = Some((star_idx, star_text_idx + 1));
+        } else {
+            return false;
+        }
+    }
+    //only trailing *s can be left over
+    pattern[pattern_idx..].iter().all(|a_char| *a_char == ANY_CHARS_WILDCARD)
+}
+
+//splits a line into words, keeping apostrophes inside words like 'twere or o'ertook
+pub fn split_words(text: &str) -> Vec<String> {
+    text.split(|a_char: char| !a_char.is_alphanumeric() && a_char != '\'')
+        .map(|a_word| a_word.trim_matches('\''))
+        .filter(|a_word| !a_word.is_empty())
+        .map(|a_word| a_word.to_string())
+        .collect()
+}
+
+pub struct SearchQuery {
+    pub pattern: String,
+    pub whole_word: bool,     //the pattern has to match whole words rather than any part of a line
+    pub case_sensitive: bool,
+    pub context_lines: usize, //lines of dialogue shown before and after each match
+}
+
+impl SearchQuery {
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            whole_word: false,
+            case_sensitive: false,
+            context_lines: DEFAULT_CONTEXT_LINES,
+        }
+    }
+
+    fn normalize(&self, text: &str) -> String {
+        if self.case_sensitive {
+            text.to_string()
+        } else {
+            text.to_lowercase()
+        }
+    }
+
+    //true if the line contains the pattern. A whole-word phrase has to match that many consecutive words
+    pub fn matches(&self, line_text: &str) -> bool {
+        let pattern = self.normalize(&self.pattern);
+        let line_text = self.normalize(line_text);
+
+        if self.whole_word {
+            let pattern_words: Vec<Vec<char>> = pattern.split_whitespace().map(|a_word| a_word.chars().collect()).collect();
+            let line_words: Vec<Vec<char>> = split_words(&line_text).iter().map(|a_word| a_word.chars().collect()).collect();
+            if pattern_words.is_empty() || line_words.len() < pattern_words.len() {
+                return false;
+            }
+            line_words.windows(pattern_words.len()).any(|a_window| {
+                a_window.iter().zip(pattern_words.iter()).all(|(a_word, a_pattern)| glob_match(a_pattern, a_word))
+            })
+        } else {
+            //substring search is a glob with a * on either side
+            let mut pattern_chars: Vec<char> = vec![ANY_CHARS_WILDCARD];
+            pattern_chars.extend(pattern.chars());
+            pattern_chars.push(ANY_CHARS_WILDCARD);
+            let line_chars: Vec<char> = line_text.chars().collect();
+            glob_match(&pattern_chars, &line_chars)
+        }
+    }
+}
+
+//prints every matching line with its location and surrounding dialogue in speaking order, returns the number of matches
+pub fn search_play(play: &Play, query: &SearchQuery) -> Result<usize, u8> {
+    let mut stdout = io::stdout().lock();
+    let mut stderr = io::stderr().lock();
+    let scene_titles = play.fragment_scene_titles()?;
+    let cast = play.cast();
+    let mut match_count: usize = 0;
+
+    for (fragment_idx, a_fragment) in play.fragments().iter().enumerate() {
+        let scene_ref = match a_fragment.lock() {
+            Ok(scene_ref) => scene_ref,
+            Err(_) => {
+                let _ = writeln!(stderr, "Error: Failed to acquire lock on ith scene fragment");
+                return Err(GENERATION_FAILURE);
+            }
+        };
+        let speaking_order = scene_ref.speaking_order();
+
+        for (order_idx, (line_num, player_idx, line_idx)) in speaking_order.iter().enumerate() {
+            let plyr = &scene_ref.chars_in_play[*player_idx];
+            if !query.matches(&plyr.char_lines[*line_idx].1) {
+                continue;
+            }
+            match_count += 1;
+
+            let _ = writeln!(stdout);
+            let _ = writeln!(stdout,"{}, fragment {}, {}, line {} ({})",
+                scene_titles[fragment_idx], fragment_idx + 1, plyr.speaker_header(cast), line_num, plyr.part_file);
+
+            let context_start = order_idx.saturating_sub(query.context_lines);
+            let context_end = (order_idx + query.context_lines + 1).min(speaking_order.len());
+            for (context_idx, (context_line_num, context_player_idx, context_line_idx)) in speaking_order[context_start..context_end].iter().enumerate() {
+                let context_plyr = &scene_ref.chars_in_play[*context_player_idx];
+                let line_mark = if context_start + context_idx == order_idx { MATCH_MARK } else { CONTEXT_MARK };
+                //the sub-lines of a wrapped line are indented under it
+                let line_text = context_plyr.char_lines[*context_line_idx].1.replace(SUB_LINE_DELIM, SUB_LINE_INDENT);
+                let _ = writeln!(stdout,"  {} {} {}: {}", line_mark, context_line_num, context_plyr.speaker_header(cast), line_text);
+            }
+        }
+    }
+
+    let _ = writeln!(stdout);
+    let _ = writeln!(stdout,"{} match(es) for '{}'", match_count, query.pattern);
+    Ok(match_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(pattern: &str, text: &str) -> bool {
+        glob_match(&pattern.chars().collect::<Vec<char>>(), &text.chars().collect::<Vec<char>>())
+    }
+
+    #[test]
+    fn glob_wildcards() {
+        assert!(glob("drab", "drab"));
+        assert!(!glob("drab", "drabs"));
+        assert!(glob("d?ab", "drab"));
+        assert!(!glob("d?ab", "dab"));
+        assert!(glob("*", ""));
+        assert!(glob("**", "anything"));
+        assert!(glob("re*ld?", "reynaldo"));
+        //the * has to give back characters it took too early
+        assert!(glob("*ab*ab", "abcabxab"));
+        assert!(!glob("*ab*abz", "abcabxab"));
+        assert!(!glob("?", ""));
+        assert!(glob("", ""));
+        assert!(!glob("", "a"));
+    }
+
+    #[test]
+    fn words_keep_inner_apostrophes() {
+        assert_eq!(split_words("'Twere good, o'ertook--'tis so."), vec!["Twere", "good", "o'ertook", "tis", "so"]);
+    }
+
+    #[test]
+    fn query_matching() {
+        let mut query = SearchQuery::new("Drab");
+        assert!(query.matches("Drinking, fencing, swearing, quarrelling, drabbing"));
+        query.whole_word = true;
+        assert!(!query.matches("Drinking, fencing, swearing, quarrelling, drabbing"));
+        assert!(query.matches("you must not put another scandal on him, drab."));
+        query.case_sensitive = true;
+        assert!(!query.matches("you must not put another scandal on him, drab."));
+
+        //a whole word phrase matches consecutive words whatever the punctuation between them
+        let mut query = SearchQuery::new("my lord");
+        query.whole_word = true;
+        assert!(query.matches("Well said, very well said. Look you, sir, My, lord!"));
+        assert!(!query.matches("my good lord"));
+        let mut query = SearchQuery::new("o'er*");
+        query.whole_word = true;
+        assert!(query.matches("Gis him o'ertook in's rouse"));
+    }
+}
@@ -0,0 +1,368 @@
+//ssml.rs renders a prepared Play as SSML for text to speech table reads: one document per scene, or one per character per scene in split mode, with each character's voice read from a voice file and breaks at speaker changes and stage directions. Hanson Li, Aman Verma, Johnny Huang
+
+use super::declarations::{WHINGE, GENERATION_FAILURE};
+use super::line_num::LineNum;
+use super::personae::{Persona, PERSONAE_HEADING, persona_line};
+use super::play::Play;
+use super::player::ALL_GROUP;
+use super::scene_fragments::SceneFragment;
+use super::script_gen::grab_trimmed_file_lines;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+
+//a voice file line looks like: Polonius | en-GB-RyanNeural
+pub const VOICE_DELIM: char = '|';
+pub const DEFAULT_VOICE_KEY: &str = "default"; //voice for characters the file does not list
+pub const LANG_KEY: &str = "lang";             //xml:lang of every document
+pub const DEFAULT_LANG: &str = "en-GB";
+
+pub const SSML_EXTENSION: &str = "ssml";
+pub const SPEAKER_BREAK: &str = "400ms";
+pub const DIRECTION_BREAK: &str = "1200ms";
+pub const LINE_MARK_PREFIX: &str = "line-"; //<mark name="line-2-12"/> before line 12 of fragment 2, so split voices can be lined up when mixing
+pub const SSML_INDENT: &str = "  ";
+pub const PERSONAE_TAG: &str = "personae"; //the cast list is read first, from base_name_personae.ssml
+
+//escapes the five characters XML gives a meaning to
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for a_char in text.chars() {
+        match a_char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(a_char),
+        }
+    }
+    escaped
+}
+
+#[derive(Debug, Clone)]
+pub struct VoiceMap {
+    pub voices: Vec<(String, String)>, //character key and voice name, in file order
+    pub default_voice: Option<String>,
+    pub lang: String,
+}
+
+impl VoiceMap {
+    pub fn new() -> Self {
+        Self {
+            voices: Vec::new(),
+            default_voice: None,
+            lang: DEFAULT_LANG.to_string(),
+        }
+    }
+
+    //splits one voice file line into the character and their voice
+    pub fn add_config(&mut self, voice_line: &str) {
+        let mut stderr = io::stderr().lock();
+        if voice_line.is_empty() {
+            return;
+        }
+        let Some((char_key, voice_name)) = voice_line.split_once(VOICE_DELIM).map(|(char_key, voice_name)| (char_key.trim(), voice_name.trim())) else {
+            if WHINGE.load(Ordering::SeqCst) {
+                let _ = writeln!(stderr,"Whinge Warning: voice line '{}' is missing '{}' between the character and the voice, skipping it", voice_line, VOICE_DELIM);
+            }
+            return;
+        };
+        if char_key.is_empty() || voice_name.is_empty() {
+            if WHINGE.load(Ordering::SeqCst) {
+                let _ = writeln!(stderr,"Whinge Warning: voice line '{}' has an empty character or voice, skipping it", voice_line);
+            }
+            return;
+        }
+        match char_key {
+            DEFAULT_VOICE_KEY => self.default_voice = Some(voice_name.to_string()),
+            LANG_KEY => self.lang = voice_name.to_string(),
+            _ => self.voices.push((char_key.to_string(), voice_name.to_string())),
+        }
+    }
+
+    //reads the voice file and adds each of its lines
+    pub fn prepare(&mut self, voice_fname: &String) -> Result<(), u8> {
+        let mut stderr = io::stderr().lock();
+        let mut voice_lines: Vec<String> = Vec::new();
+
+        if let Err(e_code) = grab_trimmed_file_lines(voice_fname, &mut voice_lines) {
+            let _ = writeln!(stderr,"Error: could not read voice file '{}', error code {}", voice_fname, e_code);
+            return Err(GENERATION_FAILURE);
+        }
+        for a_line in voice_lines.iter() {
+            self.add_config(a_line);
+        }
+        Ok(())
+    }
+
+    //the voice for a player's lines. A shared line uses the group's own voice if it has one, otherwise its first member's
+    pub fn voice_for(&self, char_name: &str, members: &[String]) -> Option<String> {
+        let find_voice = |a_name: &str| self.voices.iter().find(|(char_key, _)| char_key == a_name).map(|(_, voice_name)| voice_name.to_string());
+        find_voice(char_name)
+            .or_else(|| members.first().and_then(|first_member| find_voice(first_member)))
+            .or_else(|| self.default_voice.clone())
+    }
+}
+
+impl Default for VoiceMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//one SSML document, named for the file it is written to
+#[derive(Debug, Clone)]
+pub struct SsmlDocument {
+    pub file_name: String,
+    pub text: String,
+}
+
+//true if someone walks on or off between the previous line and this one, so the pause is a stage direction rather than a change of speaker
+fn direction_before(scene_ref: &SceneFragment, line_num: LineNum, prev_line_num: Option<LineNum>) -> bool {
+    scene_ref.chars_in_play.iter().filter(|a_player| a_player.char_name != ALL_GROUP).any(|a_player| {
+        a_player.enter_at == Some(line_num) || (prev_line_num.is_some() && a_player.exit_at == prev_line_num)
+    })
+}
+
+//the start of every document, up to and including the title comment
+fn document_head(voice_map: &VoiceMap, title: &str) -> Vec<String> {
+    vec![
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>".to_string(),
+        format!("<speak version=\"1.0\" xmlns=\"http://www.w3.org/2001/10/synthesis\" xml:lang=\"{}\">", escape_xml(&voice_map.lang)),
+        format!("{}<!-- {} -->", SSML_INDENT, escape_xml(title).replace("--", "- -")),
+    ]
+}
+
+//reads the cast list out in the default voice, one sentence per character, before the first scene
+fn render_personae(personae: &[Persona], voice_map: &VoiceMap) -> String {
+    let mut ssml_lines = document_head(voice_map, PERSONAE_HEADING);
+    let mut indent = SSML_INDENT.to_string();
+    if let Some(voice_name) = voice_map.default_voice.as_ref() {
+        ssml_lines.push(format!("{}<voice name=\"{}\">", SSML_INDENT, escape_xml(voice_name)));
+        indent.push_str(SSML_INDENT);
+    }
+    ssml_lines.push(format!("{}<s>{}</s>", indent, escape_xml(PERSONAE_HEADING)));
+    ssml_lines.push(format!("{}<break time=\"{}\"/>", indent, SPEAKER_BREAK));
+    for a_persona in personae.iter() {
+        ssml_lines.push(format!("{}<s>{}</s>", indent, escape_xml(&persona_line(a_persona))));
+    }
+    if voice_map.default_voice.is_some() {
+        ssml_lines.push(format!("{}</voice>", SSML_INDENT));
+    }
+    ssml_lines.push("</speak>".to_string());
+
+    let mut ssml_text = ssml_lines.join("\n");
+    ssml_text.push('\n');
+    ssml_text
+}
+
+//renders one scene. With only_character set just that character's lines are spoken, with breaks where everyone else speaks
+fn render_scene(play: &Play, scene_fragments: &[std::sync::Arc<std::sync::Mutex<SceneFragment>>], scene_title: &str,
+                voice_map: &VoiceMap, only_character: Option<&str>) -> Result<String, u8> {
+    let mut stderr = io::stderr().lock();
+    let cast = play.cast();
+    let mut ssml_lines = document_head(voice_map, scene_title);
+    let mut prev_names: Option<Vec<String>> = None;
+    let mut prev_speaker: Option<String> = None;
+    let mut open_voice = false;
+
+    for (fragment_idx, a_fragment) in scene_fragments.iter().enumerate() {
+        let scene_ref = match a_fragment.lock() {
+            Ok(scene_ref) => scene_ref,
+            Err(_) => {
+                let _ = writeln!(stderr, "Error: Failed to acquire lock on ith scene fragment");
+                return Err(GENERATION_FAILURE);
+            }
+        };
+        //a change of who is on stage between fragments is a stage direction
+        let fragment_names = scene_ref.names_in_fragment();
+        let mut pending_direction = prev_names.as_ref().is_some_and(|prev_names| *prev_names != fragment_names);
+        prev_names = Some(fragment_names);
+        let mut prev_line_num: Option<LineNum> = None;
+
+        for (line_num, player_idx, line_idx) in scene_ref.speaking_order() {
+            let plyr = &scene_ref.chars_in_play[player_idx];
+            pending_direction = pending_direction || direction_before(&scene_ref, line_num, prev_line_num);
+            prev_line_num = Some(line_num);
+            let speaks_here = only_character.is_none_or(|char_name| plyr.char_name == char_name || plyr.members.iter().any(|a_member| a_member == char_name));
+            //a new speaker, or the same one after a stage direction, starts a new block
+            let new_block = pending_direction || prev_speaker.as_deref() != Some(plyr.char_name.as_str());
+
+            if new_block {
+                if open_voice {
+                    ssml_lines.push(format!("{}</voice>", SSML_INDENT));
+                    open_voice = false;
+                }
+                if prev_speaker.is_some() {
+                    let break_time = if pending_direction { DIRECTION_BREAK } else { SPEAKER_BREAK };
+                    ssml_lines.push(format!("{}<break time=\"{}\"/>", SSML_INDENT, break_time));
+                }
+                pending_direction = false;
+            }
+            prev_speaker = Some(plyr.char_name.to_string());
+
+            if !speaks_here {
+                //the other voices' lines are left out but their place is kept with a mark
+                ssml_lines.push(format!("{}<mark name=\"{}{}-{}\"/>", SSML_INDENT, LINE_MARK_PREFIX, fragment_idx + 1, line_num));
+                continue;
+            }
+            if new_block {
+                ssml_lines.push(format!("{}<!-- {} -->", SSML_INDENT, escape_xml(&plyr.speaker_header(cast)).replace("--", "- -")));
+                //without a voice for the character the engine's own voice reads the lines
+                if let Some(voice_name) = voice_map.voice_for(&plyr.char_name, &plyr.members) {
+                    ssml_lines.push(format!("{}<voice name=\"{}\">", SSML_INDENT, escape_xml(&voice_name)));
+                    open_voice = true;
+                }
+            }
+            ssml_lines.push(format!("{}{}<mark name=\"{}{}-{}\"/><s>{}</s>", SSML_INDENT, SSML_INDENT, LINE_MARK_PREFIX, fragment_idx + 1, line_num, escape_xml(&plyr.char_lines[line_idx].1)));
+        }
+    }
+    if open_voice {
+        ssml_lines.push(format!("{}</voice>", SSML_INDENT));
+    }
+    ssml_lines.push("</speak>".to_string());
+
+    let mut ssml_text = ssml_lines.join("\n");
+    ssml_text.push('\n');
+    Ok(ssml_text)
+}
+
+//renders every scene of the play, named base_name_ii_1.ssml. In split mode there is a document per character per scene instead,
+//named base_name_ii_1_Polonius.ssml, with the same marks so the voices can be mixed back together. A cast list comes first as base_name_personae.ssml
+pub fn export_ssml(play: &Play, voice_map: &VoiceMap, base_name: &str, split: bool, personae: &[Persona]) -> Result<Vec<SsmlDocument>, u8> {
+    let mut stderr = io::stderr().lock();
+    let mut documents: Vec<SsmlDocument> = Vec::new();
+    if !personae.is_empty() {
+        documents.push(SsmlDocument {
+            file_name: format!("{}_{}.{}", base_name, PERSONAE_TAG, SSML_EXTENSION),
+            text: render_personae(personae, voice_map),
+        });
+    }
+    let mut scene_idx: usize = 0;
+
+    for an_act in play.acts().iter() {
+        for a_scene in an_act.scenes.iter() {
+            let scene_tag = a_scene.meta.file_tag(scene_idx);
+            let scene_title = a_scene.meta.short_title();
+            scene_idx += 1;
+            if !split {
+                documents.push(SsmlDocument {
+                    file_name: format!("{}_{}.{}", base_name, scene_tag, SSML_EXTENSION),
+                    text: render_scene(play, &a_scene.fragments, &scene_title, voice_map, None)?,
+                });
+                continue;
+            }
+
+            //every character who speaks in the scene, shared lines count for each member
+            let mut speakers: Vec<String> = Vec::new();
+            for a_fragment in a_scene.fragments.iter() {
+                let Ok(scene_ref) = a_fragment.lock() else {
+                    let _ = writeln!(stderr, "Error: Failed to acquire lock on ith scene fragment");
+                    return Err(GENERATION_FAILURE);
+                };
+                for a_name in scene_ref.names_in_fragment() {
+                    if !speakers.contains(&a_name) {
+                        speakers.push(a_name);
+                    }
+                }
+            }
+            for a_speaker in speakers.iter() {
+                documents.push(SsmlDocument {
+                    file_name: format!("{}_{}_{}.{}", base_name, scene_tag, a_speaker, SSML_EXTENSION),
+                    text: render_scene(play, &a_scene.fragments, &scene_title, voice_map, Some(a_speaker))?,
+                });
+            }
+        }
+    }
+    Ok(documents)
+}
+
+//writes each document into out_dir, or prints them one after another when there is no directory
+pub fn write_ssml(documents: &[SsmlDocument], out_dir: Option<&str>) -> Result<(), u8> {
+    let mut stdout = io::stdout().lock();
+    let mut stderr = io::stderr().lock();
+    let Some(dir_name) = out_dir else {
+        for a_document in documents.iter() {
+            let _ = write!(stdout, "{}", a_document.text);
+        }
+        return Ok(());
+    };
+    if let Err(e_msg) = fs::create_dir_all(dir_name) {
+        let _ = writeln!(stderr, "Error: could not create output directory {}: {}", dir_name, e_msg);
+        return Err(GENERATION_FAILURE);
+    }
+    for a_document in documents.iter() {
+        let document_path = Path::new(dir_name).join(&a_document.file_name);
+        if let Err(e_msg) = fs::write(&document_path, &a_document.text) {
+            let _ = writeln!(stderr, "Error: could not write {}: {}", document_path.display(), e_msg);
+            return Err(GENERATION_FAILURE);
+        }
+        let _ = writeln!(stdout, "Wrote {}", document_path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::player::Player;
+    use super::super::scene_meta::SceneMeta;
+
+    fn player(char_name: &str, char_lines: &[(usize, &str)]) -> Player {
+        let mut plyr = Player::new(&char_name.to_string());
+        plyr.char_lines = char_lines.iter().map(|(line_num, line_text)| (LineNum::from(*line_num), line_text.to_string())).collect();
+        plyr
+    }
+
+    fn sample_play() -> Play {
+        let mut play = Play::new();
+        play.push_scene(SceneMeta::from_title("Hamlet ACT II Scene I A room"));
+        let mut fragment = SceneFragment::new(&"Hamlet ACT II Scene I A room".to_string());
+        fragment.add_player(player("Polonius", &[(0, "Give him this money & these notes"), (2, "Farewell")]));
+        fragment.add_player(player("Reynaldo", &[(1, "I will, my lord")]));
+        play.push_fragment(fragment);
+        play
+    }
+
+    #[test]
+    fn xml_characters_are_escaped() {
+        assert_eq!(escape_xml("<Tom & \"Jerry\"'s>"), "&lt;Tom &amp; &quot;Jerry&quot;&apos;s&gt;");
+        assert_eq!(escape_xml("plain text"), "plain text");
+    }
+
+    #[test]
+    fn voices_come_from_the_file_or_the_default() {
+        let mut voice_map = VoiceMap::new();
+        for voice_line in ["Polonius | en-GB-RyanNeural", "Rosencrantz | en-GB-ThomasNeural", "default | en-GB-SoniaNeural", "lang | en-US", "no delimiter"] {
+            voice_map.add_config(voice_line);
+        }
+        assert_eq!(voice_map.lang, "en-US");
+        assert_eq!(voice_map.voice_for("Polonius", &[]), Some("en-GB-RyanNeural".to_string()));
+        assert_eq!(voice_map.voice_for("Rosencrantz+Guildenstern", &["Rosencrantz".to_string(), "Guildenstern".to_string()]), Some("en-GB-ThomasNeural".to_string()));
+        assert_eq!(voice_map.voice_for("Ophelia", &[]), Some("en-GB-SoniaNeural".to_string()));
+    }
+
+    #[test]
+    fn split_mode_gives_each_character_their_own_lines() {
+        let mut voice_map = VoiceMap::new();
+        voice_map.add_config("Polonius | en-GB-RyanNeural");
+        let whole = export_ssml(&sample_play(), &voice_map, "hamlet", false, &[]).unwrap();
+        assert_eq!(whole.len(), 1);
+        assert_eq!(whole[0].file_name, "hamlet_ii_1.ssml");
+        assert!(whole[0].text.contains("<s>Give him this money &amp; these notes</s>") && whole[0].text.contains("<s>I will, my lord</s>"));
+
+        let split = export_ssml(&sample_play(), &voice_map, "hamlet", true, &[]).unwrap();
+        let file_names: Vec<&str> = split.iter().map(|a_document| a_document.file_name.as_str()).collect();
+        assert_eq!(file_names, vec!["hamlet_ii_1_Polonius.ssml", "hamlet_ii_1_Reynaldo.ssml"]);
+        //the other voice's lines are left out but marked, so the documents line up
+        assert!(split[0].text.contains("<voice name=\"en-GB-RyanNeural\">") && !split[0].text.contains("I will, my lord"));
+        assert!(split[0].text.contains("<mark name=\"line-1-1\"/>"));
+        assert!(split[1].text.contains("<s>I will, my lord</s>") && !split[1].text.contains("<voice"));
+        assert!(split[1].text.contains("<mark name=\"line-1-0\"/>") && split[1].text.contains("<mark name=\"line-1-2\"/>"));
+        for a_document in split.iter() {
+            assert!(a_document.text.starts_with("<?xml") && a_document.text.ends_with("</speak>\n"));
+        }
+    }
+}
@@ -0,0 +1,415 @@
+//subtitles.rs writes the recital of a prepared Play as SRT or WebVTT cues for surtitles, timed from each line's word count at a words per minute rate or retimed from a CSV of measured timestamps. Johnny Huang, Aman Verma, Hanson Li
+
+use super::declarations::{GENERATION_FAILURE, WHINGE};
+use super::line_num::LineNum;
+use super::personae::{Persona, PERSONAE_HEADING, persona_line};
+use super::play::Play;
+use super::script_gen::grab_trimmed_file_lines;
+use std::io::{self, Write};
+use std::sync::atomic::Ordering;
+
+pub const SRT_FORMAT: &str = "srt";
+pub const VTT_FORMAT: &str = "vtt";
+pub const VTT_HEADER: &str = "WEBVTT";
+
+pub const DEFAULT_WPM: usize = 150;
+pub const DEFAULT_ROW_WIDTH: usize = 42;  //characters per row, the usual limit for surtitles
+pub const ROWS_PER_CUE: usize = 2;
+pub const MIN_CUE_MS: u64 = 1200;          //even a one word line stays up long enough to read
+pub const CUE_GAP_MS: u64 = 200;           //pause between one cue and the next
+pub const SPEAKER_LABEL_DELIM: &str = ": "; //"POL.: Give him this money"
+
+//a timings file line looks like: fragment,line,start,end e.g. 1,3,00:00:04.500,00:00:07.250
+pub const TIMING_DELIM: char = ',';
+
+pub type MeasuredTiming = (usize, LineNum, u64, Option<u64>); //fragment number, line number, start and end in milliseconds
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+}
+
+impl SubtitleFormat {
+    pub fn from_name(format_name: &str) -> Option<Self> {
+        match format_name {
+            SRT_FORMAT => Some(SubtitleFormat::Srt),
+            VTT_FORMAT => Some(SubtitleFormat::Vtt),
+            _ => None,
+        }
+    }
+}
+
+//one line of the play on screen
+#[derive(Debug, Clone)]
+pub struct Cue {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub voice_name: String,    //cast display name, carried by the WebVTT voice tag. Empty for the cast list
+    pub speaker_label: String, //speech prefix shown before the first row, if there is one
+    pub rows: Vec<String>,
+}
+
+//reads a timestamp as hh:mm:ss.mmm, mm:ss.mmm or plain seconds, with ',' also accepted before the milliseconds as in SRT
+pub fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let timestamp = timestamp.trim().replace(',', ".");
+    let mut total_ms: u64 = 0;
+    let time_parts: Vec<&str> = timestamp.split(':').collect();
+    if time_parts.len() > 3 {
+        return None;
+    }
+    let (seconds_part, other_parts) = time_parts.split_last()?;
+    for a_part in other_parts.iter() {
+        total_ms = (total_ms + a_part.parse::<u64>().ok()?) * 60;
+    }
+    total_ms *= 1000;
+    let (whole_seconds, fraction) = seconds_part.split_once('.').unwrap_or((seconds_part, ""));
+    if fraction.len() > 3 || !fraction.chars().all(|a_char| a_char.is_ascii_digit()) {
+        return None;
+    }
+    total_ms += whole_seconds.parse::<u64>().ok()? * 1000;
+    if !fraction.is_empty() {
+        total_ms += fraction.parse::<u64>().ok()? * 10u64.pow(3 - fraction.len() as u32);
+    }
+    Some(total_ms)
+}
+
+//a time as 00:01:02,500 for SRT or 00:01:02.500 for WebVTT
+pub fn format_timestamp(time_ms: u64, format: SubtitleFormat) -> String {
+    let millis_delim = if format == SubtitleFormat::Srt { ',' } else { '.' };
+    format!("{:02}:{:02}:{:02}{}{:03}", time_ms / 3_600_000, time_ms / 60_000 % 60, time_ms / 1000 % 60, millis_delim, time_ms % 1000)
+}
+
+//how long a line stays on screen when read at wpm words per minute
+pub fn estimate_ms(line_text: &str, wpm: usize) -> u64 {
+    let word_count = line_text.split_whitespace().count() as u64;
+    (word_count * 60_000 / wpm.max(1) as u64).max(MIN_CUE_MS)
+}
+
+//wraps text into rows of at most row_width characters, a word longer than a row gets a row to itself
+pub fn wrap_rows(line_text: &str, row_width: usize) -> Vec<String> {
+    let mut rows: Vec<String> = Vec::new();
+    let mut cur_row = String::new();
+    for a_word in line_text.split_whitespace() {
+        if !cur_row.is_empty() && cur_row.chars().count() + 1 + a_word.chars().count() > row_width {
+            rows.push(std::mem::take(&mut cur_row));
+        }
+        if !cur_row.is_empty() {
+            cur_row.push(' ');
+        }
+        cur_row.push_str(a_word);
+    }
+    if !cur_row.is_empty() {
+        rows.push(cur_row);
+    }
+    rows
+}
+
+//wraps a line into the rows of each of its cues, ROWS_PER_CUE rows to a cue. The speaker label goes before the first row of every cue,
+//so that row is label_width characters shorter
+pub fn wrap_cue_rows(line_text: &str, row_width: usize, label_width: usize) -> Vec<Vec<String>> {
+    let first_width = row_width.saturating_sub(label_width).max(1);
+    let mut cue_rows: Vec<Vec<String>> = Vec::new();
+    let mut cur_rows: Vec<String> = Vec::new();
+    let mut cur_row = String::new();
+    for a_word in line_text.split_whitespace() {
+        let cur_width = if cur_rows.is_empty() { first_width } else { row_width };
+        if !cur_row.is_empty() && cur_row.chars().count() + 1 + a_word.chars().count() > cur_width {
+            cur_rows.push(std::mem::take(&mut cur_row));
+            if cur_rows.len() == ROWS_PER_CUE {
+                cue_rows.push(std::mem::take(&mut cur_rows));
+            }
+        }
+        if !cur_row.is_empty() {
+            cur_row.push(' ');
+        }
+        cur_row.push_str(a_word);
+    }
+    if !cur_row.is_empty() {
+        cur_rows.push(cur_row);
+    }
+    if !cur_rows.is_empty() {
+        cue_rows.push(cur_rows);
+    }
+    cue_rows
+}
+
+//reads the measured timings, one per line. A first line that does not start with a number is taken as a header
+pub fn read_timings(timings_fname: &String) -> Result<Vec<MeasuredTiming>, u8> {
+    let mut stderr = io::stderr().lock();
+    let mut timing_lines: Vec<String> = Vec::new();
+    if let Err(e_code) = grab_trimmed_file_lines(timings_fname, &mut timing_lines) {
+        let _ = writeln!(stderr, "Error: could not read timings file '{}', error code {}", timings_fname, e_code);
+        return Err(GENERATION_FAILURE);
+    }
+
+    let mut timings: Vec<MeasuredTiming> = Vec::new();
+    for (line_idx, a_line) in timing_lines.iter().enumerate() {
+        if a_line.is_empty() || (line_idx == 0 && !a_line.starts_with(|a_char: char| a_char.is_ascii_digit())) {
+            continue;
+        }
+        let fields: Vec<&str> = a_line.split(TIMING_DELIM).map(|a_field| a_field.trim()).collect();
+        let parsed = match fields.as_slice() {
+            [fragment_num, line_num, start_time] | [fragment_num, line_num, start_time, ""] => {
+                fragment_num.parse::<usize>().ok().zip(LineNum::parse(line_num)).zip(parse_timestamp(start_time))
+                    .map(|((fragment_num, line_num), start_ms)| (fragment_num, line_num, start_ms, None))
+            }
+            [fragment_num, line_num, start_time, end_time] => {
+                fragment_num.parse::<usize>().ok().zip(LineNum::parse(line_num)).zip(parse_timestamp(start_time)).zip(parse_timestamp(end_time))
+                    .map(|(((fragment_num, line_num), start_ms), end_ms)| (fragment_num, line_num, start_ms, Some(end_ms)))
+            }
+            _ => None,
+        };
+        match parsed {
+            Some(a_timing) => timings.push(a_timing),
+            None => {
+                let _ = writeln!(stderr, "Error: line {} of timings file '{}' is not fragment{}line{}start[{}end]: {}",
+                    line_idx + 1, timings_fname, TIMING_DELIM, TIMING_DELIM, TIMING_DELIM, a_line);
+                return Err(GENERATION_FAILURE);
+            }
+        }
+    }
+    Ok(timings)
+}
+
+#[derive(Debug, Clone)]
+pub struct SubtitleTrack {
+    pub wpm: usize,
+    pub row_width: usize,
+    pub timings: Vec<MeasuredTiming>,
+    pub personae: Vec<Persona>, //shown before the first line when not empty
+    pub cues: Vec<Cue>,
+}
+
+impl SubtitleTrack {
+    pub fn new() -> Self {
+        Self {
+            wpm: DEFAULT_WPM,
+            row_width: DEFAULT_ROW_WIDTH,
+            timings: Vec::new(),
+            personae: Vec::new(),
+            cues: Vec::new(),
+        }
+    }
+
+    //shows the cast list from the start of the track, a heading then one character per row, in cues of two rows timed like lines.
+    //these cues have no speaker, and returns when the first line can start
+    fn build_personae(&mut self) -> u64 {
+        let mut personae_rows: Vec<String> = vec![PERSONAE_HEADING.to_string()];
+        for a_persona in self.personae.iter() {
+            personae_rows.extend(wrap_rows(&persona_line(a_persona), self.row_width));
+        }
+        let mut clock_ms: u64 = 0;
+        for some_rows in personae_rows.chunks(ROWS_PER_CUE) {
+            let end_ms = clock_ms + estimate_ms(&some_rows.join(" "), self.wpm);
+            self.cues.push(Cue {
+                start_ms: clock_ms,
+                end_ms,
+                voice_name: String::new(),
+                speaker_label: String::new(),
+                rows: some_rows.to_vec(),
+            });
+            clock_ms = end_ms + CUE_GAP_MS;
+        }
+        clock_ms
+    }
+
+    //makes the cues for every line in recital order, after the cast list if there is one. A line with a measured start begins there, otherwise it follows the previous cue.
+    //a line that needs more than two rows is split over several cues, sharing its time by word count
+    pub fn build(&mut self, play: &Play) -> Result<(), u8> {
+        let mut stderr = io::stderr().lock();
+        let cast = play.cast();
+        let mut used_timings: Vec<bool> = vec![false; self.timings.len()];
+        self.cues.clear();
+        let mut clock_ms: u64 = if self.personae.is_empty() { 0 } else { self.build_personae() };
+
+        for (fragment_idx, a_fragment) in play.fragments().iter().enumerate() {
+            let scene_ref = match a_fragment.lock() {
+                Ok(scene_ref) => scene_ref,
+                Err(_) => {
+                    let _ = writeln!(stderr, "Error: Failed to acquire lock on ith scene fragment");
+                    return Err(GENERATION_FAILURE);
+                }
+            };
+            for (line_num, player_idx, line_idx) in scene_ref.speaking_order() {
+                let plyr = &scene_ref.chars_in_play[player_idx];
+                let line_text = &plyr.char_lines[line_idx].1;
+                let measured = self.timings.iter().position(|(a_fragment_num, a_line_num, _, _)| *a_fragment_num == fragment_idx + 1 && *a_line_num == line_num);
+
+                let (start_ms, end_ms) = match measured {
+                    Some(timing_idx) => {
+                        used_timings[timing_idx] = true;
+                        let (_, _, start_ms, end_ms) = self.timings[timing_idx];
+                        (start_ms, end_ms.unwrap_or(start_ms + estimate_ms(line_text, self.wpm)))
+                    }
+                    None => (clock_ms, clock_ms + estimate_ms(line_text, self.wpm)),
+                };
+                if end_ms <= start_ms {
+                    let _ = writeln!(stderr, "Error: fragment {} line {} ends at {} before it starts at {}",
+                        fragment_idx + 1, line_num, format_timestamp(end_ms, SubtitleFormat::Srt), format_timestamp(start_ms, SubtitleFormat::Srt));
+                    return Err(GENERATION_FAILURE);
+                }
+                clock_ms = end_ms + CUE_GAP_MS;
+
+                //the speaker label takes up room on the first row of each cue
+                let speaker_label = plyr.speaker_header(cast);
+                let label_width = speaker_label.chars().count() + SPEAKER_LABEL_DELIM.len();
+                let row_chunks = wrap_cue_rows(line_text, self.row_width, label_width);
+                let total_words = line_text.split_whitespace().count().max(1) as u64;
+                let mut cue_start = start_ms;
+                let mut words_before: u64 = 0;
+                for (chunk_idx, some_rows) in row_chunks.iter().enumerate() {
+                    words_before += some_rows.iter().map(|a_row| a_row.split_whitespace().count() as u64).sum::<u64>();
+                    let cue_end = if chunk_idx + 1 == row_chunks.len() { end_ms } else { start_ms + (end_ms - start_ms) * words_before / total_words };
+                    self.cues.push(Cue {
+                        start_ms: cue_start,
+                        end_ms: cue_end,
+                        voice_name: cast.display_name(&plyr.char_name),
+                        speaker_label: speaker_label.to_string(),
+                        rows: some_rows.to_vec(),
+                    });
+                    cue_start = cue_end;
+                }
+            }
+        }
+
+        if WHINGE.load(Ordering::SeqCst) {
+            for ((fragment_num, line_num, _, _), _) in self.timings.iter().zip(used_timings).filter(|(_, was_used)| !was_used) {
+                let _ = writeln!(stderr, "Whinge Warning: measured timing for fragment {} line {} matches no line of the play", fragment_num, line_num);
+            }
+            for (cue_idx, a_pair) in self.cues.windows(2).enumerate() {
+                if a_pair[1].start_ms < a_pair[0].end_ms {
+                    let _ = writeln!(stderr, "Whinge Warning: cue {} starts before cue {} ends", cue_idx + 2, cue_idx + 1);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    //the cues as an SRT or WebVTT file
+    pub fn render(&self, format: SubtitleFormat) -> String {
+        let mut subtitle_lines: Vec<String> = Vec::new();
+        if format == SubtitleFormat::Vtt {
+            subtitle_lines.push(VTT_HEADER.to_string());
+            subtitle_lines.push(String::new());
+        }
+        for (cue_idx, a_cue) in self.cues.iter().enumerate() {
+            subtitle_lines.push((cue_idx + 1).to_string());
+            subtitle_lines.push(format!("{} --> {}", format_timestamp(a_cue.start_ms, format), format_timestamp(a_cue.end_ms, format)));
+            let mut cue_rows: Vec<String> = a_cue.rows.clone();
+            if let Some(first_row) = cue_rows.first_mut().filter(|_| !a_cue.speaker_label.is_empty()) {
+                *first_row = format!("{}{}{}", a_cue.speaker_label, SPEAKER_LABEL_DELIM, first_row);
+            }
+            match format {
+                SubtitleFormat::Srt => subtitle_lines.extend(cue_rows),
+                SubtitleFormat::Vtt if a_cue.voice_name.is_empty() => subtitle_lines.extend(cue_rows.iter().map(|a_row| escape_vtt(a_row))),
+                //one voice span runs over both rows of the cue
+                SubtitleFormat::Vtt => {
+                    let escaped_rows: Vec<String> = cue_rows.iter().map(|a_row| escape_vtt(a_row)).collect();
+                    subtitle_lines.push(format!("<v {}>{}</v>", escape_vtt(&a_cue.voice_name), escaped_rows.join("\n")));
+                }
+            }
+            subtitle_lines.push(String::new());
+        }
+        let mut subtitle_text = subtitle_lines.join("\n");
+        subtitle_text.push('\n');
+        subtitle_text
+    }
+}
+
+impl Default for SubtitleTrack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//WebVTT cue text gives '&', '<' and '>' a meaning
+fn escape_vtt(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::player::Player;
+    use super::super::scene_fragments::SceneFragment;
+    use super::super::scene_meta::SceneMeta;
+
+    fn sample_play() -> Play {
+        let mut polonius = Player::new(&"Polonius".to_string());
+        polonius.char_lines = vec![(LineNum::from(0), "Give him this money and these notes, Reynaldo".to_string()), (LineNum::from(2), "Farewell".to_string())];
+        let mut reynaldo = Player::new(&"Reynaldo".to_string());
+        reynaldo.char_lines = vec![(LineNum::from(1), "I will, my lord".to_string())];
+        let mut fragment = SceneFragment::new(&"Hamlet ACT II Scene I A room".to_string());
+        fragment.add_player(polonius);
+        fragment.add_player(reynaldo);
+        let mut play = Play::new();
+        play.push_scene(SceneMeta::from_title("Hamlet ACT II Scene I A room"));
+        play.push_fragment(fragment);
+        play
+    }
+
+    #[test]
+    fn timestamps_are_read_and_written() {
+        assert_eq!(parse_timestamp("00:01:02.500"), Some(62_500));
+        assert_eq!(parse_timestamp("01:02,5"), Some(62_500));
+        assert_eq!(parse_timestamp(" 4.25 "), Some(4_250));
+        assert_eq!(parse_timestamp("1:00:00"), Some(3_600_000));
+        assert_eq!(parse_timestamp("1:2:3:4"), None);
+        assert_eq!(parse_timestamp("4.2500"), None);
+        assert_eq!(parse_timestamp("a:04"), None);
+        assert_eq!(format_timestamp(3_723_045, SubtitleFormat::Srt), "01:02:03,045");
+        assert_eq!(format_timestamp(3_723_045, SubtitleFormat::Vtt), "01:02:03.045");
+        assert_eq!(estimate_ms("one", DEFAULT_WPM), MIN_CUE_MS);
+        assert_eq!(estimate_ms("one two three four five", 150), 2_000);
+    }
+
+    #[test]
+    fn rows_wrap_at_word_boundaries() {
+        assert_eq!(wrap_rows("Give him this money and these notes", 12), vec!["Give him", "this money", "and these", "notes"]);
+        assert_eq!(wrap_rows("Drabbing:--you may go so far", 6), vec!["Drabbing:--you", "may go", "so far"]);
+        assert!(wrap_rows("   ", 10).is_empty());
+    }
+
+    #[test]
+    fn every_cue_leaves_room_for_the_label() {
+        //a label of 5 characters leaves 7 of each cue's first row, the second row has all 12
+        let cue_rows = wrap_cue_rows("Give him this money and these notes, Reynaldo", 12, 5);
+        assert_eq!(cue_rows, vec![vec!["Give", "him this"], vec!["money", "and these"], vec!["notes,", "Reynaldo"]]);
+        for some_rows in cue_rows.iter() {
+            assert!(some_rows[0].chars().count() + 5 <= 12 && some_rows.len() <= ROWS_PER_CUE);
+        }
+    }
+
+    #[test]
+    fn measured_timings_retime_their_lines() {
+        let mut track = SubtitleTrack::new();
+        track.build(&sample_play()).unwrap();
+        let starts: Vec<u64> = track.cues.iter().map(|a_cue| a_cue.start_ms).collect();
+        assert_eq!(starts, vec![0, 3_400, 5_200]);
+
+        //a measured line starts where it was measured, and the ones after it follow on from it
+        track.timings = vec![(1, LineNum::from(1), 10_000, Some(12_500)), (2, LineNum::from(0), 0, None)];
+        track.build(&sample_play()).unwrap();
+        let times: Vec<(u64, u64)> = track.cues.iter().map(|a_cue| (a_cue.start_ms, a_cue.end_ms)).collect();
+        assert_eq!(times, vec![(0, 3_200), (10_000, 12_500), (12_700, 13_900)]);
+
+        track.timings = vec![(1, LineNum::from(2), 5_000, Some(4_000))];
+        assert_eq!(track.build(&sample_play()), Err(GENERATION_FAILURE));
+    }
+
+    #[test]
+    fn long_lines_are_split_over_cues_with_the_label_on_each() {
+        let mut track = SubtitleTrack::new();
+        track.row_width = 20;
+        track.build(&sample_play()).unwrap();
+        let polonius_cues: Vec<&Cue> = track.cues.iter().filter(|a_cue| a_cue.rows.join(" ").contains("Reynaldo") || a_cue.rows.join(" ").contains("money")).collect();
+        assert!(polonius_cues.len() > 1);
+        let rendered = track.render(SubtitleFormat::Srt);
+        for a_row in rendered.lines().filter(|a_row| !a_row.contains("-->")) {
+            assert!(a_row.chars().count() <= 20, "{} is wider than a row", a_row);
+        }
+        //the second cue of a split line starts where the first ends
+        assert_eq!(polonius_cues[1].start_ms, polonius_cues[0].end_ms);
+    }
+}
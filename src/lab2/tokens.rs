@@ -0,0 +1,128 @@
+//tokens.rs splits a script or config file line into tokens. Whitespace separates tokens, a # starting a token comments out the rest of the line,
+//double quotes keep spaces and # inside one token, and a backslash escapes the character after it. Hanson Li, Johnny Huang, Aman Verma
+
+pub const COMMENT_CHAR: char = '#';
+pub const QUOTE_CHAR: char = '"';
+pub const ESCAPE_CHAR: char = '\\';
+
+pub type LineToken = (String, usize);  //token text with quotes and escapes resolved, and the column it starts at counting from 1
+pub type TokenError = (usize, String); //column counting from 1 and what is wrong there
+
+//a backslash only escapes these, any other backslash is kept as it is so Windows paths still work unquoted
+fn is_escapable(a_char: char) -> bool {
+    a_char == COMMENT_CHAR || a_char == QUOTE_CHAR || a_char == ESCAPE_CHAR || a_char.is_whitespace()
+}
+
+//splits one line into tokens. A quote can start anywhere in a token, so ./data/"Player King".txt is one token
+pub fn split_tokens(line: &str) -> Result<Vec<LineToken>, TokenError> {
+    let mut tokens: Vec<LineToken> = Vec::new();
+    let mut cur_token: Option<LineToken> = None;
+    let mut open_quote: Option<usize> = None; //column of the quote the current token is inside of
+    let mut line_chars = line.chars().enumerate().peekable();
+
+    while let Some((char_idx, a_char)) = line_chars.next() {
+        let column = char_idx + 1;
+        if a_char.is_whitespace() && open_quote.is_none() {
+            if let Some(a_token) = cur_token.take() {
+                tokens.push(a_token);
+            }
+            continue;
+        }
+        if a_char == COMMENT_CHAR && open_quote.is_none() && cur_token.is_none() {
+            break;
+        }
+
+        let token_text = &mut cur_token.get_or_insert_with(|| (String::new(), column)).0;
+        match a_char {
+            QUOTE_CHAR => open_quote = if open_quote.is_some() { None } else { Some(column) },
+            ESCAPE_CHAR => match line_chars.peek() {
+                Some((_, next_char)) if is_escapable(*next_char) => {
+                    token_text.push(*next_char);
+                    line_chars.next();
+                }
+                None if open_quote.is_some() => return Err((column, "a \\ at the end of the line escapes nothing, so the quote is never closed".to_string())),
+                _ => token_text.push(a_char),
+            },
+            _ => token_text.push(a_char),
+        }
+    }
+
+    if let Some(quote_column) = open_quote {
+        return Err((quote_column, "this quote is never closed".to_string()));
+    }
+    if let Some(a_token) = cur_token {
+        tokens.push(a_token);
+    }
+    Ok(tokens)
+}
+
+//the token as it would have to be written in a file to read back as itself, quoted only when it needs to be
+pub fn quote_token(token_text: &str) -> String {
+    let needs_quotes = token_text.is_empty() || token_text.starts_with(COMMENT_CHAR)
+        || token_text.chars().any(|a_char| a_char == QUOTE_CHAR || a_char.is_whitespace())
+        || token_text.chars().zip(token_text.chars().skip(1)).any(|(a_char, next_char)| a_char == ESCAPE_CHAR && is_escapable(next_char));
+    if !needs_quotes {
+        return token_text.to_string();
+    }
+    let mut quoted = String::from(QUOTE_CHAR);
+    for a_char in token_text.chars() {
+        if a_char == QUOTE_CHAR || a_char == ESCAPE_CHAR {
+            quoted.push(ESCAPE_CHAR);
+        }
+        quoted.push(a_char);
+    }
+    quoted.push(QUOTE_CHAR);
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_texts(line: &str) -> Vec<String> {
+        split_tokens(line).unwrap().into_iter().map(|(token_text, _)| token_text).collect()
+    }
+
+    #[test]
+    fn whitespace_splits_and_columns_count_from_one() {
+        assert_eq!(split_tokens("Polonius  ./data/Polonius.txt").unwrap(),
+            vec![("Polonius".to_string(), 1), ("./data/Polonius.txt".to_string(), 11)]);
+        assert!(split_tokens("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn quotes_keep_spaces_and_comment_chars() {
+        assert_eq!(token_texts(r#"Player_King ./data/"Player King".txt"#), vec!["Player_King", "./data/Player King.txt"]);
+        assert_eq!(token_texts(r#""a # b" c"#), vec!["a # b", "c"]);
+        assert_eq!(token_texts(r#""""#), vec![""]);
+    }
+
+    #[test]
+    fn comments_only_start_a_token() {
+        assert_eq!(token_texts("King ./data/King.txt # the new king"), vec!["King", "./data/King.txt"]);
+        assert_eq!(token_texts("take#2"), vec!["take#2"]);
+        assert!(token_texts("# a whole line").is_empty());
+    }
+
+    #[test]
+    fn backslash_escapes_only_what_needs_it() {
+        assert_eq!(token_texts(r"Player\ King \#1 \\ \q"), vec!["Player King", "#1", r"\", r"\q"]);
+        assert_eq!(token_texts(r"C:\data\King.txt"), vec![r"C:\data\King.txt"]);
+        assert_eq!(token_texts(r#""say \"hi\"""#), vec![r#"say "hi""#]);
+    }
+
+    #[test]
+    fn unclosed_quotes_name_their_column() {
+        assert_eq!(split_tokens(r#"King "./data/King.txt"#).unwrap_err().0, 6);
+        assert_eq!(split_tokens(r#"King "./data/\"#).unwrap_err().0, 14);
+    }
+
+    #[test]
+    fn quote_token_reads_back_as_itself() {
+        for token_text in ["plain", "Player King", "#1", "", r#"say "hi""#, r"C:\data", r"a\ b", r"ends\"] {
+            let quoted = quote_token(token_text);
+            assert_eq!(token_texts(&quoted), vec![token_text.to_string()], "{} was quoted as {}", token_text, quoted);
+        }
+        assert_eq!(quote_token("plain"), "plain");
+    }
+}
@@ -0,0 +1,172 @@
+//variables.rs fills in ${NAME} in script and config file paths, so the same script can find its data wherever a machine keeps it. A name is looked up
+//in the --define options first, then the environment, then the [set NAME value] lines read so far, so [set] gives a default the other two override. Aman Verma, Hanson Li, Johnny Huang
+
+use super::declarations::GENERATION_FAILURE;
+use super::tokens::{LineToken, TokenError};
+use std::env;
+use std::sync::Mutex;
+
+pub const VARIABLE_START: &str = "${";
+pub const VARIABLE_END: char = '}';
+pub const DOLLAR_ESCAPE: &str = "$$"; //$$ is a plain $, so $${NAME} is written as it is
+pub const DEFINE_DELIM: char = '=';   //--define DATA=./data
+pub const SET_DIRECTIVE: &str = "[set"; //[set DATA ./data]
+pub const SET_END: char = ']';
+
+pub type VariableUse = (String, String, bool); //name, the value it had and whether that came from a [set] line
+
+static DEFINES: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+static SET_VALUES: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+//every variable filled in while reading the play, so the cache can tell when one would now have another value
+static USED: Mutex<Vec<VariableUse>> = Mutex::new(Vec::new());
+
+fn is_name_char(a_char: char) -> bool {
+    a_char.is_ascii_alphanumeric() || a_char == '_'
+}
+
+pub fn is_valid_name(var_name: &str) -> bool {
+    !var_name.is_empty() && var_name.chars().all(is_name_char)
+}
+
+//takes one --define value, NAME=value. Err if there is no = or the name is not letters, digits and _
+pub fn add_define(define_spec: &str) -> Result<(), u8> {
+    match define_spec.split_once(DEFINE_DELIM) {
+        Some((var_name, var_value)) if is_valid_name(var_name) => {
+            if let Ok(mut defines) = DEFINES.lock() {
+                defines.push((var_name.to_string(), var_value.to_string()));
+            }
+            Ok(())
+        }
+        _ => {
+            println!("Error: --define expects NAME=value with a name of letters, digits and _ but got '{}'", define_spec);
+            Err(GENERATION_FAILURE)
+        }
+    }
+}
+
+//a value from a [set] line, a later [set] of the same name replaces it
+pub fn set_value(var_name: &str, var_value: &str) {
+    if let Ok(mut set_values) = SET_VALUES.lock() {
+        set_values.retain(|(a_name, _)| a_name != var_name);
+        set_values.push((var_name.to_string(), var_value.to_string()));
+    }
+}
+
+//forgets the [set] values and the variables used, before a script is read
+pub fn reset_script_values() {
+    if let Ok(mut set_values) = SET_VALUES.lock() {
+        set_values.clear();
+    }
+    if let Ok(mut used) = USED.lock() {
+        used.clear();
+    }
+}
+
+pub fn variables_used() -> Vec<VariableUse> {
+    USED.lock().map(|used| used.clone()).unwrap_or_default()
+}
+
+//the value a name has outside of the script, from --define (the last one given wins) or the environment
+pub fn outside_value(var_name: &str) -> Option<String> {
+    let defined = DEFINES.lock().ok().and_then(|defines| defines.iter().rev().find(|(a_name, _)| a_name == var_name).map(|(_, a_value)| a_value.to_string()));
+    defined.or_else(|| env::var(var_name).ok())
+}
+
+fn lookup(var_name: &str) -> Option<(String, bool)> {
+    if let Some(var_value) = outside_value(var_name) {
+        return Some((var_value, false));
+    }
+    SET_VALUES.lock().ok()
+        .and_then(|set_values| set_values.iter().find(|(a_name, _)| a_name == var_name).map(|(_, a_value)| (a_value.to_string(), true)))
+}
+
+//fills in a token read from a file, Err giving the column of the problem in the line
+pub fn substitute_token(a_token: &LineToken) -> Result<String, TokenError> {
+    let (token_text, token_column) = a_token;
+    substitute(token_text).map_err(|(char_offset, problem)| (token_column + char_offset, problem))
+}
+
+//fills in every ${NAME} in text. Err gives how many characters into text the problem starts, counting from 0, and what it is
+pub fn substitute(text: &str) -> Result<String, (usize, String)> {
+    let mut filled = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut char_offset: usize = 0;
+
+    while let Some(dollar_idx) = rest.find('$') {
+        filled.push_str(&rest[..dollar_idx]);
+        char_offset += rest[..dollar_idx].chars().count();
+        rest = &rest[dollar_idx..];
+
+        if let Some(after_escape) = rest.strip_prefix(DOLLAR_ESCAPE) {
+            filled.push('$');
+            rest = after_escape;
+            char_offset += DOLLAR_ESCAPE.len();
+        } else if let Some(after_start) = rest.strip_prefix(VARIABLE_START) {
+            let Some(end_idx) = after_start.find(VARIABLE_END) else {
+                return Err((char_offset, format!("{} is never closed with {}", VARIABLE_START, VARIABLE_END)));
+            };
+            let var_name = &after_start[..end_idx];
+            if !is_valid_name(var_name) {
+                return Err((char_offset, format!("'{}' is not a variable name, names are letters, digits and _", var_name)));
+            }
+            let Some((var_value, from_set)) = lookup(var_name) else {
+                return Err((char_offset, format!("variable {} is not defined by [set], the environment or --define", var_name)));
+            };
+            if let Ok(mut used) = USED.lock() {
+                if !used.iter().any(|(a_name, _, _)| a_name == var_name) {
+                    used.push((var_name.to_string(), var_value.to_string(), from_set));
+                }
+            }
+            filled.push_str(&var_value);
+            char_offset += VARIABLE_START.len() + var_name.chars().count() + 1;
+            rest = &after_start[end_idx + 1..];
+        } else {
+            filled.push('$');
+            rest = &rest[1..];
+            char_offset += 1;
+        }
+    }
+    filled.push_str(rest);
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //the variables are shared by every test thread, so each test uses names no other test does
+
+    #[test]
+    fn dollar_dollar_is_a_plain_dollar() {
+        assert_eq!(substitute("cost$$5").unwrap(), "cost$5");
+        assert_eq!(substitute("$${NOT_FILLED_IN}").unwrap(), "${NOT_FILLED_IN}");
+        assert_eq!(substitute("a $ b $").unwrap(), "a $ b $");
+    }
+
+    #[test]
+    fn set_values_are_filled_in_and_defines_override_them() {
+        set_value("LAB2_TEST_SET_ONLY", "./set");
+        set_value("LAB2_TEST_OVERRIDDEN", "./set");
+        add_define("LAB2_TEST_OVERRIDDEN=./defined").unwrap();
+        assert_eq!(substitute("${LAB2_TEST_SET_ONLY}/King.txt").unwrap(), "./set/King.txt");
+        assert_eq!(substitute("${LAB2_TEST_OVERRIDDEN}/King.txt").unwrap(), "./defined/King.txt");
+        assert!(variables_used().contains(&("LAB2_TEST_SET_ONLY".to_string(), "./set".to_string(), true)));
+    }
+
+    #[test]
+    fn undefined_names_fail_at_their_column() {
+        let (char_offset, problem) = substitute("ab${LAB2_TEST_NEVER_DEFINED}/x").unwrap_err();
+        assert_eq!(char_offset, 2);
+        assert!(problem.contains("LAB2_TEST_NEVER_DEFINED"));
+        assert_eq!(substitute_token(&("$$${LAB2_TEST_NEVER_DEFINED}".to_string(), 5)).unwrap_err().0, 7);
+    }
+
+    #[test]
+    fn bad_names_and_unclosed_braces_fail() {
+        assert_eq!(substitute("x${not-a-name}").unwrap_err().0, 1);
+        assert_eq!(substitute("x${}").unwrap_err().0, 1);
+        assert_eq!(substitute("xyz${OPEN").unwrap_err().0, 3);
+        assert!(add_define("no_equals_sign").is_err());
+        assert!(add_define("bad name=x").is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//watch.rs keeps re-running validate or recite while the script is edited. The run itself happens in a child process that reports every file it read,
+//and those files are polled for changes so no OS specific notification is needed. Johnny Huang, Aman Verma, Hanson Li
+
+use super::cmd_line::{CmdLine, OPTION_PREFIX, OPTION_VALUE_DELIM};
+use super::declarations::{ARG_SCRIPT_IDX, GENERATION_FAILURE, WHINGE, WHINGE_TOKEN};
+use super::script_gen::files_read;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process::{self, Command};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+pub const WATCH_FLAG: &str = "watch";
+pub const INTERVAL_OPTION: &str = "interval";
+pub const DEFAULT_INTERVAL_MS: u64 = 500;
+pub const READ_LOG_ENV: &str = "LAB2_READ_LOG"; //the child writes the files it read to the file this names
+
+pub type FileStamp = Option<(u64, SystemTime)>; //size and modified time, None while the file is missing
+
+struct WatchRun {
+    stdout_text: String,
+    stderr_text: String,
+    diagnostics: Vec<String>, //warnings and errors, in the order they were printed
+    exit_code: i32,
+    files: Vec<String>,       //every file the run read
+}
+
+//writes out the files this run read when a watching parent asked for them
+pub fn write_read_log() {
+    if let Ok(log_fname) = env::var(READ_LOG_ENV) {
+        let _ = fs::write(log_fname, files_read().join("\n"));
+    }
+}
+
+//warnings go to stderr but some errors are printed with the normal output, so those are picked out of stdout too
+fn is_diagnostic(output_line: &str) -> bool {
+    output_line.starts_with("Error") || output_line.contains("Warning:")
+}
+
+//the command line again without --watch and --interval, so the child does one run
+fn child_args(cmd_line: &CmdLine) -> Vec<String> {
+    let mut args: Vec<String> = vec![cmd_line.command.to_string()];
+    args.extend(cmd_line.positionals.iter().cloned());
+    for (option_name, option_value) in cmd_line.options.iter() {
+        if option_name == WATCH_FLAG || option_name == INTERVAL_OPTION {
+            continue;
+        }
+        if option_value.is_empty() {
+            args.push(format!("{}{}", OPTION_PREFIX, option_name));
+        } else {
+            args.push(format!("{}{}{}{}", OPTION_PREFIX, option_name, OPTION_VALUE_DELIM, option_value));
+        }
+    }
+    if WHINGE.load(Ordering::SeqCst) {
+        args.push(WHINGE_TOKEN.to_string());
+    }
+    args
+}
+
+fn stamp_file(file_name: &str) -> FileStamp {
+    let file_meta = fs::metadata(file_name).ok()?;
+    Some((file_meta.len(), file_meta.modified().ok()?))
+}
+
+fn stamp_files(files: &[String]) -> Vec<FileStamp> {
+    files.iter().map(|a_file| stamp_file(a_file)).collect()
+}
+
+//runs the command once in a child process and collects what it printed and read
+fn run_child(cmd_line: &CmdLine, log_fname: &str) -> Result<WatchRun, u8> {
+    let mut stderr = io::stderr().lock();
+    let program_path = match env::current_exe() {
+        Ok(program_path) => program_path,
+        Err(e_msg) => {
+            let _ = writeln!(stderr, "Error: could not find the program to re-run: {}", e_msg);
+            return Err(GENERATION_FAILURE);
+        }
+    };
+    let _ = fs::remove_file(log_fname);
+    let child_output = match Command::new(program_path).args(child_args(cmd_line)).env(READ_LOG_ENV, log_fname).output() {
+        Ok(child_output) => child_output,
+        Err(e_msg) => {
+            let _ = writeln!(stderr, "Error: could not re-run {}: {}", cmd_line.command, e_msg);
+            return Err(GENERATION_FAILURE);
+        }
+    };
+
+    let stdout_text = String::from_utf8_lossy(&child_output.stdout).to_string();
+    let stderr_text = String::from_utf8_lossy(&child_output.stderr).to_string();
+    let mut diagnostics: Vec<String> = stdout_text.lines().filter(|a_line| is_diagnostic(a_line)).map(|a_line| a_line.to_string()).collect();
+    diagnostics.extend(stderr_text.lines().filter(|a_line| !a_line.trim().is_empty()).map(|a_line| a_line.to_string()));
+
+    //the script is watched even if the run failed before reading it
+    let mut files: Vec<String> = vec![cmd_line.positionals[ARG_SCRIPT_IDX].to_string()];
+    if let Ok(log_text) = fs::read_to_string(log_fname) {
+        for a_file in log_text.lines().filter(|a_line| !a_line.is_empty()) {
+            if !files.iter().any(|seen_file| seen_file == a_file) {
+                files.push(a_file.to_string());
+            }
+        }
+    }
+    let _ = fs::remove_file(log_fname);
+
+    Ok(WatchRun {
+        stdout_text,
+        stderr_text,
+        exit_code: child_output.status.code().unwrap_or(i32::from(GENERATION_FAILURE)),
+        diagnostics,
+        files,
+    })
+}
+
+//lines of new_lines not in old_lines. A line printed twice now but once before counts once as new
+fn missing_from(new_lines: &[String], old_lines: &[String]) -> Vec<String> {
+    let mut unmatched: Vec<&String> = old_lines.iter().collect();
+    let mut missing: Vec<String> = Vec::new();
+    for a_line in new_lines.iter() {
+        match unmatched.iter().position(|old_line| *old_line == a_line) {
+            Some(old_idx) => {
+                unmatched.remove(old_idx);
+            }
+            None => missing.push(a_line.to_string()),
+        }
+    }
+    missing
+}
+
+//runs the command, then polls every file it read every interval_ms and runs it again whenever one of them changes, is created or removed,
+//or the run reads a different set of files. Only runs until interrupted, so it only returns if the command can no longer be run
+pub fn watch(cmd_line: &CmdLine, interval_ms: u64) -> u8 {
+    let log_path = env::temp_dir().join(format!("lab2_watch_{}.log", process::id()));
+    let log_fname = log_path.display().to_string();
+
+    let mut last_run = match run_child(cmd_line, &log_fname) {
+        Ok(first_run) => first_run,
+        Err(e_code) => return e_code,
+    };
+    //the first run's output is shown in full
+    let _ = write!(io::stdout().lock(), "{}", last_run.stdout_text);
+    let _ = write!(io::stderr().lock(), "{}", last_run.stderr_text);
+    let mut stamps = stamp_files(&last_run.files);
+    let mut run_num: usize = 1;
+    println!("Watching {} file(s) every {}ms, press Ctrl-C to stop", last_run.files.len(), interval_ms);
+
+    loop {
+        thread::sleep(Duration::from_millis(interval_ms));
+        let new_stamps = stamp_files(&last_run.files);
+        let changed_files: Vec<&String> = last_run.files.iter().zip(stamps.iter().zip(new_stamps.iter()))
+            .filter(|(_, (old_stamp, new_stamp))| old_stamp != new_stamp)
+            .map(|(a_file, _)| a_file)
+            .collect();
+        if changed_files.is_empty() {
+            continue;
+        }
+
+        let mut stdout = io::stdout().lock();
+        let change_names: Vec<&str> = changed_files.iter().map(|a_file| a_file.as_str()).collect();
+        let _ = writeln!(stdout, "\nChanged: {}", change_names.join(", "));
+        let this_run = match run_child(cmd_line, &log_fname) {
+            Ok(this_run) => this_run,
+            Err(e_code) => return e_code,
+        };
+        run_num += 1;
+
+        let added_files = missing_from(&this_run.files, &last_run.files);
+        let dropped_files = missing_from(&last_run.files, &this_run.files);
+        if !added_files.is_empty() {
+            let _ = writeln!(stdout, "Now reading: {}", added_files.join(", "));
+        }
+        if !dropped_files.is_empty() {
+            let _ = writeln!(stdout, "No longer reading: {}", dropped_files.join(", "));
+        }
+        let new_diagnostics = missing_from(&this_run.diagnostics, &last_run.diagnostics);
+        let resolved_count = missing_from(&last_run.diagnostics, &this_run.diagnostics).len();
+        for a_line in new_diagnostics.iter() {
+            let _ = writeln!(stdout, "{}", a_line);
+        }
+        let output_status = if this_run.stdout_text == last_run.stdout_text { "unchanged" } else { "changed" };
+        let _ = writeln!(stdout, "Run {}: {} diagnostic(s), {} new, {} resolved, output {}, exit code {}",
+            run_num, this_run.diagnostics.len(), new_diagnostics.len(), resolved_count, output_status, this_run.exit_code);
+
+        //files that were only just read are stamped now, so the next poll compares against this run
+        stamps = stamp_files(&this_run.files);
+        last_run = this_run;
+    }
+}
@@ -2,64 +2,596 @@
 
 pub mod lab2;
 use std::env;
-use std::sync::atomic::Ordering;
 use lab2::declarations::{
-    ARGS_MAX,
-    ARGS_MIN,
+    ARG_PROGRAM_IDX,
     ARG_SCRIPT_IDX,
-    ARG_WHINGE_IDX,
+    ARG_DOUBLING_IDX,
+    ARG_QUERY_IDX,
+    ARG_NEW_SCRIPT_IDX,
+    ARG_FORMAT_IDX,
+    ARG_CONVERT_SOURCE_IDX,
+    ARG_OUT_DIR_IDX,
+    ARG_CACHE_ACTION_IDX,
+    ARG_CACHE_TARGET_IDX,
+    CACHE_POSITIONALS,
+    CHECK_FAILURE,
+    CONCORDANCE_POSITIONALS,
+    DIFF_POSITIONALS,
+    DOUBLING_POSITIONALS,
     EXIT_BAD_CMDLINE,
+    EXPORT_POSITIONALS,
     GENERATION_FAILURE,
-    WHINGE,
+    IMPORT_POSITIONALS,
+    RECITE_POSITIONALS,
+    RENUMBER_POSITIONALS,
+    SEARCH_POSITIONALS,
     SUCCESS_CODE,
-    ZERO_IDX
+    VALIDATE_POSITIONALS,
+    WHINGE,
 };
+use lab2::actors::ACTORS_FLAG;
+use lab2::cmd_line::{CmdLine, RECITE_COMMAND, DOUBLING_COMMAND, SEARCH_COMMAND, CONCORDANCE_COMMAND, DIFF_COMMAND, RENUMBER_COMMAND, IMPORT_COMMAND, EXPORT_COMMAND, CACHE_COMMAND, VALIDATE_COMMAND};
+use lab2::diff::{diff_plays, print_changes};
+use lab2::cache::clean_caches;
+use lab2::concordance::{Concordance, ConcordanceFormat, DEFAULT_TOP_WORDS};
+use lab2::doubling::DoublingPlan;
+use lab2::draft::UNCLASSIFIED_KIND;
+use lab2::excerpt::Excerpt;
+use lab2::fountain::{FOUNTAIN_EXTENSION, export_fountain, parse_fountain, read_fountain_play};
+use lab2::personae::{Persona, PersonaeOrder, print_personae};
+use lab2::gutenberg::{GUTENBERG_FORMAT, parse_gutenberg};
+use lab2::line_num::{LINE_NUMBERS_OPTION, AS_IS_NUMBERS, FLAT_NUMBERS};
 use lab2::play::Play;
+use lab2::play_json::{JSON_FORMAT, export_json, read_json_play};
+use lab2::renumber::{DuplicatePolicy, RenumberScope, Renumbering, apply_rewrites};
 use lab2::return_wrapper::ReturnWrapper;
+use lab2::script_gen::read_text_file;
+use lab2::search::{SearchQuery, search_play};
+use lab2::ssml::{SSML_EXTENSION, VoiceMap, export_ssml, write_ssml};
+use lab2::subtitles::{SRT_FORMAT, VTT_FORMAT, SubtitleFormat, SubtitleTrack, read_timings};
+use lab2::watch::{DEFAULT_INTERVAL_MS, INTERVAL_OPTION, WATCH_FLAG, watch, write_read_log};
+use std::fs;
 use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+
+const CLEAN_ACTION: &str = "clean";
+
+//options accepted by each command
+const RECITE_OPTIONS: [&str; 10] = ["personae", "scene", "fragment", "from-line", "to-line", "only", "except", "watch", "interval", "actors"];
+const DOUBLING_OPTIONS: [&str; 0] = [];
+const SEARCH_OPTIONS: [&str; 3] = ["whole-word", "case-sensitive", "context"];
+const CONCORDANCE_OPTIONS: [&str; 2] = ["format", "top"];
+const DIFF_OPTIONS: [&str; 1] = ["format"];
+const RENUMBER_OPTIONS: [&str; 6] = ["per", "duplicates", "start", "output-dir", "dry-run", "force"];
+const IMPORT_OPTIONS: [&str; 1] = ["join-lines"];
+const EXPORT_OPTIONS: [&str; 9] = ["output", "output-dir", "voices", "split", "wpm", "width", "timings", "line-numbers", "personae"];
+const CACHE_OPTIONS: [&str; 0] = [];
+const VALIDATE_OPTIONS: [&str; 2] = ["watch", "interval"];
 
 fn usage(program_name: &str) -> String {
-    format!("Wrong command line arguments. Correct usage: {program_name} <script_file_name> [whinge]\n")
+    format!("Wrong command line arguments. Correct usage:\n  \
+        {program_name} [recite] <script_file_name> [whinge] [--no-cache] [--personae first|lines] [--scene II.1] [--fragment N]\n      \
+            [--from-line N] [--to-line N] [--only name,name] [--except name,name] [--watch] [--interval ms]\n      \
+            [--actors] [--encoding auto|utf-8|latin-1|lossy] [--encoding file_name=encoding] [--define NAME=value]\n      \
+            [--continuation join|wrap]\n  \
+        {program_name} validate <script_file_name> [--watch] [--interval ms]\n  \
+        {program_name} doubling <script_file_name> <doubling_file_name> [whinge]\n  \
+        {program_name} search <script_file_name> <query> [whinge] [--whole-word] [--case-sensitive] [--context N]\n  \
+        {program_name} concordance <script_file_name> [whinge] [--format text|csv|json] [--top N]\n  \
+        {program_name} diff <old_script_file_name> <new_script_file_name> [whinge] [--format text|json]\n  \
+        {program_name} renumber <script_file_name> [whinge] [--per fragment|scene] [--duplicates sequence|share|fail] [--start N]\n      \
+            [--output-dir DIR] [--dry-run] [--force]\n  \
+        {program_name} import fountain|gutenberg <source_file_name> <output_dir> [whinge] [--join-lines]\n  \
+        {program_name} export fountain|json <script_file_name> [whinge] [--output file_name] [--line-numbers as-is|flat] [--personae first|lines]\n  \
+        {program_name} export ssml <script_file_name> [whinge] [--voices voice_file_name] [--split] [--output-dir DIR] [--line-numbers as-is|flat]\n      \
+            [--personae first|lines]\n  \
+        {program_name} export srt|vtt <script_file_name> [whinge] [--wpm N] [--width N] [--timings timings_file_name] [--output file_name]\n      \
+            [--line-numbers as-is|flat] [--personae first|lines]\n  \
+        {program_name} cache clean <script_file_name>|<directory>\n")
 }
 
-fn parse_args(script: &mut String) -> Result<(), u8> {
+fn parse_args(cmd_line: &mut CmdLine) -> Result<(), u8> {
     let mut args: Vec<String> = Vec::new();
     for arg in env::args(){
         args.push(arg);
     }
+    *cmd_line = CmdLine::new(&args[ARG_PROGRAM_IDX]);
 
-    if args.len() < ARGS_MIN || args.len() > ARGS_MAX || (args.len()==ARGS_MAX && args[ARG_WHINGE_IDX] != "whinge"){
-        print!("{}", usage(&args[ZERO_IDX]));
+    if cmd_line.parse(&args[ARG_PROGRAM_IDX + 1..]).is_err() {
+        print!("{}", usage(&cmd_line.program_name));
         return Err(EXIT_BAD_CMDLINE);
     }
-    *script = args[ARG_SCRIPT_IDX].clone();
-    if args.len() == ARGS_MAX && args[ARG_WHINGE_IDX] == "whinge" {
-        WHINGE.store(true, Ordering::SeqCst);
+
+    //each command has its own positional arguments and options
+    let (expected_positionals, known_options): (usize, &[&str]) = match cmd_line.command.as_str() {
+        DOUBLING_COMMAND => (DOUBLING_POSITIONALS, &DOUBLING_OPTIONS),
+        SEARCH_COMMAND => (SEARCH_POSITIONALS, &SEARCH_OPTIONS),
+        CONCORDANCE_COMMAND => (CONCORDANCE_POSITIONALS, &CONCORDANCE_OPTIONS),
+        DIFF_COMMAND => (DIFF_POSITIONALS, &DIFF_OPTIONS),
+        RENUMBER_COMMAND => (RENUMBER_POSITIONALS, &RENUMBER_OPTIONS),
+        IMPORT_COMMAND => (IMPORT_POSITIONALS, &IMPORT_OPTIONS),
+        EXPORT_COMMAND => (EXPORT_POSITIONALS, &EXPORT_OPTIONS),
+        CACHE_COMMAND => (CACHE_POSITIONALS, &CACHE_OPTIONS),
+        VALIDATE_COMMAND => (VALIDATE_POSITIONALS, &VALIDATE_OPTIONS),
+        _ => (RECITE_POSITIONALS, &RECITE_OPTIONS),
+    };
+    if cmd_line.positionals.len() != expected_positionals {
+        print!("{}", usage(&cmd_line.program_name));
+        return Err(EXIT_BAD_CMDLINE);
+    }
+    if let Err(e_msg) = cmd_line.check_options(known_options) {
+        println!("Error: {}", e_msg);
+        print!("{}", usage(&cmd_line.program_name));
+        return Err(EXIT_BAD_CMDLINE);
     }
     Ok(())
 }
 
-fn main() -> ReturnWrapper {
-    let mut script_fname = String::new();
+//reads a numeric option such as --fragment 3, Err if it is given but is not a number
+fn usize_option(cmd_line: &CmdLine, option_name: &str) -> Result<Option<usize>, u8> {
+    match cmd_line.option(option_name) {
+        Some(option_value) => match option_value.parse::<usize>() {
+            Ok(number) => Ok(Some(number)),
+            Err(_) => {
+                println!("Error: --{} expects a number but got '{}'", option_name, option_value);
+                Err(EXIT_BAD_CMDLINE)
+            }
+        },
+        None => Ok(None),
+    }
+}
+
+//the cast list order asked for with --personae, None when the list is not wanted
+fn personae_order(cmd_line: &CmdLine) -> Result<Option<PersonaeOrder>, u8> {
+    match cmd_line.option("personae") {
+        Some(order_name) => match PersonaeOrder::from_name(&order_name) {
+            Some(an_order) => Ok(Some(an_order)),
+            None => {
+                print!("{}", usage(&cmd_line.program_name));
+                Err(EXIT_BAD_CMDLINE)
+            }
+        },
+        None => Ok(None),
+    }
+}
+
+//builds the excerpt to recite from the recite options, with no options it covers the whole play
+fn parse_excerpt(cmd_line: &CmdLine) -> Result<Excerpt, u8> {
+    let mut excerpt = Excerpt::new();
+    excerpt.scene_label = cmd_line.option("scene");
+    excerpt.fragment_num = usize_option(cmd_line, "fragment")?;
+    excerpt.from_line = usize_option(cmd_line, "from-line")?;
+    excerpt.to_line = usize_option(cmd_line, "to-line")?;
+    if let Some(name_list) = cmd_line.option("only") {
+        excerpt.only = Excerpt::split_names(&name_list);
+    }
+    if let Some(name_list) = cmd_line.option("except") {
+        excerpt.except = Excerpt::split_names(&name_list);
+    }
+    Ok(excerpt)
+}
+
+//reads the script named on the command line into a Play
+fn prepare_play(cmd_line: &CmdLine, play_content: &mut Play) -> Result<(), u8> {
+    prepare_script(&cmd_line.positionals[ARG_SCRIPT_IDX], play_content)
+}
+
+//a .fountain file is imported straight into the Play rather than read as a script, and a .json file is loaded as exported
+fn prepare_script(script_fname: &String, play_content: &mut Play) -> Result<(), u8> {
     let mut stderr = io::stderr().lock();
 
-    if let Err(e_code) = parse_args(&mut script_fname){
-        println!("Error in main when calling parse_args with error code {}", e_code);
-        return ReturnWrapper::new(EXIT_BAD_CMDLINE)
+    if Path::new(script_fname).extension().is_some_and(|an_extension| an_extension == FOUNTAIN_EXTENSION) {
+        *play_content = read_fountain_play(script_fname)?;
+        return Ok(());
+    }
+    if Path::new(script_fname).extension().is_some_and(|an_extension| an_extension == JSON_FORMAT) {
+        *play_content = read_json_play(script_fname)?;
+        return Ok(());
+    }
+
+    if let Err(e_code) = play_content.prepare(script_fname){
+        let _ = writeln!(stderr,"Error: in main, {}", e_code);
+        return Err(GENERATION_FAILURE);
     }
+    Ok(())
+}
+
+fn run_recite(cmd_line: &CmdLine) -> u8 {
+    //the cast list is only printed when asked for
+    let personae_order = match personae_order(cmd_line) {
+        Ok(personae_order) => personae_order,
+        Err(e_code) => return e_code,
+    };
+
+    let excerpt = match parse_excerpt(cmd_line) {
+        Ok(excerpt) => excerpt,
+        Err(e_code) => {
+            print!("{}", usage(&cmd_line.program_name));
+            return e_code;
+        }
+    };
 
     let mut play_content = Play::new();
+    if let Err(e_code) = prepare_play(cmd_line, &mut play_content) {
+        return e_code;
+    }
 
-    if let Err(e_code) = play_content.prepare(&script_fname){
+    if let Some(an_order) = personae_order {
+        match play_content.dramatis_personae(an_order) {
+            Ok(personae) => print_personae(&personae),
+            Err(e_code) => return e_code,
+        }
+    }
+    if let Err(e_code) = play_content.recite(&excerpt, cmd_line.has_flag(ACTORS_FLAG)) {
+        return e_code;
+    }
+    SUCCESS_CODE
+}
 
-        let _ = writeln!(stderr,"Error: in main, {}", e_code);
-        return ReturnWrapper::new(GENERATION_FAILURE)
+//reads the play with whinge on and runs the line checks recite makes, without reciting it
+fn run_validate(cmd_line: &CmdLine) -> u8 {
+    WHINGE.store(true, Ordering::SeqCst);
+    let mut play_content = Play::new();
+    if let Err(e_code) = prepare_play(cmd_line, &mut play_content) {
+        return e_code;
+    }
+    match play_content.validate() {
+        Ok((scene_count, fragment_count, char_count, line_count)) => {
+            println!("Read {} scene(s), {} fragment(s), {} character(s) and {} line(s) from {}",
+                scene_count, fragment_count, char_count, line_count, cmd_line.positionals[ARG_SCRIPT_IDX]);
+            SUCCESS_CODE
+        }
+        Err(e_code) => e_code,
+    }
+}
+
+//re-runs recite or validate whenever a file the script reads changes
+fn run_watch(cmd_line: &CmdLine) -> u8 {
+    match usize_option(cmd_line, INTERVAL_OPTION) {
+        Ok(None) => watch(cmd_line, DEFAULT_INTERVAL_MS),
+        Ok(Some(interval_ms)) if interval_ms > 0 => watch(cmd_line, interval_ms as u64),
+        _ => {
+            print!("{}", usage(&cmd_line.program_name));
+            EXIT_BAD_CMDLINE
+        }
+    }
+}
+
+//checks a doubling plan against the play, failing with CHECK_FAILURE if any conflict or quick change is found
+fn run_doubling(cmd_line: &CmdLine) -> u8 {
+    let mut play_content = Play::new();
+    if let Err(e_code) = prepare_play(cmd_line, &mut play_content) {
+        return e_code;
+    }
+
+    let mut doubling_plan = DoublingPlan::new();
+    if let Err(e_code) = doubling_plan.prepare(&cmd_line.positionals[ARG_DOUBLING_IDX]) {
+        return e_code;
+    }
+    match doubling_plan.check(&play_content) {
+        Ok(0) => SUCCESS_CODE,
+        Ok(_) => CHECK_FAILURE,
+        Err(e_code) => e_code,
+    }
+}
+
+//searches every line of the play, * and ? in the query are wildcards
+fn run_search(cmd_line: &CmdLine) -> u8 {
+    let mut query = SearchQuery::new(&cmd_line.positionals[ARG_QUERY_IDX]);
+    query.whole_word = cmd_line.has_flag("whole-word");
+    query.case_sensitive = cmd_line.has_flag("case-sensitive");
+    match usize_option(cmd_line, "context") {
+        Ok(Some(context_lines)) => query.context_lines = context_lines,
+        Ok(None) => {},
+        Err(e_code) => return e_code,
+    }
+
+    let mut play_content = Play::new();
+    if let Err(e_code) = prepare_play(cmd_line, &mut play_content) {
+        return e_code;
+    }
+    match search_play(&play_content, &query) {
+        Ok(_) => SUCCESS_CODE,
+        Err(e_code) => e_code,
+    }
+}
+
+//prints every word of the play with its counts per character and scene, and each character's most characteristic words
+fn run_concordance(cmd_line: &CmdLine) -> u8 {
+    let format = match cmd_line.option("format") {
+        Some(format_name) => match ConcordanceFormat::from_name(&format_name) {
+            Some(a_format) => a_format,
+            None => {
+                print!("{}", usage(&cmd_line.program_name));
+                return EXIT_BAD_CMDLINE;
+            }
+        },
+        None => ConcordanceFormat::Text,
+    };
+    let top_words = match usize_option(cmd_line, "top") {
+        Ok(top_words) => top_words.unwrap_or(DEFAULT_TOP_WORDS),
+        Err(e_code) => return e_code,
+    };
+
+    let mut play_content = Play::new();
+    if let Err(e_code) = prepare_play(cmd_line, &mut play_content) {
+        return e_code;
+    }
+    match Concordance::build(&play_content) {
+        Ok(concordance) => {
+            concordance.print(format, top_words);
+            SUCCESS_CODE
+        }
+        Err(e_code) => e_code,
+    }
+}
+
+//reports what changed between two versions of a play, failing with CHECK_FAILURE if anything did
+fn run_diff(cmd_line: &CmdLine) -> u8 {
+    let as_json = match cmd_line.option("format").as_deref() {
+        None | Some("text") => false,
+        Some("json") => true,
+        Some(_) => {
+            print!("{}", usage(&cmd_line.program_name));
+            return EXIT_BAD_CMDLINE;
+        }
+    };
+
+    let mut old_play = Play::new();
+    if let Err(e_code) = prepare_play(cmd_line, &mut old_play) {
+        return e_code;
+    }
+    let mut new_play = Play::new();
+    if let Err(e_code) = prepare_script(&cmd_line.positionals[ARG_NEW_SCRIPT_IDX], &mut new_play) {
+        return e_code;
+    }
+    match diff_plays(&old_play, &new_play) {
+        Ok(changes) => {
+            print_changes(&changes, as_json);
+            if changes.is_empty() { SUCCESS_CODE } else { CHECK_FAILURE }
+        }
+        Err(e_code) => e_code,
+    }
+}
+
+//gives every line of the play a contiguous number and rewrites the part files, or shows the changes with --dry-run
+fn run_renumber(cmd_line: &CmdLine) -> u8 {
+    let mut renumbering = Renumbering::new();
+    if let Some(scope_name) = cmd_line.option("per") {
+        match RenumberScope::from_name(&scope_name) {
+            Some(a_scope) => renumbering.scope = a_scope,
+            None => {
+                print!("{}", usage(&cmd_line.program_name));
+                return EXIT_BAD_CMDLINE;
+            }
+        }
+    }
+    if let Some(policy_name) = cmd_line.option("duplicates") {
+        match DuplicatePolicy::from_name(&policy_name) {
+            Some(a_policy) => renumbering.policy = a_policy,
+            None => {
+                print!("{}", usage(&cmd_line.program_name));
+                return EXIT_BAD_CMDLINE;
+            }
+        }
+    }
+    match usize_option(cmd_line, "start") {
+        Ok(Some(first_line)) => renumbering.first_line = first_line,
+        Ok(None) => {},
+        Err(e_code) => return e_code,
+    }
+    //a dry run writes nothing, so it shows the changes even where annotations would be left behind
+    renumbering.force = cmd_line.has_flag("force") || cmd_line.has_flag("dry-run");
+
+    //a Fountain draft or JSON play has its lines in the one file rather than in part files, so there is nothing to rewrite
+    let script_fname = &cmd_line.positionals[ARG_SCRIPT_IDX];
+    if let Some(an_extension) = Path::new(script_fname).extension().and_then(|an_extension| an_extension.to_str())
+        .filter(|an_extension| *an_extension == FOUNTAIN_EXTENSION || *an_extension == JSON_FORMAT) {
+        let _ = writeln!(io::stderr().lock(), "Error: renumber only rewrites the part files of a script, {} is a .{} file without part files. Import it first, or renumber the script it was exported from",
+            script_fname, an_extension);
+        return EXIT_BAD_CMDLINE;
+    }
+
+    let mut play_content = Play::new();
+    if let Err(e_code) = prepare_play(cmd_line, &mut play_content) {
+        return e_code;
+    }
+    let rewrites = match renumbering.plan(&play_content) {
+        Ok(rewrites) => rewrites,
+        Err(e_code) => return e_code,
+    };
+    match apply_rewrites(&rewrites, cmd_line.option("output-dir").as_deref(), cmd_line.has_flag("dry-run")) {
+        Ok(_) => SUCCESS_CODE,
+        Err(e_code) => e_code,
+    }
+}
+
+//writes the output of export to --output, or to stdout
+fn write_export(cmd_line: &CmdLine, export_text: &str) -> u8 {
+    match cmd_line.option("output") {
+        Some(output_fname) => match fs::write(&output_fname, export_text) {
+            Ok(_) => SUCCESS_CODE,
+            Err(e_msg) => {
+                let _ = writeln!(io::stderr().lock(), "Error: could not write {}: {}", output_fname, e_msg);
+                GENERATION_FAILURE
+            }
+        },
+        None => {
+            print!("{}", export_text);
+            SUCCESS_CODE
+        }
+    }
+}
+
+//turns a file in another format into a script, config files and part files in the output directory
+fn run_import(cmd_line: &CmdLine) -> u8 {
+    let source_fname = &cmd_line.positionals[ARG_CONVERT_SOURCE_IDX];
+    let source_text = match read_text_file(source_fname) {
+        Ok(source_text) => source_text,
+        Err(e_code) => return e_code,
+    };
+    let draft = match cmd_line.positionals[ARG_FORMAT_IDX].as_str() {
+        FOUNTAIN_EXTENSION => parse_fountain(&source_text),
+        GUTENBERG_FORMAT => parse_gutenberg(&source_text, cmd_line.has_flag("join-lines")),
+        _ => {
+            print!("{}", usage(&cmd_line.program_name));
+            return EXIT_BAD_CMDLINE;
+        }
+    };
+
+    let base_name = Path::new(source_fname).file_stem().map(|a_stem| a_stem.to_string_lossy().to_string()).unwrap_or_default();
+    let script_fname = match draft.write_layout(&cmd_line.positionals[ARG_OUT_DIR_IDX], &base_name) {
+        Ok(script_fname) => script_fname,
+        Err(e_code) => return e_code,
+    };
+    println!("Imported {} line(s) in {} scene(s) into {}", draft.line_count(), draft.scenes.len(), script_fname);
+    if !draft.skipped.is_empty() {
+        println!("{} line(s) that are not dialogue were skipped", draft.skipped.len());
+    }
+    //lines the importer could not classify are always listed, the rest only with whinge
+    let mut stderr = io::stderr().lock();
+    for (line_num, line_kind, line_text) in draft.skipped.iter() {
+        if line_kind == UNCLASSIFIED_KIND {
+            let _ = writeln!(stderr, "Unclassified: line {} of {}: {}", line_num, source_fname, line_text);
+        } else if WHINGE.load(Ordering::SeqCst) {
+            let _ = writeln!(stderr, "Whinge Warning: line {} of {} skipped ({}): {}", line_num, source_fname, line_kind, line_text);
+        }
+    }
+    SUCCESS_CODE
+}
 
-    }else{
-        let _ = play_content.recite();
+//writes one SSML document per scene, or per character per scene with --split, into --output-dir or to stdout
+fn run_export_ssml(cmd_line: &CmdLine, play_content: &Play, personae: &[Persona]) -> u8 {
+    let mut voice_map = VoiceMap::new();
+    if let Some(voice_fname) = cmd_line.option("voices") {
+        if let Err(e_code) = voice_map.prepare(&voice_fname) {
+            return e_code;
+        }
     }
+    let script_fname = &cmd_line.positionals[ARG_CONVERT_SOURCE_IDX];
+    let file_stem = Path::new(script_fname).file_stem().map(|a_stem| a_stem.to_string_lossy().to_string()).unwrap_or_default();
+    let base_name = file_stem.strip_suffix("_script").unwrap_or(&file_stem);
+    let documents = match export_ssml(play_content, &voice_map, base_name, cmd_line.has_flag("split"), personae) {
+        Ok(documents) => documents,
+        Err(e_code) => return e_code,
+    };
+    match write_ssml(&documents, cmd_line.option("output-dir").as_deref()) {
+        Ok(_) => SUCCESS_CODE,
+        Err(e_code) => e_code,
+    }
+}
 
-    return ReturnWrapper::new(SUCCESS_CODE)
+//times a cue for every line of the recital and renders them as SRT or WebVTT
+fn export_subtitles(cmd_line: &CmdLine, play_content: &Play, personae: &[Persona]) -> Result<String, u8> {
+    let Some(format) = SubtitleFormat::from_name(&cmd_line.positionals[ARG_FORMAT_IDX]) else {
+        return Err(EXIT_BAD_CMDLINE);
+    };
+    let mut track = SubtitleTrack::new();
+    if let Some(wpm) = usize_option(cmd_line, "wpm")? {
+        track.wpm = wpm;
+    }
+    if let Some(row_width) = usize_option(cmd_line, "width")? {
+        track.row_width = row_width;
+    }
+    if let Some(timings_fname) = cmd_line.option("timings") {
+        track.timings = read_timings(&timings_fname)?;
+    }
+    track.personae = personae.to_vec();
+    track.build(play_content)?;
+    Ok(track.render(format))
 }
 
+//writes a play in another format
+fn run_export(cmd_line: &CmdLine) -> u8 {
+    let personae_order = match personae_order(cmd_line) {
+        Ok(personae_order) => personae_order,
+        Err(e_code) => return e_code,
+    };
+    let mut play_content = Play::new();
+    if let Err(e_code) = prepare_script(&cmd_line.positionals[ARG_CONVERT_SOURCE_IDX], &mut play_content) {
+        return e_code;
+    }
+    //sub-line numbers such as 12.1 are written as they are unless --line-numbers flat asks for whole numbers
+    match cmd_line.option(LINE_NUMBERS_OPTION).as_deref() {
+        None | Some(AS_IS_NUMBERS) => {}
+        Some(FLAT_NUMBERS) => {
+            if let Err(e_code) = play_content.flatten_line_numbers() {
+                return e_code;
+            }
+        }
+        Some(_) => {
+            print!("{}", usage(&cmd_line.program_name));
+            return EXIT_BAD_CMDLINE;
+        }
+    }
+    //the cast list goes at the start of whichever format is written
+    let mut personae: Vec<Persona> = Vec::new();
+    if let Some(an_order) = personae_order {
+        match play_content.dramatis_personae(an_order) {
+            Ok(some_personae) => personae = some_personae,
+            Err(e_code) => return e_code,
+        }
+    }
+    let export_text = match cmd_line.positionals[ARG_FORMAT_IDX].as_str() {
+        FOUNTAIN_EXTENSION => export_fountain(&play_content, &personae),
+        JSON_FORMAT => export_json(&play_content, &personae),
+        SSML_EXTENSION => return run_export_ssml(cmd_line, &play_content, &personae),
+        SRT_FORMAT | VTT_FORMAT => export_subtitles(cmd_line, &play_content, &personae),
+        _ => {
+            print!("{}", usage(&cmd_line.program_name));
+            return EXIT_BAD_CMDLINE;
+        }
+    };
+    match export_text {
+        Ok(export_text) => write_export(cmd_line, &export_text),
+        Err(e_code) => e_code,
+    }
+}
+
+//cache clean removes the cache of a script, or every cache under a directory
+fn run_cache(cmd_line: &CmdLine) -> u8 {
+    if cmd_line.positionals[ARG_CACHE_ACTION_IDX] != CLEAN_ACTION {
+        print!("{}", usage(&cmd_line.program_name));
+        return EXIT_BAD_CMDLINE;
+    }
+    match clean_caches(&cmd_line.positionals[ARG_CACHE_TARGET_IDX]) {
+        Ok(removed) => {
+            for cache_fname in removed.iter() {
+                println!("Removed {}", cache_fname);
+            }
+            println!("{} cache file(s) removed", removed.len());
+            SUCCESS_CODE
+        }
+        Err(e_code) => e_code,
+    }
+}
+
+fn main() -> ReturnWrapper {
+    let mut cmd_line = CmdLine::new("");
 
+    if let Err(e_code) = parse_args(&mut cmd_line){
+        println!("Error in main when calling parse_args with error code {}", e_code);
+        return ReturnWrapper::new(EXIT_BAD_CMDLINE)
+    }
+
+    if cmd_line.has_flag(WATCH_FLAG) {
+        return ReturnWrapper::new(run_watch(&cmd_line));
+    }
+    let exit_code = match cmd_line.command.as_str() {
+        DOUBLING_COMMAND => run_doubling(&cmd_line),
+        SEARCH_COMMAND => run_search(&cmd_line),
+        CONCORDANCE_COMMAND => run_concordance(&cmd_line),
+        DIFF_COMMAND => run_diff(&cmd_line),
+        RENUMBER_COMMAND => run_renumber(&cmd_line),
+        IMPORT_COMMAND => run_import(&cmd_line),
+        EXPORT_COMMAND => run_export(&cmd_line),
+        CACHE_COMMAND => run_cache(&cmd_line),
+        RECITE_COMMAND => run_recite(&cmd_line),
+        VALIDATE_COMMAND => run_validate(&cmd_line),
+        _ => EXIT_BAD_CMDLINE,
+    };
+    write_read_log();
+
+    return ReturnWrapper::new(exit_code)
+}
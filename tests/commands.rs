@@ -0,0 +1,188 @@
+//commands.rs runs the lab2 binary the way a user would, for behaviour that spans several modules such as exporting a play and reading it
+//back. Johnny Huang, Aman Verma, Hanson Li
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+//exit code, stdout and stderr of one run of lab2 in dir_name
+fn run_lab2(dir_name: &Path, args: &[&str]) -> (Option<i32>, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_lab2")).args(args).current_dir(dir_name).output().expect("lab2 should run");
+    (output.status.code(), String::from_utf8_lossy(&output.stdout).to_string(), String::from_utf8_lossy(&output.stderr).to_string())
+}
+
+fn repo_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+//an empty directory of its own for each test
+fn scratch_dir(test_name: &str) -> PathBuf {
+    let dir_name = std::env::temp_dir().join(format!("lab2_{}_{}", test_name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir_name);
+    fs::create_dir_all(&dir_name).expect("scratch directory should be created");
+    dir_name
+}
+
+fn path_text(a_path: &Path) -> &str {
+    a_path.to_str().expect("test paths are UTF-8")
+}
+
+#[test]
+fn fountain_export_imports_as_the_same_play() {
+    let scratch = scratch_dir("fountain");
+    for script_name in ["partial_hamlet_act_ii_script", "hamlet_cast_script", "presence_script"] {
+        let fountain_file = scratch.join(format!("{}.fountain", script_name));
+        let import_dir = scratch.join(script_name);
+        let (export_code, _, _) = run_lab2(repo_dir(), &["export", "fountain", &format!("data/{}.txt", script_name), "--output", path_text(&fountain_file)]);
+        assert_eq!(export_code, Some(0));
+        let (import_code, _, _) = run_lab2(repo_dir(), &["import", "fountain", path_text(&fountain_file), path_text(&import_dir)]);
+        assert_eq!(import_code, Some(0));
+
+        //import numbers every fragment from 0, so renumbered lines are the only change allowed. A cast display name in a cue would show
+        //up here as characters removed and added
+        let imported_script = import_dir.join(format!("{}_script.txt", script_name));
+        let (_, diff_out, _) = run_lab2(repo_dir(), &["diff", &format!("data/{}.txt", script_name), path_text(&imported_script), "--no-cache"]);
+        let other_changes: Vec<&str> = diff_out.lines().filter(|a_line| !a_line.contains("renumbered to") && !a_line.ends_with("change(s)")).collect();
+        assert!(other_changes.is_empty(), "{} changed on the way through Fountain: {:?}", script_name, other_changes);
+    }
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn json_export_loads_as_the_same_play() {
+    let scratch = scratch_dir("json");
+    for script_name in ["partial_hamlet_act_ii_script", "hamlet_cast_script", "presence_script", "sublines_script", "continuation_script"] {
+        let script_file = format!("data/{}.txt", script_name);
+        let json_file = scratch.join(format!("{}.json", script_name));
+        let (export_code, _, _) = run_lab2(repo_dir(), &["export", "json", &script_file, "--output", path_text(&json_file)]);
+        assert_eq!(export_code, Some(0));
+
+        assert_eq!(run_lab2(repo_dir(), &[path_text(&json_file), "--no-cache"]), run_lab2(repo_dir(), &[&script_file, "--no-cache"]),
+            "{} recites differently once exported", script_name);
+        let (_, json_again, _) = run_lab2(repo_dir(), &["export", "json", path_text(&json_file)]);
+        assert_eq!(json_again, fs::read_to_string(&json_file).unwrap(), "{} exports differently once loaded", script_name);
+    }
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn cache_is_read_again_when_a_file_changes() {
+    let scratch = scratch_dir("cache_content");
+    fs::write(scratch.join("script.txt"), "[scene] The play scene\n./config.txt\n").unwrap();
+    fs::write(scratch.join("config.txt"), "Hamlet ./Hamlet.txt\n").unwrap();
+    fs::write(scratch.join("Hamlet.txt"), "0 The play's the thing\n").unwrap();
+
+    let (_, first_out, _) = run_lab2(&scratch, &["script.txt"]);
+    assert!(first_out.contains("The play's the thing"));
+    assert!(scratch.join("script.txt.lab2cache").exists());
+    assert_eq!(run_lab2(&scratch, &["script.txt"]).1, first_out);
+
+    //same size and modification time, so only the content hash can tell
+    let part_file = scratch.join("Hamlet.txt");
+    let modified = fs::metadata(&part_file).unwrap().modified().unwrap();
+    fs::write(&part_file, "0 The PLAY's the thing\n").unwrap();
+    fs::File::options().write(true).open(&part_file).unwrap().set_modified(modified).unwrap();
+    assert!(run_lab2(&scratch, &["script.txt"]).1.contains("The PLAY's the thing"));
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn cache_is_read_again_when_a_define_changes() {
+    let scratch = scratch_dir("cache_define");
+    fs::create_dir_all(scratch.join("a")).unwrap();
+    fs::create_dir_all(scratch.join("b")).unwrap();
+    fs::write(scratch.join("script.txt"), "[set PARTS ./a]\n[scene] The play scene\n./config.txt\n").unwrap();
+    fs::write(scratch.join("config.txt"), "Hamlet ${PARTS}/Hamlet.txt\n").unwrap();
+    fs::write(scratch.join("a/Hamlet.txt"), "0 The play's the thing\n").unwrap();
+    fs::write(scratch.join("b/Hamlet.txt"), "0 Wherein I'll catch the conscience of the king\n").unwrap();
+
+    assert!(run_lab2(&scratch, &["script.txt"]).1.contains("The play's the thing"));
+    assert!(scratch.join("script.txt.lab2cache").exists());
+    assert!(run_lab2(&scratch, &["script.txt", "--define", "PARTS=./b"]).1.contains("Wherein I'll catch"));
+    assert!(run_lab2(&scratch, &["script.txt"]).1.contains("The play's the thing"));
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+//every script, JSON play and Fountain draft under data
+fn fixtures() -> Vec<String> {
+    let mut fixture_names: Vec<String> = fs::read_dir(repo_dir().join("data")).expect("data should be readable")
+        .filter_map(|an_entry| an_entry.ok().map(|an_entry| an_entry.file_name().to_string_lossy().to_string()))
+        .filter(|a_name| a_name.ends_with("_script.txt") || a_name.ends_with(".json") || a_name.ends_with(".fountain"))
+        .map(|a_name| format!("data/{}", a_name))
+        .collect();
+    fixture_names.sort();
+    fixture_names
+}
+
+#[test]
+fn actor_threads_recite_like_recite() {
+    for fixture_name in fixtures() {
+        for extra_args in [&[][..], &["whinge"][..], &["--from-line", "10", "--to-line", "40"][..]] {
+            let mut recite_args = vec![fixture_name.as_str(), "--no-cache"];
+            recite_args.extend(extra_args);
+            let sequential = run_lab2(repo_dir(), &recite_args);
+            recite_args.push("--actors");
+            assert_eq!(run_lab2(repo_dir(), &recite_args), sequential, "{:?} differs with --actors", recite_args);
+        }
+    }
+}
+
+#[test]
+fn every_export_lists_the_personae_when_asked() {
+    let scratch = scratch_dir("personae");
+    let (_, recital, _) = run_lab2(repo_dir(), &["data/hamlet_cast_script.txt", "--no-cache"]);
+    //JSON has the list as fields rather than as text
+    for (format_name, heading, reynaldo) in [("fountain", "Dramatis Personae", "Reynaldo, servant to Polonius (15 lines)"),
+                                             ("json", "\"personae\": [", "\"display_name\": \"Reynaldo, servant to Polonius\""),
+                                             ("ssml", "Dramatis Personae", "Reynaldo, servant to Polonius (15 lines)"),
+                                             ("srt", "Dramatis Personae", "Reynaldo, servant to Polonius"),
+                                             ("vtt", "Dramatis Personae", "Reynaldo, servant to Polonius")] {
+        let (export_code, plain_out, _) = run_lab2(repo_dir(), &["export", format_name, "data/hamlet_cast_script.txt"]);
+        assert_eq!(export_code, Some(0));
+        assert!(!plain_out.contains(heading), "export {} lists the personae without being asked", format_name);
+        let (export_code, personae_out, export_err) = run_lab2(repo_dir(), &["export", format_name, "data/hamlet_cast_script.txt", "--personae", "first"]);
+        assert_eq!(export_code, Some(0), "export {} --personae failed: {}", format_name, export_err);
+        assert!(personae_out.contains(heading) && personae_out.contains(reynaldo), "export {} does not list the personae", format_name);
+    }
+
+    //the cast list is only for readers, so the play reads back the same with it
+    let json_file = scratch.join("hamlet_cast.json");
+    let (export_code, _, _) = run_lab2(repo_dir(), &["export", "json", "data/hamlet_cast_script.txt", "--personae", "lines", "--output", path_text(&json_file)]);
+    assert_eq!(export_code, Some(0));
+    assert_eq!(run_lab2(repo_dir(), &[path_text(&json_file), "--no-cache"]).1, recital);
+    let fountain_file = scratch.join("hamlet_cast.fountain");
+    let (export_code, _, _) = run_lab2(repo_dir(), &["export", "fountain", "data/hamlet_cast_script.txt", "--personae", "first", "--output", path_text(&fountain_file)]);
+    assert_eq!(export_code, Some(0));
+    let (import_code, _, _) = run_lab2(repo_dir(), &["import", "fountain", path_text(&fountain_file), path_text(&scratch.join("imported"))]);
+    assert_eq!(import_code, Some(0));
+    let (_, imported_recital, _) = run_lab2(repo_dir(), &[path_text(&scratch.join("imported").join("hamlet_cast_script.txt")), "--no-cache"]);
+    assert!(!imported_recital.contains("Dramatis Personae") && !imported_recital.is_empty());
+    let _ = fs::remove_dir_all(&scratch);
+}
+
+#[test]
+fn renumber_writes_nothing_it_cannot_write_safely() {
+    let scratch = scratch_dir("renumber");
+    fs::write(scratch.join("acts_script.txt"), "[scene] ACT I Scene I One\n./one/config.txt\n[scene] ACT I Scene II Two\n./two/config.txt\n").unwrap();
+    for act_dir in ["one", "two"] {
+        fs::create_dir_all(scratch.join(act_dir)).unwrap();
+        fs::write(scratch.join(act_dir).join("config.txt"), format!("Polonius ./{}/Polonius.txt\n", act_dir)).unwrap();
+        fs::write(scratch.join(act_dir).join("Polonius.txt"), "3 Give him this money\n5 Farewell\n").unwrap();
+    }
+
+    //both part files are called Polonius.txt, so they cannot share an output directory
+    let (renumber_code, _, renumber_err) = run_lab2(&scratch, &["renumber", "acts_script.txt", "--output-dir", "out", "--no-cache"]);
+    assert_ne!(renumber_code, Some(0));
+    assert!(renumber_err.contains("would both be written to"), "{}", renumber_err);
+    assert!(!scratch.join("out").join("Polonius.txt").exists());
+    let (renumber_code, _, _) = run_lab2(&scratch, &["renumber", "acts_script.txt", "--no-cache"]);
+    assert_eq!(renumber_code, Some(0));
+    assert_eq!(fs::read_to_string(scratch.join("two").join("Polonius.txt")).unwrap(), "0 Give him this money\n1 Farewell\n");
+
+    for other_play in ["data/hamlet_draft.fountain", "data/presence_play.json"] {
+        let (renumber_code, _, renumber_err) = run_lab2(repo_dir(), &["renumber", other_play, "--dry-run"]);
+        assert_eq!(renumber_code, Some(1));
+        assert!(renumber_err.contains("renumber only rewrites the part files of a script"), "{}", renumber_err);
+    }
+    let _ = fs::remove_dir_all(&scratch);
+}